@@ -0,0 +1,11 @@
+DROP TABLE worker_auth;
+DROP TABLE worker;
+DROP TABLE worker_role;
+DROP TABLE stats;
+DROP TABLE dish_to_order;
+DROP TABLE orders;
+DROP TABLE dish_to_product;
+DROP TABLE products;
+DROP TABLE dishes;
+DROP TABLE tables;
+DROP TABLE waiters;
@@ -0,0 +1,119 @@
+-- tables that existed before migrations were kept in the repo, IF NOT EXISTS lets
+-- databases created by hand be brought under migrations without changes
+
+CREATE TABLE IF NOT EXISTS waiters
+(
+    id         BIGSERIAL PRIMARY KEY,
+    first_name VARCHAR(40) NOT NULL,
+    last_name  VARCHAR(40) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tables
+(
+    id          BIGSERIAL PRIMARY KEY,
+    seat_count  INTEGER NOT NULL CHECK (seat_count > 0),
+    is_occupied BOOLEAN NOT NULL DEFAULT FALSE,
+    reserved_at TIMESTAMPTZ,
+    reserved_by VARCHAR(50),
+    waiter_id   BIGINT REFERENCES waiters (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS tables_waiter_id_idx ON tables (waiter_id);
+
+CREATE TABLE IF NOT EXISTS dishes
+(
+    id                 BIGSERIAL PRIMARY KEY,
+    name               VARCHAR(255) NOT NULL,
+    type               TEXT         NOT NULL,
+    portion_weight_g   INTEGER      NOT NULL CHECK (portion_weight_g > 0),
+    price              INTEGER      NOT NULL CHECK (price > 0),
+    approx_cook_time_s INTEGER      NOT NULL CHECK (approx_cook_time_s > 0)
+);
+
+-- stock may go below zero, when more is sold than was accounted
+CREATE TABLE IF NOT EXISTS products
+(
+    id         BIGSERIAL PRIMARY KEY,
+    name       VARCHAR(50) NOT NULL,
+    in_stock_g INTEGER     NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS dish_to_product
+(
+    id         BIGSERIAL PRIMARY KEY,
+    dish_id    BIGINT  NOT NULL REFERENCES dishes (id) ON DELETE CASCADE,
+    product_id BIGINT  NOT NULL REFERENCES products (id),
+    weight_g   INTEGER NOT NULL CHECK (weight_g > 0)
+);
+
+CREATE INDEX IF NOT EXISTS dish_to_product_dish_id_idx ON dish_to_product (dish_id);
+CREATE INDEX IF NOT EXISTS dish_to_product_product_id_idx ON dish_to_product (product_id);
+
+CREATE TABLE IF NOT EXISTS orders
+(
+    id           BIGSERIAL PRIMARY KEY,
+    table_id     BIGINT      NOT NULL REFERENCES tables (id),
+    total_cost   INTEGER     NOT NULL DEFAULT 0 CHECK (total_cost >= 0),
+    is_confirmed BOOLEAN     NOT NULL DEFAULT FALSE,
+    is_paid      BOOLEAN     NOT NULL DEFAULT FALSE,
+    is_cooked    BOOLEAN     NOT NULL DEFAULT FALSE,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    cooked_at    TIMESTAMPTZ,
+    confirmed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS orders_table_id_idx ON orders (table_id);
+CREATE INDEX IF NOT EXISTS orders_created_at_idx ON orders (created_at DESC);
+
+CREATE TABLE IF NOT EXISTS dish_to_order
+(
+    id       BIGSERIAL PRIMARY KEY,
+    dish_id  BIGINT  NOT NULL REFERENCES dishes (id),
+    order_id BIGINT  NOT NULL REFERENCES orders (id) ON DELETE CASCADE,
+    count    INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS dish_to_order_dish_id_idx ON dish_to_order (dish_id);
+
+CREATE TABLE IF NOT EXISTS stats
+(
+    id     BIGSERIAL PRIMARY KEY,
+    day    DATE    NOT NULL,
+    income INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS worker_role
+(
+    id         SERIAL PRIMARY KEY,
+    title      VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS worker
+(
+    id         SERIAL PRIMARY KEY,
+    first_name VARCHAR(255) NOT NULL,
+    last_name  VARCHAR(255) NOT NULL,
+    role_id    INTEGER      NOT NULL REFERENCES worker_role (id),
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS worker_role_id_idx ON worker (role_id);
+
+CREATE TABLE IF NOT EXISTS worker_auth
+(
+    id         SERIAL PRIMARY KEY,
+    email      VARCHAR(255) NOT NULL,
+    password   VARCHAR(255) NOT NULL,
+    token      VARCHAR(255),
+    worker_id  INTEGER      NOT NULL REFERENCES worker (id),
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS worker_auth_worker_id_idx ON worker_auth (worker_id);
+-- soft deleted accounts free their email
+CREATE UNIQUE INDEX IF NOT EXISTS worker_auth_email_unique ON worker_auth (email)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE dishes
+    DROP COLUMN image_url,
+    DROP COLUMN description;
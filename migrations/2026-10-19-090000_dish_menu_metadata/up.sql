@@ -0,0 +1,3 @@
+ALTER TABLE dishes
+    ADD COLUMN description TEXT,
+    ADD COLUMN image_url   VARCHAR(255);
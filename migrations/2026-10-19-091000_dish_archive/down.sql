@@ -0,0 +1,4 @@
+DROP INDEX dishes_active_name_unique;
+
+ALTER TABLE dishes
+    DROP COLUMN archived_at;
@@ -0,0 +1,11 @@
+ALTER TABLE dishes
+    ADD COLUMN archived_at TIMESTAMPTZ;
+
+-- active dishes that got one name by concurrent creates are told apart by their id
+UPDATE dishes AS dup
+SET name = LEFT(dup.name, 255 - LENGTH(' #' || dup.id)) || ' #' || dup.id
+FROM dishes AS kept
+WHERE dup.name = kept.name
+  AND dup.id > kept.id;
+
+CREATE UNIQUE INDEX dishes_active_name_unique ON dishes (name) WHERE archived_at IS NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE dishes
+    DROP COLUMN dietary_labels;
+
+ALTER TABLE products
+    DROP COLUMN allergens;
@@ -0,0 +1,5 @@
+ALTER TABLE products
+    ADD COLUMN allergens TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE dishes
+    ADD COLUMN dietary_labels TEXT[] NOT NULL DEFAULT '{}';
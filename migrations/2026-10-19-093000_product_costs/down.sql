@@ -0,0 +1,4 @@
+DROP TABLE product_costs;
+
+ALTER TABLE products
+    DROP COLUMN cost_per_kg;
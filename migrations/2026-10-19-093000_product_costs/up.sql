@@ -0,0 +1,12 @@
+ALTER TABLE products
+    ADD COLUMN cost_per_kg INTEGER NOT NULL DEFAULT 0 CHECK (cost_per_kg >= 0);
+
+CREATE TABLE product_costs
+(
+    id          BIGSERIAL PRIMARY KEY,
+    product_id  BIGINT      NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+    cost_per_kg INTEGER     NOT NULL CHECK (cost_per_kg >= 0),
+    valid_from  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX product_costs_product_id_valid_from_idx ON product_costs (product_id, valid_from DESC);
@@ -0,0 +1,9 @@
+DROP TABLE order_line_modifiers;
+
+ALTER TABLE dish_to_order
+    DROP COLUMN line_key,
+    DROP COLUMN note;
+
+DROP TABLE modifier_products;
+DROP TABLE modifiers;
+DROP TABLE modifier_groups;
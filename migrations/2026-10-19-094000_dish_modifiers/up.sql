@@ -0,0 +1,45 @@
+CREATE TABLE modifier_groups
+(
+    id          BIGSERIAL PRIMARY KEY,
+    dish_id     BIGINT       NOT NULL REFERENCES dishes (id) ON DELETE CASCADE,
+    name        VARCHAR(100) NOT NULL,
+    is_multiple BOOLEAN      NOT NULL DEFAULT FALSE,
+    is_required BOOLEAN      NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX modifier_groups_dish_id_idx ON modifier_groups (dish_id);
+
+CREATE TABLE modifiers
+(
+    id          BIGSERIAL PRIMARY KEY,
+    group_id    BIGINT       NOT NULL REFERENCES modifier_groups (id) ON DELETE CASCADE,
+    name        VARCHAR(100) NOT NULL,
+    price_delta INTEGER      NOT NULL DEFAULT 0
+);
+
+CREATE INDEX modifiers_group_id_idx ON modifiers (group_id);
+
+-- negative weight removes the product from the dish ("no onions"), positive adds extra
+CREATE TABLE modifier_products
+(
+    id             BIGSERIAL PRIMARY KEY,
+    modifier_id    BIGINT  NOT NULL REFERENCES modifiers (id) ON DELETE CASCADE,
+    product_id     BIGINT  NOT NULL REFERENCES products (id),
+    weight_delta_g INTEGER NOT NULL
+);
+
+CREATE INDEX modifier_products_modifier_id_idx ON modifier_products (modifier_id);
+
+-- line_key identifies lines that may be merged: same dish, modifiers and note
+ALTER TABLE dish_to_order
+    ADD COLUMN note     TEXT,
+    ADD COLUMN line_key TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE order_line_modifiers
+(
+    id          BIGSERIAL PRIMARY KEY,
+    line_id     BIGINT NOT NULL REFERENCES dish_to_order (id) ON DELETE CASCADE,
+    modifier_id BIGINT NOT NULL REFERENCES modifiers (id)
+);
+
+CREATE INDEX order_line_modifiers_line_id_idx ON order_line_modifiers (line_id);
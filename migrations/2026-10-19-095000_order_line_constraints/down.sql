@@ -0,0 +1,6 @@
+ALTER TABLE stats
+    DROP CONSTRAINT stats_day_unique;
+
+ALTER TABLE dish_to_order
+    DROP CONSTRAINT dish_to_order_count_positive,
+    DROP CONSTRAINT dish_to_order_line_unique;
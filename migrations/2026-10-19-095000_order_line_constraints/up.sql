@@ -0,0 +1,42 @@
+-- merge lines that were duplicated by concurrent additions before the constraint existed
+UPDATE dish_to_order AS kept
+SET count = merged.total
+FROM (SELECT MIN(id) AS id, SUM(count) AS total
+      FROM dish_to_order
+      GROUP BY order_id, dish_id, line_key
+      HAVING COUNT(*) > 1) AS merged
+WHERE kept.id = merged.id;
+
+DELETE
+FROM dish_to_order AS dup
+    USING dish_to_order AS kept
+WHERE dup.order_id = kept.order_id
+  AND dup.dish_id = kept.dish_id
+  AND dup.line_key = kept.line_key
+  AND dup.id > kept.id;
+
+DELETE
+FROM dish_to_order
+WHERE count <= 0;
+
+ALTER TABLE dish_to_order
+    ADD CONSTRAINT dish_to_order_line_unique UNIQUE (order_id, dish_id, line_key),
+    ADD CONSTRAINT dish_to_order_count_positive CHECK (count > 0);
+
+-- same for daily stats, which were read and then inserted or updated
+UPDATE stats AS kept
+SET income = merged.total
+FROM (SELECT MIN(id) AS id, SUM(income) AS total
+      FROM stats
+      GROUP BY day
+      HAVING COUNT(*) > 1) AS merged
+WHERE kept.id = merged.id;
+
+DELETE
+FROM stats AS dup
+    USING stats AS kept
+WHERE dup.day = kept.day
+  AND dup.id > kept.id;
+
+ALTER TABLE stats
+    ADD CONSTRAINT stats_day_unique UNIQUE (day);
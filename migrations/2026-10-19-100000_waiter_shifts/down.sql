@@ -0,0 +1,5 @@
+ALTER TABLE orders
+    DROP COLUMN paid_at,
+    DROP COLUMN waiter_id;
+
+DROP TABLE shifts;
@@ -0,0 +1,35 @@
+CREATE TABLE shifts
+(
+    id         BIGSERIAL PRIMARY KEY,
+    waiter_id  BIGINT      NOT NULL REFERENCES waiters (id),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ended_at   TIMESTAMPTZ,
+    CONSTRAINT shifts_end_after_start CHECK (ended_at IS NULL OR ended_at >= started_at)
+);
+
+CREATE INDEX shifts_waiter_id_started_at_idx ON shifts (waiter_id, started_at DESC);
+-- a waiter is clocked in at most once at a time
+CREATE UNIQUE INDEX shifts_one_open_per_waiter_idx ON shifts (waiter_id) WHERE ended_at IS NULL;
+
+-- waiter serving the order, follows the table when it is handed over until the order is paid
+ALTER TABLE orders
+    ADD COLUMN waiter_id BIGINT REFERENCES waiters (id);
+
+CREATE INDEX orders_waiter_id_idx ON orders (waiter_id);
+
+UPDATE orders
+SET waiter_id = tables.waiter_id
+FROM tables
+WHERE orders.table_id = tables.id
+  AND NOT orders.is_paid;
+
+-- when the order was paid, shifts count orders by it. Orders paid before take the latest
+-- known step of the order
+ALTER TABLE orders
+    ADD COLUMN paid_at TIMESTAMPTZ;
+
+UPDATE orders
+SET paid_at = COALESCE(cooked_at, confirmed_at, created_at)
+WHERE is_paid;
+
+CREATE INDEX orders_paid_at_idx ON orders (paid_at);
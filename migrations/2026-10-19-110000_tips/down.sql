@@ -0,0 +1,4 @@
+DROP TABLE tips;
+
+ALTER TABLE waiters
+    DROP COLUMN worker_id;
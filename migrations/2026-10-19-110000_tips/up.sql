@@ -0,0 +1,16 @@
+-- staff record of the waiter, its role decides the share of pooled tips
+ALTER TABLE waiters
+    ADD COLUMN worker_id INTEGER UNIQUE REFERENCES worker (id);
+
+-- gratuity is kept apart from stats.income, which is the revenue of the restaurant
+CREATE TABLE tips
+(
+    id         BIGSERIAL PRIMARY KEY,
+    order_id   BIGINT      NOT NULL UNIQUE REFERENCES orders (id),
+    waiter_id  BIGINT REFERENCES waiters (id),
+    amount     INTEGER     NOT NULL CHECK (amount > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX tips_created_at_idx ON tips (created_at);
+CREATE INDEX tips_waiter_id_idx ON tips (waiter_id);
@@ -0,0 +1,15 @@
+-- append-only trail of changes, rows are never updated or deleted by the service
+CREATE TABLE audit_log
+(
+    id         BIGSERIAL PRIMARY KEY,
+    -- worker who made the change, unknown for changes made before workers were tracked
+    worker_id  INTEGER REFERENCES worker (id),
+    entity     VARCHAR(64) NOT NULL,
+    entity_id  BIGINT      NOT NULL,
+    action     VARCHAR(64) NOT NULL,
+    diff       JSONB       NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_entity_idx ON audit_log (entity, entity_id);
+CREATE INDEX audit_log_created_at_idx ON audit_log (created_at);
@@ -0,0 +1,12 @@
+DROP TRIGGER audit_log_append_only ON audit_log;
+DROP FUNCTION audit_log_append_only();
+
+DROP INDEX audit_log_worker_id_idx;
+
+-- entries of menus have no numeric id
+DELETE
+FROM audit_log
+WHERE entity_id !~ '^-?[0-9]+$';
+
+ALTER TABLE audit_log
+    ALTER COLUMN entity_id TYPE BIGINT USING entity_id::bigint;
@@ -0,0 +1,18 @@
+-- menus are kept in redis and identified by their date, so ids of entities are text
+ALTER TABLE audit_log
+    ALTER COLUMN entity_id TYPE VARCHAR(64) USING entity_id::text;
+
+CREATE INDEX audit_log_worker_id_idx ON audit_log (worker_id);
+
+CREATE FUNCTION audit_log_append_only() RETURNS trigger AS
+$$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE
+    ON audit_log
+    FOR EACH ROW
+EXECUTE FUNCTION audit_log_append_only();
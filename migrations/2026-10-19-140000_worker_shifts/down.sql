@@ -0,0 +1,8 @@
+-- shifts of workers who are not waiters can't be kept
+DELETE
+FROM shifts
+WHERE waiter_id IS NULL;
+
+ALTER TABLE shifts
+    DROP COLUMN worker_id,
+    ALTER COLUMN waiter_id SET NOT NULL;
@@ -0,0 +1,15 @@
+-- any worker clocks in, waiters without a staff record still clock in by their own id.
+-- A shift of a waiter with a staff record keeps both ids
+ALTER TABLE shifts
+    ALTER COLUMN waiter_id DROP NOT NULL,
+    ADD COLUMN worker_id INTEGER REFERENCES worker (id),
+    ADD CONSTRAINT shifts_of_waiter_or_worker CHECK (waiter_id IS NOT NULL OR worker_id IS NOT NULL);
+
+UPDATE shifts
+SET worker_id = waiters.worker_id
+FROM waiters
+WHERE shifts.waiter_id = waiters.id;
+
+CREATE INDEX shifts_worker_id_started_at_idx ON shifts (worker_id, started_at DESC);
+-- a worker is clocked in at most once at a time, whichever id they clock in by
+CREATE UNIQUE INDEX shifts_one_open_per_worker_idx ON shifts (worker_id) WHERE ended_at IS NULL;
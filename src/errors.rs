@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+
+use actix::MailboxError;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use redis::RedisError;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::types::FieldError;
+
+/// Error of any route. Responds with the matching status and a body like
+/// `{"code": "order_confirmed", "message": "...", "details": [...]}`, where `code` is stable
+/// and may be matched by clients, while `message` is meant for humans only
+#[derive(Debug)]
+pub enum ApiError {
+    /// 400, the request can't be parsed
+    BadRequest(String),
+    /// 404, the referenced record does not exist
+    NotFound(String),
+    /// 409, the request contradicts the current state, e.g. the order is already confirmed
+    Conflict { code: &'static str, message: String },
+    /// 422, `details` list every problem of the request
+    Validation(Vec<FieldError>),
+    /// 422, the idempotency key was already sent with another method, path or body
+    IdempotencyKeyReused(String),
+    /// 429, the client sent too many requests and may repeat it in `retry_after_s` seconds
+    TooManyRequests { message: String, retry_after_s: u64 },
+    /// 500, the change is saved in the database, but cached menus still show the old state
+    CacheOutdated(String),
+    /// 503, the database or redis can't be reached, the request may be repeated later
+    Unavailable(String),
+    /// 500
+    Internal(String),
+}
+
+/// Body of every error response
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    /// present for validation errors only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldError>>,
+}
+
+impl ApiError {
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::Conflict {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError::Validation(vec![FieldError {
+            field: field.into(),
+            message: message.into(),
+        }])
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict { code, .. } => code,
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::IdempotencyKeyReused(_) => "idempotency_key_reused",
+            ApiError::TooManyRequests { .. } => "rate_limited",
+            ApiError::CacheOutdated(_) => "cache_outdated",
+            ApiError::Unavailable(_) => "service_unavailable",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict { message, .. }
+            | ApiError::IdempotencyKeyReused(message)
+            | ApiError::TooManyRequests { message, .. }
+            | ApiError::CacheOutdated(message)
+            | ApiError::Unavailable(message)
+            | ApiError::Internal(message) => f.write_str(message),
+            ApiError::Validation(errors) => {
+                write!(f, "Request has {} invalid field(s)", errors.len())
+            }
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::Validation(_) | ApiError::IdempotencyKeyReused(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::CacheOutdated(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut resp = HttpResponse::build(self.status_code());
+
+        if let ApiError::TooManyRequests { retry_after_s, .. } = self {
+            resp.insert_header((RETRY_AFTER, retry_after_s.to_string()));
+        }
+
+        resp.json(ErrorBody {
+            code: self.code().to_owned(),
+            message: self.to_string(),
+            details: match self {
+                ApiError::Validation(errors) => Some(errors.clone()),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl From<DieselError> for ApiError {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => ApiError::NotFound("Record is not found".to_owned()),
+            DieselError::DatabaseError(kind, info) => {
+                let message = info.message().to_owned();
+
+                match kind {
+                    DatabaseErrorKind::UniqueViolation => {
+                        ApiError::conflict("already_exists", message)
+                    }
+                    DatabaseErrorKind::ForeignKeyViolation => {
+                        ApiError::conflict("reference_violation", message)
+                    }
+                    DatabaseErrorKind::CheckViolation => {
+                        ApiError::conflict("constraint_violation", message)
+                    }
+                    DatabaseErrorKind::SerializationFailure => {
+                        ApiError::conflict("concurrent_update", message)
+                    }
+                    DatabaseErrorKind::ClosedConnection => ApiError::Unavailable(message),
+                    _ => ApiError::Internal(message),
+                }
+            }
+            err => ApiError::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<MailboxError> for ApiError {
+    fn from(err: MailboxError) -> Self {
+        ApiError::Unavailable(format!("Database worker is unavailable: {err}"))
+    }
+}
+
+impl From<RedisError> for ApiError {
+    fn from(err: RedisError) -> Self {
+        if err.is_connection_refusal()
+            || err.is_connection_dropped()
+            || err.is_io_error()
+            || err.is_timeout()
+        {
+            ApiError::Unavailable(format!("Redis is unavailable: {err}"))
+        } else {
+            ApiError::Internal(format!("Redis error: {err}"))
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        ApiError::Internal(format!("Malformed cached JSON: {err}"))
+    }
+}
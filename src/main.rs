@@ -1,6 +1,7 @@
 #![allow(unused)]
 
-use std::env;
+use std::fmt::Display;
+use std::time::Duration;
 
 use actix::{Addr, SyncArbiter};
 use actix_cors::Cors;
@@ -10,56 +11,270 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 use dotenv::dotenv;
 use futures::StreamExt;
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
 
+use crate::errors::ApiError;
 use crate::services::redis_handling::RedisHandler;
-use services::db_utils::{get_db_pool, AppState, PgActor};
+use crate::settings::{DatabaseSettings, RedisSettings, Settings};
+use services::db_utils::{get_db_pool, pending_migrations, run_migrations, AppState, PgActor};
+use services::guest::TableTokens;
+use services::ApiDoc;
+use types::{DishType, PoolInitializationError};
 
+mod errors;
 mod schema;
 mod services;
+mod settings;
 mod types;
 
-fn init_pg_db() -> Addr<PgActor> {
-    let db_url = env::var("PG_DATABASE_URL").expect("PG_DATABASE_URL must be set");
-    let pool: Pool<ConnectionManager<PgConnection>> = get_db_pool(&db_url).unwrap();
+#[cfg(test)]
+mod settings_tests;
 
-    SyncArbiter::start(5, move || PgActor(pool.clone()))
+type PgPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Commands of the binary, it serves the API when started without one
+enum Command {
+    Serve,
+    /// applies pending migrations and exits
+    Migrate,
+}
+
+impl Command {
+    fn from_args() -> Result<Self, String> {
+        match std::env::args().nth(1).as_deref() {
+            None => Ok(Command::Serve),
+            Some("migrate") => Ok(Command::Migrate),
+            Some(other) => Err(format!(
+                "Unknown command '{other}', run without arguments to serve or with 'migrate'"
+            )),
+        }
+    }
+}
+
+fn init_pg_pool(settings: &DatabaseSettings) -> Result<PgPool, PoolInitializationError> {
+    get_db_pool(
+        &settings.url,
+        settings.pool_size,
+        Duration::from_secs(settings.connection_timeout_s),
+    )
+}
+
+fn init_pg_db(settings: &DatabaseSettings, pool: PgPool) -> Addr<PgActor> {
+    SyncArbiter::start(settings.actor_threads, move || PgActor(pool.clone()))
+}
+
+fn migrate(pool: &PgPool) {
+    let mut conn = pool
+        .get()
+        .unwrap_or_else(|err| exit_with(format!("Failed to connect to the database: {err}")));
+    let applied = run_migrations(&mut conn)
+        .unwrap_or_else(|err| exit_with(format!("Failed to apply migrations: {err}")));
+
+    if applied.is_empty() {
+        println!("Database schema is up to date");
+    }
+    for version in applied {
+        println!("Applied migration {version}");
+    }
+}
+
+/// Serving with an outdated schema would fail on the first query touching a new column,
+/// so the service refuses to start instead
+fn ensure_schema_is_current(pool: &PgPool) {
+    let mut conn = pool
+        .get()
+        .unwrap_or_else(|err| exit_with(format!("Failed to connect to the database: {err}")));
+    let pending = pending_migrations(&mut conn)
+        .unwrap_or_else(|err| exit_with(format!("Failed to check migrations: {err}")));
+
+    if !pending.is_empty() {
+        exit_with(format!(
+            "Database schema is behind, pending migrations: {}.\n\
+             Run `nnn-rust-back migrate` or set database.migrate_on_startup",
+            pending.join(", ")
+        ));
+    }
 }
 
-fn init_redis_db() -> redis::Client {
-    let db_uri = env::var("REDIS_DATABASE_URI").expect("REDIS_DATABASE_URI must be set");
+fn init_redis_db(settings: &RedisSettings) -> redis::RedisResult<redis::Client> {
+    redis::Client::open(settings.url.as_str())
+}
 
-    redis::Client::open(db_uri).unwrap()
+/// Stops the service before it started, configuration problems are not worth a panic
+fn exit_with(message: impl Display) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1)
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    let pg_db = init_pg_db();
-    let redis_db = init_redis_db();
+    let command = Command::from_args().unwrap_or_else(|err| exit_with(err));
+    let settings = Settings::load().unwrap_or_else(|err| exit_with(err));
+
+    let pool = init_pg_pool(&settings.database)
+        .unwrap_or_else(|err| exit_with(format!("Failed to connect to the database: {err}")));
 
-    let addr = env::var("ADDRESS").unwrap_or("127.0.0.1:8080".to_owned());
-    let frontend_origin = env::var("FRONT_ORIGIN").unwrap_or("http://localhost:5173".to_owned());
+    match command {
+        Command::Migrate => {
+            migrate(&pool);
+            return Ok(());
+        }
+        Command::Serve if settings.database.migrate_on_startup => migrate(&pool),
+        Command::Serve => ensure_schema_is_current(&pool),
+    }
+
+    let pg_db = init_pg_db(&settings.database, pool);
+    let redis_db = init_redis_db(&settings.redis)
+        .unwrap_or_else(|err| exit_with(format!("Invalid redis.url: {err}")));
+    let redis_handler =
+        RedisHandler::new(redis_db, Duration::from_millis(settings.redis.timeout_ms));
+    let table_tokens = TableTokens::new(&settings.guest.token_secret);
 
     HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin(&frontend_origin)
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+        let cors = settings
+            .server
+            .cors_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
             .allow_any_header()
+            .expose_headers(vec![
+                types::IDEMPOTENT_REPLAY_HEADER,
+                "Deprecation",
+                "Link",
+                "ETag",
+                "Retry-After",
+            ])
             .max_age(3600);
 
         App::new()
             .app_data(Data::new(AppState {
                 pg_db: pg_db.clone(),
-                redis_handler: RedisHandler::new(redis_db.clone()),
+                redis_handler: redis_handler.clone(),
+                course_order: settings.menu.course_order.clone(),
+                min_margin_pct: settings.menu.min_margin_percent,
+                menu_max_age_s: settings.menu.cache_max_age_s,
+                idempotency_ttl_s: settings.idempotency.ttl_s,
+                health_timeout_ms: settings.health.timeout_ms,
+                table_tokens: table_tokens.clone(),
+                guest_app_url: settings.guest.app_url.clone(),
+                guest_requests_per_minute: settings.guest.requests_per_minute,
+                tips: settings.tips.clone(),
+                restaurant: settings.restaurant.clone(),
+            }))
+            .app_data(web::JsonConfig::default().error_handler(|err, _| {
+                ApiError::BadRequest(format!("Malformed request body: {err}")).into()
+            }))
+            .app_data(web::PathConfig::default().error_handler(|err, _| {
+                ApiError::NotFound(format!("Malformed path: {err}")).into()
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| {
+                ApiError::BadRequest(format!("Malformed query: {err}")).into()
             }))
             .service(services::home_page)
+            .service(services::openapi_json)
+            .service(Scalar::with_url("/docs", ApiDoc::openapi()))
+            .service(
+                web::scope("/health")
+                    .service(services::health::liveness)
+                    .service(services::health::readiness),
+            )
+            .service(
+                web::scope("/api/v1/restaurant")
+                    .service(services::api_v1::restaurant_route::show_restaurant),
+            )
+            .service(
+                web::scope("/api/v1/waiters")
+                    .service(services::api_v1::waiters_route::list_waiters)
+                    .service(services::api_v1::waiters_route::hire_waiter)
+                    .service(services::api_v1::waiters_route::list_on_shift_waiters)
+                    .service(services::api_v1::waiters_route::clock_in)
+                    .service(services::api_v1::waiters_route::clock_out)
+                    .service(services::api_v1::waiters_route::put_waiter_worker),
+            )
+            .service(
+                web::scope("/api/v1/workers")
+                    .service(services::api_v1::workers_route::list_on_shift_staff)
+                    .service(services::api_v1::workers_route::clock_in)
+                    .service(services::api_v1::workers_route::clock_out),
+            )
+            .service(
+                web::scope("/api/v1/menus")
+                    .service(services::api_v1::menus_route::show_active_menu)
+                    .service(services::api_v1::menus_route::activate_menu)
+                    .service(services::api_v1::menus_route::show_active_menu_dish)
+                    .service(services::api_v1::menus_route::add_menu)
+                    .service(services::api_v1::menus_route::show_menu_cache_report)
+                    .service(services::api_v1::menus_route::show_menu)
+                    .service(services::api_v1::menus_route::remove_menu),
+            )
+            .service(
+                web::scope("/api/v1/orders")
+                    .wrap(services::idempotency::Idempotent::Route("orders"))
+                    .service(services::api_v1::orders_route::list_orders)
+                    .service(services::api_v1::orders_route::add_order)
+                    .service(services::api_v1::orders_route::show_order)
+                    .service(services::api_v1::orders_route::add_order_line)
+                    .service(services::api_v1::orders_route::patch_order_lines)
+                    .service(services::api_v1::orders_route::put_order_line)
+                    .service(services::api_v1::orders_route::remove_order_line)
+                    .service(services::api_v1::orders_route::mark_order_confirmed)
+                    .service(services::api_v1::orders_route::mark_order_cooked)
+                    .service(services::api_v1::orders_route::mark_order_paid)
+                    .service(services::api_v1::orders_route::transfer_order)
+                    .service(services::api_v1::orders_route::merge_orders),
+            )
+            .service(
+                web::scope("/api/v1/dishes")
+                    .service(services::api_v1::dishes_route::list_dishes)
+                    .service(services::api_v1::dishes_route::add_dish)
+                    .service(services::api_v1::dishes_route::list_dish_costings)
+                    .service(services::api_v1::dishes_route::show_dish)
+                    .service(services::api_v1::dishes_route::patch_dish)
+                    .service(services::api_v1::dishes_route::remove_dish)
+                    .service(services::api_v1::dishes_route::put_dish_ingredients)
+                    .service(services::api_v1::dishes_route::list_dish_modifier_groups)
+                    .service(services::api_v1::dishes_route::add_dish_modifier_group),
+            )
+            .service(
+                web::scope("/api/v1/products")
+                    .service(services::api_v1::products_route::list_products)
+                    .service(services::api_v1::products_route::put_product_allergens)
+                    .service(services::api_v1::products_route::put_product_cost)
+                    .service(services::api_v1::products_route::list_product_costs),
+            )
+            .service(
+                web::scope("/api/v1/tables")
+                    .service(services::api_v1::tables_route::show_table_guest_token)
+                    .service(services::api_v1::tables_route::show_tables_qr_sheet)
+                    .service(services::api_v1::tables_route::show_table_qr)
+                    .service(services::api_v1::tables_route::put_table_waiter),
+            )
+            .service(
+                web::scope("/api/v1/tips").service(services::api_v1::tips_route::show_tip_payouts),
+            )
+            .service(
+                web::scope("/api/v1/audit")
+                    .service(services::api_v1::audit_route::list_audit_entries),
+            )
+            .service(
+                web::scope("/api/v1/guest")
+                    .wrap(services::idempotency::Idempotent::GuestTable)
+                    .service(services::guest::show_guest_menu)
+                    .service(services::guest::show_guest_order)
+                    .service(services::guest::add_guest_order_line),
+            )
             .service(
                 web::scope("/waiters")
+                    .wrap(services::deprecation_headers())
                     .service(services::waiters_route::fetch_waiters)
                     .service(services::waiters_route::add_waiter),
             )
             .service(
                 web::scope("/menu")
+                    .wrap(services::deprecation_headers())
                     .service(services::menu_route::create_menu)
                     .service(services::menu_route::set_active_menu)
                     .service(services::menu_route::view_menu)
@@ -69,17 +284,41 @@ async fn main() -> std::io::Result<()> {
             )
             .service(
                 web::scope("/order")
+                    .wrap(services::deprecation_headers())
+                    .wrap(services::idempotency::Idempotent::Route("order"))
                     .service(services::order_route::get_ordered_dishes)
                     .service(services::order_route::get_all_orders)
                     .service(services::order_route::create_blank_order)
                     .service(services::order_route::add_dish_to_order)
                     .service(services::order_route::decrement_dish_in_order)
                     .service(services::order_route::delete_dish_from_order)
+                    .service(services::order_route::batch_edit_order)
+                    .service(services::order_route::set_line_count)
+                    .service(services::order_route::decrement_order_line)
+                    .service(services::order_route::delete_order_line)
                     .service(services::order_route::confirm_order)
                     .service(services::order_route::cook_order)
                     .service(services::order_route::pay_for_order),
             )
-            .service(web::scope("/dishes").service(services::dishes_route::create_dish))
+            .service(
+                web::scope("/dishes")
+                    .wrap(services::deprecation_headers())
+                    .service(services::dishes_route::get_costing)
+                    .service(services::dishes_route::create_dish)
+                    .service(services::dishes_route::update_dish)
+                    .service(services::dishes_route::replace_dish_ingredients)
+                    .service(services::dishes_route::get_dish_modifiers)
+                    .service(services::dishes_route::create_modifier_group)
+                    .service(services::dishes_route::archive_dish),
+            )
+            .service(
+                web::scope("/products")
+                    .wrap(services::deprecation_headers())
+                    .service(services::products_route::get_products)
+                    .service(services::products_route::set_product_allergens)
+                    .service(services::products_route::set_product_cost)
+                    .service(services::products_route::get_product_cost_history),
+            )
             .service(
                 web::scope("/test")
                     .service(services::test_route::healthcheck)
@@ -87,7 +326,12 @@ async fn main() -> std::io::Result<()> {
             )
             .wrap(cors)
     })
-    .bind(addr)?
+    .client_request_timeout(Duration::from_millis(
+        settings.server.client_request_timeout_ms,
+    ))
+    .keep_alive(Duration::from_secs(settings.server.keep_alive_s))
+    .shutdown_timeout(settings.server.shutdown_timeout_s)
+    .bind(&settings.server.address)?
     .run()
     .await
 }
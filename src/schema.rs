@@ -1,11 +1,28 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_log (id) {
+        id -> Int8,
+        worker_id -> Nullable<Int4>,
+        #[max_length = 64]
+        entity -> Varchar,
+        #[max_length = 64]
+        entity_id -> Varchar,
+        #[max_length = 64]
+        action -> Varchar,
+        diff -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     dish_to_order (id) {
         id -> Int8,
         dish_id -> Int8,
         order_id -> Int8,
         count -> Int4,
+        note -> Nullable<Text>,
+        line_key -> Text,
     }
 }
 
@@ -28,6 +45,49 @@ diesel::table! {
         portion_weight_g -> Int4,
         price -> Int4,
         approx_cook_time_s -> Int4,
+        description -> Nullable<Text>,
+        #[max_length = 255]
+        image_url -> Nullable<Varchar>,
+        archived_at -> Nullable<Timestamptz>,
+        dietary_labels -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    modifier_groups (id) {
+        id -> Int8,
+        dish_id -> Int8,
+        #[max_length = 100]
+        name -> Varchar,
+        is_multiple -> Bool,
+        is_required -> Bool,
+    }
+}
+
+diesel::table! {
+    modifier_products (id) {
+        id -> Int8,
+        modifier_id -> Int8,
+        product_id -> Int8,
+        weight_delta_g -> Int4,
+    }
+}
+
+diesel::table! {
+    modifiers (id) {
+        id -> Int8,
+        group_id -> Int8,
+        #[max_length = 100]
+        name -> Varchar,
+        price_delta -> Int4,
+    }
+}
+
+diesel::table! {
+    order_line_modifiers (id) {
+        id -> Int8,
+        line_id -> Int8,
+        modifier_id -> Int8,
     }
 }
 
@@ -42,6 +102,8 @@ diesel::table! {
         created_at -> Timestamptz,
         cooked_at -> Nullable<Timestamptz>,
         confirmed_at -> Nullable<Timestamptz>,
+        waiter_id -> Nullable<Int8>,
+        paid_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -51,6 +113,27 @@ diesel::table! {
         #[max_length = 50]
         name -> Varchar,
         in_stock_g -> Int4,
+        allergens -> Array<Text>,
+        cost_per_kg -> Int4,
+    }
+}
+
+diesel::table! {
+    product_costs (id) {
+        id -> Int8,
+        product_id -> Int8,
+        cost_per_kg -> Int4,
+        valid_from -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    shifts (id) {
+        id -> Int8,
+        waiter_id -> Nullable<Int8>,
+        started_at -> Timestamptz,
+        ended_at -> Nullable<Timestamptz>,
+        worker_id -> Nullable<Int4>,
     }
 }
 
@@ -74,6 +157,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    tips (id) {
+        id -> Int8,
+        order_id -> Int8,
+        waiter_id -> Nullable<Int8>,
+        amount -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     waiters (id) {
         id -> Int8,
@@ -81,6 +174,7 @@ diesel::table! {
         first_name -> Varchar,
         #[max_length = 40]
         last_name -> Varchar,
+        worker_id -> Nullable<Int4>,
     }
 }
 
@@ -122,23 +216,45 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(audit_log -> worker (worker_id));
 diesel::joinable!(dish_to_order -> dishes (dish_id));
 diesel::joinable!(dish_to_order -> orders (order_id));
 diesel::joinable!(dish_to_product -> dishes (dish_id));
 diesel::joinable!(dish_to_product -> products (product_id));
+diesel::joinable!(modifier_groups -> dishes (dish_id));
+diesel::joinable!(modifier_products -> modifiers (modifier_id));
+diesel::joinable!(modifier_products -> products (product_id));
+diesel::joinable!(modifiers -> modifier_groups (group_id));
+diesel::joinable!(order_line_modifiers -> dish_to_order (line_id));
+diesel::joinable!(order_line_modifiers -> modifiers (modifier_id));
 diesel::joinable!(orders -> tables (table_id));
+diesel::joinable!(orders -> waiters (waiter_id));
+diesel::joinable!(product_costs -> products (product_id));
+diesel::joinable!(shifts -> waiters (waiter_id));
+diesel::joinable!(shifts -> worker (worker_id));
 diesel::joinable!(tables -> waiters (waiter_id));
+diesel::joinable!(tips -> orders (order_id));
+diesel::joinable!(tips -> waiters (waiter_id));
+diesel::joinable!(waiters -> worker (worker_id));
 diesel::joinable!(worker -> worker_role (role_id));
 diesel::joinable!(worker_auth -> worker (worker_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_log,
     dish_to_order,
     dish_to_product,
     dishes,
+    modifier_groups,
+    modifier_products,
+    modifiers,
+    order_line_modifiers,
     orders,
+    product_costs,
     products,
+    shifts,
     stats,
     tables,
+    tips,
     waiters,
     worker,
     worker_auth,
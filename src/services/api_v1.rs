@@ -0,0 +1,1558 @@
+//! Resource oriented routes under `/api/v1`. Every route responds with the affected resource,
+//! the older routes in the parent module are kept as deprecated aliases.
+
+// sub-route "/api/v1/restaurant"
+pub mod restaurant_route {
+    use actix_web::web::Data;
+    use actix_web::{get, HttpResponse};
+    use utoipa::OpenApi;
+
+    use crate::services::db_utils::AppState;
+    use crate::settings::RestaurantSettings;
+
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Name, currency, opening hours and business day",
+                body = RestaurantSettings
+            ),
+        )
+    )]
+    #[get("")]
+    pub async fn show_restaurant(state: Data<AppState>) -> HttpResponse {
+        HttpResponse::Ok().json(&state.restaurant)
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(show_restaurant))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/waiters"
+pub mod waiters_route {
+    use actix_web::web::{Data, Json, Path};
+    use actix_web::{get, post, put, HttpResponse};
+    use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::{Shift, Waiter};
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::{
+        ClockIn, ClockOut, FetchOnShiftStaff, FetchWaiters, LinkWaiterWorker, ShiftOf,
+    };
+    use crate::services::waiters_route::AddWaiterBody;
+    use crate::types::{OnShiftStaff, ShiftSummary};
+
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All waiters", body = Vec<Waiter>),
+        )
+    )]
+    #[get("")]
+    pub async fn list_waiters(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let waiters = state.pg_db.send(FetchWaiters).await??;
+
+        Ok(HttpResponse::Ok().json(waiters))
+    }
+
+    #[utoipa::path(
+        request_body = AddWaiterBody,
+        responses(
+            (status = 201, description = "New waiter", body = Waiter),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+        )
+    )]
+    #[post("")]
+    pub async fn hire_waiter(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<AddWaiterBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let waiter = body.into_inner().save(&state, worker).await?;
+
+        Ok(HttpResponse::Created().json(waiter))
+    }
+
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Waiters who are clocked in, the earliest first",
+                body = Vec<OnShiftStaff>
+            ),
+        )
+    )]
+    #[get("/on-shift")]
+    pub async fn list_on_shift_waiters(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let mut on_shift = state.pg_db.send(FetchOnShiftStaff).await??;
+        on_shift.retain(|staff| staff.waiter.is_some());
+
+        Ok(HttpResponse::Ok().json(on_shift))
+    }
+
+    #[utoipa::path(
+        params(("waiter_id" = i64, Path, description = "Waiter id")),
+        responses(
+            (status = 201, description = "New shift", body = Shift),
+            (status = 404, description = "Waiter is not found", body = ErrorBody),
+            (status = 409, description = "Waiter is already on shift", body = ErrorBody),
+        )
+    )]
+    #[post("/{waiter_id}/clock-in")]
+    pub async fn clock_in(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let shift = state
+            .pg_db
+            .send(worker.acting(ClockIn(ShiftOf::Waiter(path.into_inner()))))
+            .await??;
+
+        Ok(HttpResponse::Created().json(shift))
+    }
+
+    #[utoipa::path(
+        params(("waiter_id" = i64, Path, description = "Waiter id")),
+        responses(
+            (
+                status = 200,
+                description = "Summary of the ended shift with orders to hand over",
+                body = ShiftSummary
+            ),
+            (status = 404, description = "Waiter is not found", body = ErrorBody),
+            (status = 409, description = "Waiter is not on shift", body = ErrorBody),
+        )
+    )]
+    #[post("/{waiter_id}/clock-out")]
+    pub async fn clock_out(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let summary = state
+            .pg_db
+            .send(worker.acting(ClockOut(ShiftOf::Waiter(path.into_inner()))))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(summary))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub struct LinkWorkerBody {
+        /// staff record of the waiter, `null` unlinks it
+        worker_id: Option<i32>,
+    }
+
+    #[utoipa::path(
+        params(("waiter_id" = i64, Path, description = "Waiter id")),
+        request_body = LinkWorkerBody,
+        responses(
+            (status = 200, description = "Waiter with the staff record", body = Waiter),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 404, description = "Waiter or worker is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Worker is linked to another waiter",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[put("/{waiter_id}/worker")]
+    pub async fn put_waiter_worker(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<LinkWorkerBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let waiter = state
+            .pg_db
+            .send(worker.acting(LinkWaiterWorker {
+                waiter_id: path.into_inner(),
+                worker_id: body.worker_id,
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(waiter))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        list_waiters,
+        hire_waiter,
+        list_on_shift_waiters,
+        clock_in,
+        clock_out,
+        put_waiter_worker
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/workers"
+pub mod workers_route {
+    use actix_web::web::{Data, Path};
+    use actix_web::{get, post, HttpResponse};
+    use utoipa::OpenApi;
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::Shift;
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::{ClockIn, ClockOut, FetchOnShiftStaff, ShiftOf};
+    use crate::types::{OnShiftStaff, ShiftSummary};
+
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Waiters and other staff who are clocked in, the earliest first",
+                body = Vec<OnShiftStaff>
+            ),
+        )
+    )]
+    #[get("/on-shift")]
+    pub async fn list_on_shift_staff(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let on_shift = state.pg_db.send(FetchOnShiftStaff).await??;
+
+        Ok(HttpResponse::Ok().json(on_shift))
+    }
+
+    #[utoipa::path(
+        params(("worker_id" = i32, Path, description = "Worker id")),
+        responses(
+            (status = 201, description = "New shift", body = Shift),
+            (status = 404, description = "Worker is not found", body = ErrorBody),
+            (status = 409, description = "Worker is already on shift", body = ErrorBody),
+        )
+    )]
+    #[post("/{worker_id}/clock-in")]
+    pub async fn clock_in(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i32>,
+    ) -> Result<HttpResponse, ApiError> {
+        let shift = state
+            .pg_db
+            .send(worker.acting(ClockIn(ShiftOf::Worker(path.into_inner()))))
+            .await??;
+
+        Ok(HttpResponse::Created().json(shift))
+    }
+
+    #[utoipa::path(
+        params(("worker_id" = i32, Path, description = "Worker id")),
+        responses(
+            (
+                status = 200,
+                description = "Summary of the ended shift, orders and tables of a waiter included",
+                body = ShiftSummary
+            ),
+            (status = 404, description = "Worker is not found", body = ErrorBody),
+            (status = 409, description = "Worker is not on shift", body = ErrorBody),
+        )
+    )]
+    #[post("/{worker_id}/clock-out")]
+    pub async fn clock_out(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i32>,
+    ) -> Result<HttpResponse, ApiError> {
+        let summary = state
+            .pg_db
+            .send(worker.acting(ClockOut(ShiftOf::Worker(path.into_inner()))))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(summary))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(list_on_shift_staff, clock_in, clock_out))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/dishes"
+pub mod dishes_route {
+    use actix_web::web::{Data, Json, Path};
+    use actix_web::{delete, get, patch, post, put, HttpResponse};
+    use utoipa::OpenApi;
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::Dish;
+    use crate::services::db_utils::AppState;
+    use crate::services::dishes_route::{
+        archive, costings, replace_ingredients, CreateDishBody, CreateModifierGroupBody,
+        UpdateDishBody,
+    };
+    use crate::services::messages::{FetchDish, FetchDishModifiers, FetchDishes};
+    use crate::types::{DishCosting, Ingredient, ModifierGroupInfo};
+
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All dishes, including archived ones", body = Vec<Dish>),
+        )
+    )]
+    #[get("")]
+    pub async fn list_dishes(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let dishes = state.pg_db.send(FetchDishes).await??;
+
+        Ok(HttpResponse::Ok().json(dishes))
+    }
+
+    #[utoipa::path(
+        request_body = CreateDishBody,
+        responses(
+            (status = 201, description = "New dish", body = Dish),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+        )
+    )]
+    #[post("")]
+    pub async fn add_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<CreateDishBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = body.into_inner().save(&state, worker).await?;
+
+        Ok(HttpResponse::Created().json(dish))
+    }
+
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Food cost and margin of every dish",
+                body = Vec<DishCosting>
+            ),
+        )
+    )]
+    #[get("/costing")]
+    pub async fn list_dish_costings(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        Ok(HttpResponse::Ok().json(costings(&state).await?))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (status = 200, description = "Dish", body = Dish),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+        )
+    )]
+    #[get("/{dish_id}")]
+    pub async fn show_dish(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = state.pg_db.send(FetchDish(path.into_inner())).await??;
+
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = UpdateDishBody,
+        responses(
+            (status = 200, description = "Updated dish", body = Dish),
+            (status = 400, description = "Malformed or empty body", body = ErrorBody),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+            (
+                status = 500,
+                description = "Dish is saved, cached menus are outdated",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[patch("/{dish_id}")]
+    pub async fn patch_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<UpdateDishBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (status = 200, description = "Archived dish", body = Dish),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+        )
+    )]
+    #[delete("/{dish_id}")]
+    pub async fn remove_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = archive(&state, worker, path.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = Vec<Ingredient>,
+        responses(
+            (
+                status = 200,
+                description = "New ingredients as (product name, grams) pairs",
+                body = Vec<(String, i32)>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid ingredients", body = ErrorBody),
+        )
+    )]
+    #[put("/{dish_id}/ingredients")]
+    pub async fn put_dish_ingredients(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<Vec<Ingredient>>,
+    ) -> Result<HttpResponse, ApiError> {
+        let ingredients =
+            replace_ingredients(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(ingredients))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (
+                status = 200,
+                description = "Modifier groups of the dish",
+                body = Vec<ModifierGroupInfo>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+        )
+    )]
+    #[get("/{dish_id}/modifier-groups")]
+    pub async fn list_dish_modifier_groups(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let groups = state
+            .pg_db
+            .send(FetchDishModifiers(path.into_inner()))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(groups))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = CreateModifierGroupBody,
+        responses(
+            (
+                status = 201,
+                description = "All modifier groups of the dish, including the new one",
+                body = Vec<ModifierGroupInfo>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+        )
+    )]
+    #[post("/{dish_id}/modifier-groups")]
+    pub async fn add_dish_modifier_group(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<CreateModifierGroupBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let groups = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Created().json(groups))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        list_dishes,
+        add_dish,
+        list_dish_costings,
+        show_dish,
+        patch_dish,
+        remove_dish,
+        put_dish_ingredients,
+        list_dish_modifier_groups,
+        add_dish_modifier_group,
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/products"
+pub mod products_route {
+    use actix_web::web::{Data, Json, Path};
+    use actix_web::{get, put, HttpResponse};
+    use utoipa::OpenApi;
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::{Product, ProductCost};
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::{FetchProductCostHistory, FetchProducts};
+    use crate::services::products_route::{set_allergens, SetProductCostBody};
+    use crate::types::Allergen;
+
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All products", body = Vec<Product>),
+        )
+    )]
+    #[get("")]
+    pub async fn list_products(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let products = state.pg_db.send(FetchProducts).await??;
+
+        Ok(HttpResponse::Ok().json(products))
+    }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        request_body = Vec<Allergen>,
+        responses(
+            (status = 200, description = "Updated product", body = Product),
+            (status = 404, description = "Product is not found", body = ErrorBody),
+            (
+                status = 500,
+                description = "Product is saved, cached menus are outdated",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[put("/{product_id}/allergens")]
+    pub async fn put_product_allergens(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<Vec<Allergen>>,
+    ) -> Result<HttpResponse, ApiError> {
+        let product = set_allergens(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(product))
+    }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        request_body = SetProductCostBody,
+        responses(
+            (status = 200, description = "Updated product", body = Product),
+            (status = 404, description = "Product is not found", body = ErrorBody),
+            (status = 422, description = "Negative cost", body = ErrorBody),
+        )
+    )]
+    #[put("/{product_id}/cost")]
+    pub async fn put_product_cost(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<SetProductCostBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let product = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Ok().json(product))
+    }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        responses(
+            (status = 200, description = "Cost changes, newest first", body = Vec<ProductCost>),
+        )
+    )]
+    #[get("/{product_id}/costs")]
+    pub async fn list_product_costs(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let history = state
+            .pg_db
+            .send(FetchProductCostHistory(path.into_inner()))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(history))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        list_products,
+        put_product_allergens,
+        put_product_cost,
+        list_product_costs,
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/menus"
+pub mod menus_route {
+    use actix_web::http::header::ContentType;
+    use actix_web::web::{Data, Json, Path, Query};
+    use actix_web::{delete, get, post, put, HttpRequest, HttpResponse};
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use utoipa::{OpenApi, ToSchema};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::conditional::{conditional, Validators};
+    use crate::services::db_utils::AppState;
+    use crate::services::menu_route::{
+        audit_menu, check_menu_worker, filtered_menu, CreateMenuBody, MenuFilter,
+    };
+    use crate::types::{Menu, MenuCacheReport, RedisDish};
+
+    #[utoipa::path(
+        params(MenuFilter),
+        responses(
+            (status = 200, description = "Active menu", body = Menu),
+            (status = 304, description = "Client's copy is still current"),
+            (status = 404, description = "No menu is active", body = ErrorBody),
+            (status = 422, description = "Unknown allergen", body = ErrorBody),
+        )
+    )]
+    #[get("/active")]
+    pub async fn show_active_menu(
+        req: HttpRequest,
+        state: Data<AppState>,
+        filter: Query<MenuFilter>,
+    ) -> Result<HttpResponse, ApiError> {
+        let menu = state.redis_handler.get_menu().await?;
+        let validators = menu.revision.as_ref().map(Validators::of_menu);
+
+        conditional(&req, validators, state.menu_max_age_s, || {
+            filtered_menu(menu.json, &filter)
+        })
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    struct ActivateMenuBody {
+        date: NaiveDate,
+    }
+
+    #[utoipa::path(
+        request_body = ActivateMenuBody,
+        responses(
+            (status = 200, description = "Menu that is active now", body = Menu),
+            (status = 404, description = "Menu is not found", body = ErrorBody),
+        )
+    )]
+    #[put("/active")]
+    pub async fn activate_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<ActivateMenuBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let menu = state.redis_handler.get_menu_by_date(&body.date).await?;
+
+        check_menu_worker(&state, worker).await?;
+        state.redis_handler.set_active_menu(&body.date).await?;
+        let after = json!({ "is_active": true });
+        audit_menu(&state, worker, &body.date, "activate", Value::Null, after).await?;
+
+        Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(menu.json))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (status = 200, description = "Dish of the active menu", body = RedisDish),
+            (status = 304, description = "Client's copy is still current"),
+            (status = 404, description = "Dish is not in the active menu", body = ErrorBody),
+        )
+    )]
+    #[get("/active/dishes/{dish_id}")]
+    pub async fn show_active_menu_dish(
+        req: HttpRequest,
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish_id = path.into_inner();
+        let redis_dish = state.redis_handler.get_dish(dish_id).await?;
+        let validators = redis_dish
+            .revision
+            .as_ref()
+            .map(|revision| Validators::of_dish(revision, dish_id));
+
+        conditional(&req, validators, state.menu_max_age_s, || {
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .body(redis_dish.json))
+        })
+    }
+
+    #[utoipa::path(
+        request_body = CreateMenuBody,
+        responses(
+            (status = 201, description = "New menu", body = Menu),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+        )
+    )]
+    #[post("")]
+    pub async fn add_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<CreateMenuBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let body = body.into_inner();
+        let date = body.date;
+
+        body.save(&state, worker).await?;
+
+        let menu = state.redis_handler.get_menu_by_date(&date).await?;
+
+        Ok(HttpResponse::Created()
+            .content_type(ContentType::json())
+            .body(menu.json))
+    }
+
+    // registered before `/{date}`, which would take "cache-report" for a malformed date
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "Problems of cached menus", body = MenuCacheReport),
+        )
+    )]
+    #[get("/cache-report")]
+    pub async fn show_menu_cache_report(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let report = state.redis_handler.check_menu_cache().await?;
+
+        Ok(HttpResponse::Ok().json(report))
+    }
+
+    #[utoipa::path(
+        params(("date" = NaiveDate, Path, description = "Date of the menu"), MenuFilter),
+        responses(
+            (status = 200, description = "Menu", body = Menu),
+            (status = 304, description = "Client's copy is still current"),
+            (status = 404, description = "Menu is not found", body = ErrorBody),
+            (status = 422, description = "Unknown allergen", body = ErrorBody),
+        )
+    )]
+    #[get("/{date}")]
+    pub async fn show_menu(
+        req: HttpRequest,
+        state: Data<AppState>,
+        path: Path<NaiveDate>,
+        filter: Query<MenuFilter>,
+    ) -> Result<HttpResponse, ApiError> {
+        let menu = state
+            .redis_handler
+            .get_menu_by_date(&path.into_inner())
+            .await?;
+        let validators = menu.revision.as_ref().map(Validators::of_menu);
+
+        conditional(&req, validators, state.menu_max_age_s, || {
+            filtered_menu(menu.json, &filter)
+        })
+    }
+
+    #[utoipa::path(
+        params(("date" = NaiveDate, Path, description = "Date of the menu")),
+        responses(
+            (status = 204, description = "Menu is deleted"),
+        )
+    )]
+    #[delete("/{date}")]
+    pub async fn remove_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<NaiveDate>,
+    ) -> Result<HttpResponse, ApiError> {
+        let date = path.into_inner();
+
+        check_menu_worker(&state, worker).await?;
+        state.redis_handler.delete_menu(&date).await?;
+        let before = json!({ "date": date });
+        audit_menu(&state, worker, &date, "delete", before, Value::Null).await?;
+
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        show_active_menu,
+        activate_menu,
+        show_active_menu_dish,
+        add_menu,
+        show_menu_cache_report,
+        show_menu,
+        remove_menu,
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/orders"
+pub mod orders_route {
+    use actix_web::web::{Bytes, Data, Json, Path};
+    use actix_web::{delete, get, patch, post, put, HttpResponse};
+    use chrono::Utc;
+    use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_utils::AppState;
+    use crate::services::idempotency::IdempotencyKey;
+    use crate::services::messages::{
+        ConfirmOrder, CookOrder, CreateOrder, DeleteDishFromOrder, FetchOrder, FetchOrders,
+        MergeOrders, PayForOrder, TransferOrder,
+    };
+    use crate::services::order_route::{add_line, batch_edit, AddDishBody, SetLineCountBody};
+    use crate::types::{BatchEdit, OrderInfo, OrderLineRef};
+
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All orders with their lines", body = Vec<OrderInfo>),
+        )
+    )]
+    #[get("")]
+    pub async fn list_orders(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let orders = state.pg_db.send(FetchOrders).await??;
+
+        Ok(HttpResponse::Ok().json(orders))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    struct NewOrderBody {
+        table_id: i64,
+    }
+
+    #[utoipa::path(
+        params(IdempotencyKey),
+        request_body = NewOrderBody,
+        responses(
+            (status = 201, description = "New order", body = OrderInfo),
+            (status = 409, description = "Table does not exist", body = ErrorBody),
+        )
+    )]
+    #[post("")]
+    pub async fn add_order(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<NewOrderBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = state
+            .pg_db
+            .send(worker.acting(CreateOrder(body.table_id)))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Created().json(order))
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id")),
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+        )
+    )]
+    #[get("/{order_id}")]
+    pub async fn show_order(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order = state.pg_db.send(FetchOrder(path.into_inner())).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    struct NewLineBody {
+        dish_id: i64,
+        #[serde(flatten)]
+        line: AddDishBody,
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body = NewLineBody,
+        responses(
+            (status = 201, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or dish is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is confirmed or dish is archived",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Invalid modifiers or note", body = ErrorBody),
+        )
+    )]
+    #[post("/{order_id}/lines")]
+    pub async fn add_order_line(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<NewLineBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+        let body = body.into_inner();
+
+        add_line(&state, worker, order_id, body.dish_id, body.line).await?;
+
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Created().json(order))
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body = BatchEdit,
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is confirmed or dish is archived",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Invalid lines", body = ErrorBody),
+        )
+    )]
+    #[patch("/{order_id}/lines")]
+    pub async fn patch_order_lines(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<BatchEdit>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order = batch_edit(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("line_id" = i64, Path, description = "Order line id"),
+            IdempotencyKey,
+        ),
+        request_body = SetLineCountBody,
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+            (status = 422, description = "Count is out of range", body = ErrorBody),
+        )
+    )]
+    #[put("/{order_id}/lines/{line_id}")]
+    pub async fn put_order_line(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<(i64, i64)>,
+        body: Json<SetLineCountBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let (order_id, line_id) = path.into_inner();
+
+        let order = body
+            .into_inner()
+            .save(&state, worker, order_id, line_id)
+            .await?;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("line_id" = i64, Path, description = "Order line id"),
+            IdempotencyKey,
+        ),
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+        )
+    )]
+    #[delete("/{order_id}/lines/{line_id}")]
+    pub async fn remove_order_line(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<(i64, i64)>,
+    ) -> Result<HttpResponse, ApiError> {
+        let (order_id, line_id) = path.into_inner();
+
+        state
+            .pg_db
+            .send(worker.acting(DeleteDishFromOrder {
+                order_id,
+                line: OrderLineRef::Line(line_id),
+            }))
+            .await??;
+
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        responses(
+            (status = 200, description = "Confirmed order", body = OrderInfo),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (status = 409, description = "Order is already confirmed", body = ErrorBody),
+        )
+    )]
+    #[post("/{order_id}/confirm")]
+    pub async fn mark_order_confirmed(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+
+        state
+            .pg_db
+            .send(worker.acting(ConfirmOrder(order_id)))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        responses(
+            (status = 200, description = "Cooked order", body = OrderInfo),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is not confirmed or already cooked",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[post("/{order_id}/cook")]
+    pub async fn mark_order_cooked(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+
+        state
+            .pg_db
+            .send(worker.acting(CookOrder(order_id)))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[derive(Deserialize, ToSchema, Default)]
+    pub struct PayBody {
+        /// gratuity on top of the total cost, it goes to the waiter of the table
+        #[serde(default)]
+        tip: i32,
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body(
+            content = Option<PayBody>,
+            description = "May be omitted when there is no tip"
+        ),
+        responses(
+            (status = 200, description = "Paid order", body = OrderInfo),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is not confirmed or already paid",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Negative tip", body = ErrorBody),
+        )
+    )]
+    #[post("/{order_id}/pay")]
+    pub async fn mark_order_paid(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Bytes,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+
+        // terminals without tipping post no body at all
+        let body = if body.is_empty() {
+            PayBody::default()
+        } else {
+            serde_json::from_slice::<PayBody>(&body)
+                .map_err(|err| ApiError::BadRequest(format!("Malformed request body: {err}")))?
+        };
+
+        if body.tip < 0 {
+            return Err(ApiError::invalid("tip", "must not be negative"));
+        }
+
+        state
+            .pg_db
+            .send(worker.acting(PayForOrder {
+                order_id,
+                tip: body.tip,
+                day: state.restaurant.business_day(Utc::now()),
+            }))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub struct TransferOrderBody {
+        table_id: i64,
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body = TransferOrderBody,
+        responses(
+            (status = 200, description = "Order at its new table", body = OrderInfo),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 404, description = "Order or table is not found", body = ErrorBody),
+            (status = 409, description = "Order is already paid", body = ErrorBody),
+        )
+    )]
+    #[post("/{order_id}/transfer")]
+    pub async fn transfer_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<TransferOrderBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+
+        state
+            .pg_db
+            .send(worker.acting(TransferOrder {
+                order_id,
+                table_id: body.table_id,
+            }))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub struct MergeOrderBody {
+        /// order whose lines are moved, it is deleted afterwards
+        from_order_id: i64,
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body = MergeOrderBody,
+        responses(
+            (status = 200, description = "Order with lines of both orders", body = OrderInfo),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 404, description = "Either order is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Either order is paid, or only one of them is confirmed",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Order is merged into itself", body = ErrorBody),
+        )
+    )]
+    #[post("/{order_id}/merge")]
+    pub async fn merge_orders(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<MergeOrderBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_id = path.into_inner();
+
+        if body.from_order_id == order_id {
+            return Err(ApiError::invalid(
+                "from_order_id",
+                "must differ from the order merged into",
+            ));
+        }
+
+        state
+            .pg_db
+            .send(worker.acting(MergeOrders {
+                order_id,
+                from_order_id: body.from_order_id,
+            }))
+            .await??;
+        let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+        Ok(HttpResponse::Ok().json(order))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        list_orders,
+        add_order,
+        show_order,
+        add_order_line,
+        patch_order_lines,
+        put_order_line,
+        remove_order_line,
+        mark_order_confirmed,
+        mark_order_cooked,
+        mark_order_paid,
+        transfer_order,
+        merge_orders,
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/tables"
+pub mod tables_route {
+    use actix_web::http::header::{
+        ContentDisposition, ContentType, DispositionParam, DispositionType,
+    };
+    use actix_web::web::{Data, Json, Path, Query};
+    use actix_web::{get, put, HttpResponse};
+    use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::Table;
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::{AssignTableWaiter, FetchTable, FetchTables};
+    use crate::services::qr_codes::{
+        render_png, render_sheet, render_svg, table_url, QrFormat, QrQuery, SheetEntry,
+    };
+    use crate::types::TableToken;
+
+    const SVG_CONTENT_TYPE: &str = "image/svg+xml";
+
+    fn table_token(state: &AppState, table_id: i64) -> TableToken {
+        let token = state.table_tokens.sign(table_id);
+
+        TableToken {
+            table_id,
+            url: table_url(&state.guest_app_url, &token),
+            token,
+        }
+    }
+
+    #[utoipa::path(
+        params(("table_id" = i64, Path, description = "Table id")),
+        responses(
+            (status = 200, description = "Token for guest routes of the table", body = TableToken),
+            (status = 404, description = "Table is not found", body = ErrorBody),
+        )
+    )]
+    #[get("/{table_id}/guest-token")]
+    pub async fn show_table_guest_token(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let table = state.pg_db.send(FetchTable(path.into_inner())).await??;
+
+        Ok(HttpResponse::Ok().json(table_token(&state, table.id)))
+    }
+
+    #[utoipa::path(
+        params(QrQuery),
+        responses(
+            (
+                status = 200,
+                description = "Printable sheet with a labeled QR code of every table",
+                content_type = "image/svg+xml",
+                body = String
+            ),
+            (status = 404, description = "There are no tables", body = ErrorBody),
+            (status = 422, description = "PNG format or invalid size", body = ErrorBody),
+        )
+    )]
+    #[get("/qr-sheet")]
+    pub async fn show_tables_qr_sheet(
+        state: Data<AppState>,
+        query: Query<QrQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        if query.format() != QrFormat::Svg {
+            return Err(ApiError::invalid("format", "sheet is rendered as SVG only"));
+        }
+
+        let size = query.size()?;
+        let tables = state.pg_db.send(FetchTables).await??;
+
+        if tables.is_empty() {
+            return Err(ApiError::NotFound("There are no tables".to_owned()));
+        }
+
+        let entries = tables
+            .iter()
+            .map(|table| SheetEntry {
+                table_id: table.id,
+                url: table_token(&state, table.id).url,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(HttpResponse::Ok()
+            .content_type(SVG_CONTENT_TYPE)
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Inline,
+                parameters: vec![DispositionParam::Filename("tables-qr.svg".to_owned())],
+            })
+            .body(render_sheet(&entries, size)?))
+    }
+
+    #[utoipa::path(
+        params(("table_id" = i64, Path, description = "Table id"), QrQuery),
+        responses(
+            (
+                status = 200,
+                description = "QR code linking to the guest app for the table",
+                content((String = "image/svg+xml"), (Vec<u8> = "image/png"))
+            ),
+            (status = 404, description = "Table is not found", body = ErrorBody),
+            (status = 422, description = "Invalid size", body = ErrorBody),
+        )
+    )]
+    #[get("/{table_id}/qr")]
+    pub async fn show_table_qr(
+        state: Data<AppState>,
+        path: Path<i64>,
+        query: Query<QrQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        let size = query.size()?;
+        let table = state.pg_db.send(FetchTable(path.into_inner())).await??;
+        let url = table_token(&state, table.id).url;
+
+        let resp = match query.format() {
+            QrFormat::Svg => HttpResponse::Ok()
+                .content_type(SVG_CONTENT_TYPE)
+                .body(render_svg(&url, size)?),
+            QrFormat::Png => HttpResponse::Ok()
+                .content_type(ContentType::png())
+                .body(render_png(&url, size)?),
+        };
+
+        Ok(resp)
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    struct AssignWaiterBody {
+        /// `null` leaves the table without a waiter
+        waiter_id: Option<i64>,
+    }
+
+    #[utoipa::path(
+        params(("table_id" = i64, Path, description = "Table id")),
+        request_body = AssignWaiterBody,
+        responses(
+            (
+                status = 200,
+                description = "Table with the new waiter, who now serves its unpaid orders",
+                body = Table
+            ),
+            (status = 404, description = "Table is not found", body = ErrorBody),
+            (status = 409, description = "Waiter is not on shift", body = ErrorBody),
+        )
+    )]
+    #[put("/{table_id}/waiter")]
+    pub async fn put_table_waiter(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<AssignWaiterBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let table = state
+            .pg_db
+            .send(worker.acting(AssignTableWaiter {
+                table_id: path.into_inner(),
+                waiter_id: body.waiter_id,
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(table))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        show_table_guest_token,
+        show_tables_qr_sheet,
+        show_table_qr,
+        put_table_waiter
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/tips"
+pub mod tips_route {
+    use actix_web::web::{Data, Query};
+    use actix_web::{get, HttpResponse};
+    use chrono::NaiveDate;
+    use serde::Deserialize;
+    use utoipa::{IntoParams, OpenApi};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::FetchTipPayoutInputs;
+    use crate::services::tips::tip_payouts;
+    use crate::types::TipPayoutReport;
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct PeriodQuery {
+        /// first business day of the period
+        from: NaiveDate,
+        /// last business day of the period, inclusive
+        to: NaiveDate,
+    }
+
+    #[utoipa::path(
+        params(PeriodQuery),
+        responses(
+            (
+                status = 200,
+                description = "Tips owed to each worker, they are not a part of the income",
+                body = TipPayoutReport
+            ),
+            (status = 400, description = "Malformed query", body = ErrorBody),
+            (status = 422, description = "Period ends before it starts", body = ErrorBody),
+        )
+    )]
+    #[get("/payouts")]
+    pub async fn show_tip_payouts(
+        state: Data<AppState>,
+        period: Query<PeriodQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        if period.to < period.from {
+            return Err(ApiError::invalid("to", "must not be before from"));
+        }
+
+        let (from, to) = state
+            .restaurant
+            .business_days_span(period.from, period.to)
+            .ok_or_else(|| ApiError::invalid("to", "is out of range"))?;
+
+        let inputs = state
+            .pg_db
+            .send(FetchTipPayoutInputs {
+                from,
+                to,
+                pool_roles: state.tips.pool_roles.keys().cloned().collect(),
+            })
+            .await??;
+
+        let report = tip_payouts(&state.tips, inputs, period.from, period.to);
+
+        Ok(HttpResponse::Ok().json(report))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(show_tip_payouts))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/api/v1/audit"
+pub mod audit_route {
+    use actix_web::web::{Data, Query};
+    use actix_web::{get, HttpResponse};
+    use chrono::{Days, NaiveDate};
+    use serde::Deserialize;
+    use utoipa::{IntoParams, OpenApi};
+
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::db_models::AuditEntry;
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::FetchAuditLog;
+    use crate::services::validation::Validator;
+
+    const DEFAULT_LIMIT: i64 = 100;
+    const MAX_LIMIT: i64 = 500;
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct AuditQuery {
+        /// kind of the changed entity, e.g. "order" or "menu"
+        entity: Option<String>,
+        /// id of the changed entity, the date for menus
+        entity_id: Option<String>,
+        /// e.g. "confirm" or "pay"
+        action: Option<String>,
+        worker_id: Option<i32>,
+        /// first business day of the changes
+        from: Option<NaiveDate>,
+        /// last business day of the changes, inclusive
+        to: Option<NaiveDate>,
+        /// id of the last entry of the previous page, only older entries are returned
+        before_id: Option<i64>,
+        /// at most 500 entries, 100 by default
+        limit: Option<i64>,
+    }
+
+    #[utoipa::path(
+        params(AuditQuery),
+        responses(
+            (status = 200, description = "Matching entries, newest first", body = Vec<AuditEntry>),
+            (status = 400, description = "Malformed query", body = ErrorBody),
+            (status = 422, description = "Invalid filters", body = ErrorBody),
+        )
+    )]
+    #[get("")]
+    pub async fn list_audit_entries(
+        state: Data<AppState>,
+        query: Query<AuditQuery>,
+    ) -> Result<HttpResponse, ApiError> {
+        let query = query.into_inner();
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+
+        let mut validator = Validator::new();
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            validator.add("limit", format!("must be between 1 and {MAX_LIMIT}"));
+        }
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if to < from {
+                validator.add("to", "must not be before from");
+            }
+        }
+        validator.into_result()?;
+
+        let restaurant = &state.restaurant;
+        let to = query
+            .to
+            .map(|to| {
+                to.checked_add_days(Days::new(1))
+                    .map(|end| restaurant.business_day_start(end))
+                    .ok_or_else(|| ApiError::invalid("to", "is out of range"))
+            })
+            .transpose()?;
+
+        let entries = state
+            .pg_db
+            .send(FetchAuditLog {
+                entity: query.entity,
+                entity_id: query.entity_id,
+                action: query.action,
+                worker_id: query.worker_id,
+                from: query.from.map(|from| restaurant.business_day_start(from)),
+                to,
+                before_id: query.before_id,
+                limit,
+            })
+            .await??;
+
+        Ok(HttpResponse::Ok().json(entries))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(list_audit_entries))]
+    pub struct ApiDoc;
+}
@@ -0,0 +1,76 @@
+//! Trail of changes made through the service. Clients tell the acting worker with the
+//! `X-Worker-Id` header, mutations of the database record it along with the changed values in
+//! the same transaction, see [`AsWorker`]. Requests without the header are recorded with no
+//! worker, e.g. those of guests
+
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use serde_json::{Map, Value};
+
+use crate::errors::ApiError;
+use crate::services::messages::AsWorker;
+
+pub const WORKER_HEADER: &str = "X-Worker-Id";
+
+/// Worker of the `X-Worker-Id` header, if any
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Worker(pub Option<i32>);
+
+impl Worker {
+    pub fn acting<M>(self, msg: M) -> AsWorker<M> {
+        AsWorker {
+            worker_id: self.0,
+            msg,
+        }
+    }
+}
+
+impl FromRequest for Worker {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let worker = match req.headers().get(WORKER_HEADER) {
+            None => Ok(Worker(None)),
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse::<i32>().ok())
+                .filter(|worker_id| *worker_id > 0)
+                .map(|worker_id| Worker(Some(worker_id)))
+                .ok_or_else(|| {
+                    ApiError::BadRequest(format!("{WORKER_HEADER} header must be a worker id"))
+                }),
+        };
+
+        ready(worker)
+    }
+}
+
+/// Keeps only the fields that differ, as `{"before": {..}, "after": {..}}`. A created or
+/// deleted entity has `null` on the missing side and every field on the other one
+pub fn changes(before: Value, after: Value) -> Value {
+    let (Value::Object(before), Value::Object(after)) = (&before, &after) else {
+        return serde_json::json!({ "before": before, "after": after });
+    };
+
+    let mut changed_before = Map::new();
+    let mut changed_after = Map::new();
+
+    let keys = before
+        .keys()
+        .chain(after.keys().filter(|key| !before.contains_key(*key)));
+
+    for key in keys {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+
+        if old != new {
+            changed_before.insert(key.clone(), old.clone());
+            changed_after.insert(key.clone(), new.clone());
+        }
+    }
+
+    serde_json::json!({ "before": changed_before, "after": changed_after })
+}
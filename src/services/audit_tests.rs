@@ -0,0 +1,168 @@
+//! Checks the diffs of audit entries and the worker taken from request headers.
+
+use actix_web::test::TestRequest;
+use actix_web::FromRequest;
+use serde_json::{json, Value};
+
+use crate::errors::ApiError;
+use crate::services::audit::{changes, Worker, WORKER_HEADER};
+
+use diesel::prelude::*;
+
+use crate::services::messages::{AsWorker, CheckActingWorker, ConfirmOrder, CookOrder};
+use crate::services::test_db::{add_dishes, setup};
+
+async fn worker_of(req: TestRequest) -> Result<Worker, ApiError> {
+    let (req, mut payload) = req.to_http_parts();
+
+    Worker::from_request(&req, &mut payload).await
+}
+
+#[test]
+fn diff_keeps_only_changed_fields() {
+    let diff = changes(
+        json!({ "id": 1, "is_paid": false, "total_cost": 300, "note": "window" }),
+        json!({ "id": 1, "is_paid": true, "total_cost": 300, "tip": 20 }),
+    );
+
+    assert_eq!(
+        diff,
+        json!({
+            "before": { "is_paid": false, "note": "window", "tip": null },
+            "after": { "is_paid": true, "note": null, "tip": 20 },
+        })
+    );
+}
+
+#[test]
+fn created_entity_has_every_field_after() {
+    let diff = changes(Value::Null, json!({ "id": 7, "name": "Soup" }));
+
+    assert_eq!(
+        diff,
+        json!({ "before": null, "after": { "id": 7, "name": "Soup" } })
+    );
+}
+
+#[actix_web::test]
+async fn worker_is_taken_from_header() {
+    assert_eq!(worker_of(TestRequest::get()).await.unwrap(), Worker(None));
+
+    let req = TestRequest::get().insert_header((WORKER_HEADER, "12"));
+    assert_eq!(worker_of(req).await.unwrap(), Worker(Some(12)));
+
+    for value in ["waiter", "0", "-3"] {
+        let req = TestRequest::get().insert_header((WORKER_HEADER, value));
+
+        assert!(
+            matches!(worker_of(req).await, Err(ApiError::BadRequest(_))),
+            "{value} must be rejected"
+        );
+    }
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn audit_entry_records_acting_worker_and_is_append_only() {
+    use crate::schema::{audit_log, worker, worker_role};
+
+    let fx = setup("audit actor").await;
+
+    let mut conn = fx.pool.get().unwrap();
+    let role_id = diesel::insert_into(worker_role::table)
+        .values(worker_role::title.eq("waiter"))
+        .returning(worker_role::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let worker_id = diesel::insert_into(worker::table)
+        .values((
+            worker::first_name.eq("Audit"),
+            worker::last_name.eq("Actor"),
+            worker::role_id.eq(role_id),
+        ))
+        .returning(worker::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+
+    add_dishes(&fx, 2).await;
+    fx.pg_db
+        .send(AsWorker {
+            worker_id: Some(worker_id),
+            msg: ConfirmOrder(fx.order_id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (entry_id, entry_worker_id, diff) = audit_log::table
+        .filter(audit_log::entity.eq("order"))
+        .filter(audit_log::entity_id.eq(fx.order_id.to_string()))
+        .filter(audit_log::action.eq("confirm"))
+        .select((audit_log::id, audit_log::worker_id, audit_log::diff))
+        .get_result::<(i64, Option<i32>, serde_json::Value)>(&mut conn)
+        .unwrap();
+
+    assert_eq!(entry_worker_id, Some(worker_id));
+    assert_eq!(diff["before"]["is_confirmed"], false);
+    assert_eq!(diff["after"]["is_confirmed"], true);
+
+    // the worker is not kept for messages sent without one
+    let line_worker_ids = audit_log::table
+        .filter(audit_log::entity_id.eq(fx.order_id.to_string()))
+        .filter(audit_log::action.eq("add_line"))
+        .select(audit_log::worker_id)
+        .load::<Option<i32>>(&mut conn)
+        .unwrap();
+    assert_eq!(line_worker_ids, vec![None, None]);
+
+    let rewritten = diesel::update(audit_log::table.find(entry_id))
+        .set(audit_log::action.eq("cook"))
+        .execute(&mut conn);
+    assert!(rewritten.is_err(), "audit log must reject updates");
+
+    let unknown_worker = fx
+        .pg_db
+        .send(AsWorker {
+            worker_id: Some(i32::MAX),
+            msg: CookOrder(fx.order_id),
+        })
+        .await
+        .unwrap();
+    assert!(
+        matches!(unknown_worker, Err(ApiError::BadRequest(_))),
+        "{unknown_worker:?}"
+    );
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn unknown_worker_is_found_before_menus_change() {
+    use crate::schema::{worker, worker_role};
+
+    let fx = setup("menu worker").await;
+
+    let mut conn = fx.pool.get().unwrap();
+    let role_id = diesel::insert_into(worker_role::table)
+        .values(worker_role::title.eq("manager"))
+        .returning(worker_role::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let worker_id = diesel::insert_into(worker::table)
+        .values((
+            worker::first_name.eq("Menu"),
+            worker::last_name.eq("Author"),
+            worker::role_id.eq(role_id),
+        ))
+        .returning(worker::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+
+    let known = fx.pg_db.send(CheckActingWorker(worker_id)).await.unwrap();
+    assert!(known.is_ok(), "{known:?}");
+
+    let unknown = fx.pg_db.send(CheckActingWorker(i32::MAX)).await.unwrap();
+    assert!(
+        matches!(unknown, Err(ApiError::BadRequest(_))),
+        "{unknown:?}"
+    );
+}
@@ -0,0 +1,96 @@
+//! Conditional GET of cached menus. Guest pages poll the menu, so clients holding its current
+//! revision get `304 Not Modified` without the body.
+
+use std::time::SystemTime;
+
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ETag, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch,
+    LastModified, TryIntoHeaderValue,
+};
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::errors::ApiError;
+use crate::types::MenuRevision;
+
+/// `ETag` and `Last-Modified` of a cached menu or one of its dishes
+pub struct Validators {
+    etag: EntityTag,
+    last_modified: HttpDate,
+}
+
+impl Validators {
+    pub fn of_menu(revision: &MenuRevision) -> Self {
+        Validators {
+            etag: EntityTag::new_strong(format!("{}.{}", revision.menu_key, revision.version)),
+            last_modified: SystemTime::from(revision.modified_at).into(),
+        }
+    }
+
+    /// Dishes have no revisions of their own, so any change of the menu changes theirs
+    pub fn of_dish(revision: &MenuRevision, dish_id: i64) -> Self {
+        Validators {
+            etag: EntityTag::new_strong(format!(
+                "{}.{}.dish-{dish_id}",
+                revision.menu_key, revision.version
+            )),
+            last_modified: SystemTime::from(revision.modified_at).into(),
+        }
+    }
+
+    /// `If-Modified-Since` is only looked at when `If-None-Match` is absent
+    fn is_fresh(&self, req: &HttpRequest) -> bool {
+        // list headers parse into an empty list when absent
+        if req.headers().contains_key(IfNoneMatch::name()) {
+            return match IfNoneMatch::parse(req) {
+                Ok(IfNoneMatch::Any) => true,
+                Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&self.etag)),
+                Err(_) => false,
+            };
+        }
+
+        match IfModifiedSince::parse(req) {
+            Ok(IfModifiedSince(since)) => {
+                SystemTime::from(self.last_modified) <= SystemTime::from(since)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Responds with `304 Not Modified` when the client's copy is fresh, otherwise with the result
+/// of `respond`. Successful responses are cacheable by anyone for `max_age_s`, then they are
+/// revalidated
+pub fn conditional(
+    req: &HttpRequest,
+    validators: Option<Validators>,
+    max_age_s: u32,
+    respond: impl FnOnce() -> Result<HttpResponse, ApiError>,
+) -> Result<HttpResponse, ApiError> {
+    let mut resp = match &validators {
+        Some(validators) if validators.is_fresh(req) => HttpResponse::NotModified().finish(),
+        _ => respond()?,
+    };
+
+    insert_header(
+        &mut resp,
+        CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(max_age_s),
+            CacheDirective::MustRevalidate,
+        ]),
+    );
+
+    if let Some(validators) = validators {
+        insert_header(&mut resp, ETag(validators.etag));
+        insert_header(&mut resp, LastModified(validators.last_modified));
+    }
+
+    Ok(resp)
+}
+
+/// Headers composed here are always valid, so encoding errors are not expected
+fn insert_header<H: Header>(resp: &mut HttpResponse, header: H) {
+    if let Ok(value) = header.try_into_value() {
+        resp.headers_mut().insert(H::name(), value);
+    }
+}
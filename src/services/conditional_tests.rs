@@ -0,0 +1,101 @@
+//! Checks validators of cached menus against conditional request headers.
+
+use actix_web::http::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::HttpResponse;
+use chrono::{TimeZone, Utc};
+
+use crate::services::conditional::{conditional, Validators};
+use crate::types::{CachedJson, MenuRevision};
+
+fn revision() -> MenuRevision {
+    MenuRevision {
+        menu_key: "menu_2026-10-19".to_owned(),
+        version: 3,
+        modified_at: Utc.with_ymd_and_hms(2026, 10, 19, 9, 30, 0).unwrap(),
+    }
+}
+
+fn respond(req: TestRequest) -> HttpResponse {
+    conditional(
+        &req.to_http_request(),
+        Some(Validators::of_menu(&revision())),
+        10,
+        || Ok(HttpResponse::Ok().body("{}")),
+    )
+    .unwrap()
+}
+
+#[test]
+fn validators_are_sent_with_the_body() {
+    let resp = respond(TestRequest::get());
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(ETAG).unwrap(), "\"menu_2026-10-19.3\"");
+    assert_eq!(
+        resp.headers().get(LAST_MODIFIED).unwrap(),
+        "Mon, 19 Oct 2026 09:30:00 GMT"
+    );
+}
+
+#[test]
+fn current_etag_is_not_modified() {
+    let resp = respond(
+        TestRequest::get().insert_header((IF_NONE_MATCH, "\"stale\", W/\"menu_2026-10-19.3\"")),
+    );
+
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(resp.headers().get(ETAG).unwrap(), "\"menu_2026-10-19.3\"");
+}
+
+#[test]
+fn stale_etag_wins_over_modification_date() {
+    let resp = respond(
+        TestRequest::get()
+            .insert_header((IF_NONE_MATCH, "\"menu_2026-10-19.2\""))
+            .insert_header((IF_MODIFIED_SINCE, "Mon, 19 Oct 2026 10:00:00 GMT")),
+    );
+
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[test]
+fn modification_date_is_compared_without_etag() {
+    let since = |date: &'static str| TestRequest::get().insert_header((IF_MODIFIED_SINCE, date));
+
+    assert_eq!(
+        respond(since("Mon, 19 Oct 2026 09:30:00 GMT")).status(),
+        StatusCode::NOT_MODIFIED
+    );
+    assert_eq!(
+        respond(since("Mon, 19 Oct 2026 09:29:59 GMT")).status(),
+        StatusCode::OK
+    );
+}
+
+#[test]
+fn active_menu_is_not_modified_before_its_activation() {
+    let activated_at = Utc.with_ymd_and_hms(2026, 10, 19, 11, 0, 0).unwrap();
+    let menu = CachedJson {
+        json: "{}".to_owned(),
+        revision: Some(revision()),
+    }
+    .activated_at(Some(activated_at));
+
+    let resp = conditional(
+        &TestRequest::get()
+            .insert_header((IF_MODIFIED_SINCE, "Mon, 19 Oct 2026 10:00:00 GMT"))
+            .to_http_request(),
+        menu.revision.as_ref().map(Validators::of_menu),
+        10,
+        || Ok(HttpResponse::Ok().body(menu.json.clone())),
+    )
+    .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(LAST_MODIFIED).unwrap(),
+        "Mon, 19 Oct 2026 11:00:00 GMT"
+    );
+}
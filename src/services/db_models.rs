@@ -1,11 +1,26 @@
 #![allow(unused)]
 #![allow(clippy::all)]
 
-use crate::types::DishType;
-use chrono::{serde::ts_seconds_option, DateTime, NaiveDate, NaiveDateTime, Utc};
+use crate::types::{Allergen, DietaryLabel, DishType};
+use chrono::{serde::ts_seconds_option, DateTime, NaiveDate, Utc};
 use diesel::{Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use utoipa::ToSchema;
+
+/// Change of an entity by a worker, `diff` holds the changed values as they were and as they
+/// became
+#[derive(Queryable, Debug, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub worker_id: Option<i32>,
+    pub entity: String,
+    pub entity_id: String,
+    pub action: String,
+    #[schema(value_type = Object)]
+    pub diff: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
 
 #[derive(Queryable, Debug, Serialize)]
 pub struct DishToOrder {
@@ -13,6 +28,8 @@ pub struct DishToOrder {
     pub dish_id: i64,
     pub order_id: i64,
     pub count: i32,
+    pub note: Option<String>,
+    pub line_key: String,
 }
 
 #[derive(Queryable, Debug, Serialize)]
@@ -23,7 +40,7 @@ pub struct DishToProduct {
     pub weight_g: i32,
 }
 
-#[derive(Queryable, Debug, Serialize, Deserialize, Clone)]
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Dish {
     pub id: i64,
     pub name: String,
@@ -31,9 +48,40 @@ pub struct Dish {
     pub portion_weight_g: i32,
     pub price: i32,
     pub approx_cook_time_s: i32,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub dietary_labels: Vec<DietaryLabel>,
+}
+
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ModifierGroup {
+    pub id: i64,
+    pub dish_id: i64,
+    pub name: String,
+    /// several modifiers of the group may be chosen at once
+    pub is_multiple: bool,
+    /// at least one modifier of the group must be chosen
+    pub is_required: bool,
+}
+
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct Modifier {
+    pub id: i64,
+    pub group_id: i64,
+    pub name: String,
+    pub price_delta: i32,
+}
+
+#[derive(Queryable, Debug, Serialize)]
+pub struct ModifierProduct {
+    pub id: i64,
+    pub modifier_id: i64,
+    pub product_id: i64,
+    pub weight_delta_g: i32,
 }
 
-#[derive(Queryable, Debug, Serialize, Deserialize, Clone)]
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Order {
     pub id: i64,
     pub table_id: i64,
@@ -41,16 +89,29 @@ pub struct Order {
     pub is_confirmed: bool,
     pub is_paid: bool,
     pub is_cooked: bool,
-    pub created_at: NaiveDateTime,
-    pub cooked_at: Option<NaiveDateTime>,
-    pub confirmed_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub cooked_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// waiter of the table when the order was created or handed over
+    pub waiter_id: Option<i64>,
+    pub paid_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Queryable, Debug, Serialize)]
+#[derive(Queryable, Debug, Serialize, ToSchema)]
 pub struct Product {
     pub id: i64,
     pub name: String,
     pub in_stock_g: i32,
+    pub allergens: Vec<Allergen>,
+    pub cost_per_kg: i32,
+}
+
+#[derive(Queryable, Debug, Serialize, ToSchema)]
+pub struct ProductCost {
+    pub id: i64,
+    pub product_id: i64,
+    pub cost_per_kg: i32,
+    pub valid_from: DateTime<Utc>,
 }
 
 #[derive(Queryable, Debug, Serialize)]
@@ -60,21 +121,45 @@ pub struct Stats {
     pub income: i32,
 }
 
-#[derive(Queryable, Debug, Serialize)]
+#[derive(Queryable, Debug, Serialize, ToSchema)]
 pub struct Table {
     pub id: i64,
     pub seat_count: i32,
     pub is_occupied: bool,
-    pub reserved_at: Option<NaiveDateTime>,
+    pub reserved_at: Option<DateTime<Utc>>,
     pub reserved_by: Option<String>,
     pub waiter_id: Option<i64>,
 }
 
-#[derive(Queryable, Debug, Serialize)]
+/// Time a waiter or another worker is clocked in, `ended_at` is missing while the shift goes on.
+/// A waiter with a staff record has both ids
+#[derive(Queryable, Debug, Serialize, Clone, ToSchema)]
+pub struct Shift {
+    pub id: i64,
+    pub waiter_id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub worker_id: Option<i32>,
+}
+
+#[derive(Queryable, Debug, Clone, Serialize, ToSchema)]
 pub struct Waiter {
     pub id: i64,
     pub first_name: String,
     pub last_name: String,
+    /// staff record of the waiter, its role decides the share of pooled tips
+    pub worker_id: Option<i32>,
+}
+
+/// Gratuity paid along with the order, it is not a part of the restaurant income
+#[derive(Queryable, Debug, Serialize, ToSchema)]
+pub struct Tip {
+    pub id: i64,
+    pub order_id: i64,
+    /// waiter of the table when the order was paid
+    pub waiter_id: Option<i64>,
+    pub amount: i32,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Queryable, Debug, Serialize)]
@@ -83,8 +168,8 @@ pub struct Worker {
     pub first_name: String,
     pub last_name: String,
     pub role_id: i32,
-    pub created_at: NaiveDateTime,
-    pub deleted_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Queryable, Debug, Serialize)]
@@ -94,14 +179,14 @@ pub struct WorkerAuth {
     pub password: String,
     pub token: Option<String>,
     pub worker_id: i32,
-    pub created_at: NaiveDateTime,
-    pub deleted_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Queryable, Debug, Serialize)]
 pub struct WorkerRole {
     pub id: i32,
     pub title: String,
-    pub created_at: NaiveDateTime,
-    pub deleted_at: Option<NaiveDateTime>,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
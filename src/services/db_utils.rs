@@ -1,25 +1,81 @@
+use crate::services::guest::TableTokens;
+use crate::services::redis_handling::RedisHandler;
+use std::time::Duration;
+
 use actix::{Actor, Addr, SyncContext};
-use diesel::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
-use crate::services::redis_handling::RedisHandler;
+use diesel::PgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use crate::settings::{RestaurantSettings, TipSettings};
+use crate::types::{DishType, MigrationError, PoolInitializationError};
 
-use crate::types::PoolInitializationError;
+/// Every migration of `migrations` directory, built into the binary
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
 pub struct PgActor(pub Pool<ConnectionManager<PgConnection>>);
 
 pub struct AppState {
     pub pg_db: Addr<PgActor>,
-    pub redis_handler: RedisHandler
+    pub redis_handler: RedisHandler,
+    pub course_order: Vec<DishType>,
+    /// dishes with lower gross margin (in percents of price) are flagged in costing report
+    pub min_margin_pct: f64,
+    /// for how long clients and proxies may show a menu before revalidating it
+    pub menu_max_age_s: u32,
+    /// for how long responses to requests with `Idempotency-Key` are replayed
+    pub idempotency_ttl_s: u64,
+    /// limits each dependency probe of the readiness check
+    pub health_timeout_ms: u64,
+    /// signs and checks tokens of tables in guest QR codes
+    pub table_tokens: TableTokens,
+    /// page of the guest app, QR codes link to `{guest_app_url}/{token}`
+    pub guest_app_url: String,
+    /// requests guests of one table may send per minute
+    pub guest_requests_per_minute: u64,
+    /// how tips are shared between waiters and the pooled roles
+    pub tips: TipSettings,
+    pub restaurant: RestaurantSettings,
 }
 
 impl Actor for PgActor {
     type Context = SyncContext<Self>;
 }
 
-pub fn get_db_pool(db_url: &str) -> Result<Pool<ConnectionManager<PgConnection>>, PoolInitializationError> {
+pub fn get_db_pool(
+    db_url: &str,
+    pool_size: u32,
+    connection_timeout: Duration,
+) -> Result<Pool<ConnectionManager<PgConnection>>, PoolInitializationError> {
     let manager: ConnectionManager<PgConnection> = ConnectionManager::<PgConnection>::new(db_url);
-    match Pool::builder().build(manager) {
+    match Pool::builder()
+        .max_size(pool_size)
+        .connection_timeout(connection_timeout)
+        .build(manager)
+    {
         Ok(val) => Ok(val),
-        Err(err) => Err(PoolInitializationError(err.to_string()))
+        Err(err) => Err(PoolInitializationError(err.to_string())),
     }
-}
\ No newline at end of file
+}
+
+/// Versions of migrations that are not applied to the database yet
+pub fn pending_migrations(conn: &mut PgConnection) -> Result<Vec<String>, MigrationError> {
+    let pending = conn
+        .pending_migrations(MIGRATIONS)
+        .map_err(|err| MigrationError(err.to_string()))?;
+
+    Ok(pending
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect())
+}
+
+/// Applies pending migrations one by one, each in its own transaction.
+/// Returns versions of the applied ones
+pub fn run_migrations(conn: &mut PgConnection) -> Result<Vec<String>, MigrationError> {
+    let applied = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(|err| MigrationError(err.to_string()))?;
+
+    Ok(applied.iter().map(|version| version.to_string()).collect())
+}
@@ -0,0 +1,79 @@
+//! Creates and renames dishes against a real database, see [`crate::services::test_db`].
+
+use futures::future::join_all;
+
+use crate::errors::ApiError;
+use crate::services::messages::{CreateDish, UpdateDish};
+use crate::services::test_db::{setup, CONCURRENCY, DISH_PRICE};
+use crate::types::DishType;
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn one_of_concurrent_dishes_gets_the_name() {
+    let fx = setup("dish name").await;
+
+    let name = format!("Twin {}", fx.dish_id);
+    let results = join_all((0..CONCURRENCY).map(|_| {
+        fx.pg_db.send(CreateDish {
+            dish_name: name.clone(),
+            dish_type: DishType::Main,
+            price: DISH_PRICE,
+            approx_cook_time_s: 600,
+            portion_weight_g: 300,
+            description: Some("Twin dish".to_owned()),
+            image_url: None,
+            dietary_labels: vec![],
+            ingredients: vec![],
+        })
+    }))
+    .await;
+
+    let mut created = vec![];
+    for res in results {
+        match res.unwrap() {
+            Ok(dish) => created.push(dish),
+            Err(ApiError::Conflict { code, .. }) => assert_eq!(code, "dish_name_taken"),
+            Err(err) => panic!("unexpected error: {err:?}"),
+        }
+    }
+    assert_eq!(created.len(), 1);
+
+    let renamed = fx
+        .pg_db
+        .send(UpdateDish {
+            dish_id: fx.dish_id,
+            dish_name: Some(name),
+            dish_type: None,
+            price: None,
+            approx_cook_time_s: None,
+            portion_weight_g: None,
+            description: None,
+            image_url: None,
+            dietary_labels: None,
+        })
+        .await
+        .unwrap();
+    assert!(
+        matches!(&renamed, Err(ApiError::Conflict { code, .. }) if *code == "dish_name_taken"),
+        "{renamed:?}"
+    );
+
+    // `Some(None)` clears the description, `None` keeps the image
+    let cleared = fx
+        .pg_db
+        .send(UpdateDish {
+            dish_id: created[0].id,
+            dish_name: None,
+            dish_type: None,
+            price: None,
+            approx_cook_time_s: None,
+            portion_weight_g: None,
+            description: Some(None),
+            image_url: None,
+            dietary_labels: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cleared.description, None);
+}
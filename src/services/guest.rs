@@ -0,0 +1,243 @@
+//! Ordering by guests from their phones. The QR code on a table links to the guest app with
+//! the token of the table, which can't be forged without the secret. Guests read the active
+//! menu and add dishes to the unconfirmed order of their table, the waiter reviews and confirms
+//! it as any other order. Routes take the table from the token only, so no other order is reached
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::Payload;
+use actix_web::web::{Data, Json, Query};
+use actix_web::{get, post, FromRequest, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use futures::future::LocalBoxFuture;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::{ApiError, ErrorBody};
+use crate::services::audit::Worker;
+use crate::services::conditional::{conditional, Validators};
+use crate::services::db_utils::AppState;
+use crate::services::idempotency::IdempotencyKey;
+use crate::services::menu_route::{filtered_menu, MenuFilter};
+use crate::services::messages::{FetchOrder, FetchTableOrder, OpenTableOrder};
+use crate::services::order_route::{add_line, AddDishBody};
+use crate::types::{Menu, OrderInfo};
+
+/// Bytes of the signature kept in tokens, enough against guessing and short for QR codes
+const SIGNATURE_LEN: usize = 16;
+const RATE_LIMIT_WINDOW_S: u64 = 60;
+
+/// Signs tokens of the form `{table_id}.{signature}`, where the signature is a truncated
+/// HMAC-SHA256 of the table id
+#[derive(Clone)]
+pub struct TableTokens {
+    mac: Hmac<Sha256>,
+}
+
+impl TableTokens {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            mac: Hmac::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size"),
+        }
+    }
+
+    pub fn sign(&self, table_id: i64) -> String {
+        let signature = self.table_mac(table_id).finalize().into_bytes();
+
+        format!(
+            "{table_id}.{}",
+            URL_SAFE_NO_PAD.encode(&signature[..SIGNATURE_LEN])
+        )
+    }
+
+    /// Returns id of the table the token is signed for
+    pub fn verify(&self, token: &str) -> Option<i64> {
+        let (id, signature) = token.split_once('.')?;
+        let table_id = id.parse::<i64>().ok()?;
+
+        // "+1" or "01" are parsed too, but they are never signed
+        if table_id.to_string() != id {
+            return None;
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        if signature.len() != SIGNATURE_LEN {
+            return None;
+        }
+
+        self.table_mac(table_id)
+            .verify_truncated_left(&signature)
+            .ok()
+            .map(|_| table_id)
+    }
+
+    fn table_mac(&self, table_id: i64) -> Hmac<Sha256> {
+        let mut mac = self.mac.clone();
+        mac.update(format!("table:{table_id}").as_bytes());
+
+        mac
+    }
+}
+
+/// Table of the `{token}` path segment. Extracting it counts the request against the limit
+/// of the table, so it must go before other extractors of guest routes
+pub struct GuestTable(pub i64);
+
+impl FromRequest for GuestTable {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let state = req.app_data::<Data<AppState>>().cloned();
+        let token = req.match_info().get("token").map(str::to_owned);
+
+        Box::pin(async move {
+            let state = state
+                .ok_or_else(|| ApiError::Internal("Application state is missing".to_owned()))?;
+            let table_id = token
+                .and_then(|token| state.table_tokens.verify(&token))
+                .ok_or_else(|| ApiError::NotFound("Table token is not valid".to_owned()))?;
+
+            limit_requests(&state, table_id).await?;
+
+            Ok(GuestTable(table_id))
+        })
+    }
+}
+
+/// Guests of one table share the limit, so one phone can't flood the kitchen with orders
+async fn limit_requests(state: &AppState, table_id: i64) -> Result<(), ApiError> {
+    let now_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    let count = state
+        .redis_handler
+        .count_request(&format!("table-{table_id}"), RATE_LIMIT_WINDOW_S, now_s)
+        .await?;
+
+    if count > state.guest_requests_per_minute {
+        return Err(ApiError::TooManyRequests {
+            message: format!(
+                "Table {table_id} sent more than {} requests in a minute",
+                state.guest_requests_per_minute
+            ),
+            retry_after_s: RATE_LIMIT_WINDOW_S - now_s % RATE_LIMIT_WINDOW_S,
+        });
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    params(("token" = String, Path, description = "Token of the table"), MenuFilter),
+    responses(
+        (status = 200, description = "Active menu", body = Menu),
+        (status = 304, description = "Client's copy is still current"),
+        (status = 404, description = "Token is not valid or no menu is active", body = ErrorBody),
+        (status = 422, description = "Unknown allergen", body = ErrorBody),
+        (status = 429, description = "Too many requests from the table", body = ErrorBody),
+    )
+)]
+#[get("/tables/{token}/menu")]
+pub async fn show_guest_menu(
+    _table: GuestTable,
+    req: HttpRequest,
+    state: Data<AppState>,
+    filter: Query<MenuFilter>,
+) -> Result<HttpResponse, ApiError> {
+    let menu = state.redis_handler.get_menu().await?;
+    let validators = menu.revision.as_ref().map(Validators::of_menu);
+
+    conditional(&req, validators, state.menu_max_age_s, || {
+        filtered_menu(menu.json, &filter)
+    })
+}
+
+#[utoipa::path(
+    params(("token" = String, Path, description = "Token of the table")),
+    responses(
+        (status = 200, description = "The latest unpaid order of the table", body = OrderInfo),
+        (
+            status = 404,
+            description = "Token is not valid or the table has no unpaid order",
+            body = ErrorBody
+        ),
+        (status = 429, description = "Too many requests from the table", body = ErrorBody),
+    )
+)]
+#[get("/tables/{token}/order")]
+pub async fn show_guest_order(
+    table: GuestTable,
+    state: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let order = state.pg_db.send(FetchTableOrder(table.0)).await??;
+
+    Ok(HttpResponse::Ok().json(order))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GuestLineBody {
+    dish_id: i64,
+    #[serde(flatten)]
+    line: AddDishBody,
+}
+
+#[utoipa::path(
+    params(("token" = String, Path, description = "Token of the table"), IdempotencyKey),
+    request_body = GuestLineBody,
+    responses(
+        (
+            status = 201,
+            description = "Unconfirmed order of the table with the new line",
+            body = OrderInfo
+        ),
+        (status = 404, description = "Token is not valid", body = ErrorBody),
+        (
+            status = 409,
+            description = "Waiter has just confirmed the order, the line may be added again",
+            body = ErrorBody
+        ),
+        (
+            status = 422,
+            description = "Dish is not in the active menu, invalid modifiers or note",
+            body = ErrorBody
+        ),
+        (status = 429, description = "Too many requests from the table", body = ErrorBody),
+    )
+)]
+#[post("/tables/{token}/order/lines")]
+pub async fn add_guest_order_line(
+    table: GuestTable,
+    state: Data<AppState>,
+    body: Json<GuestLineBody>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+
+    // unlike waiters, guests order only what they are shown
+    match state.redis_handler.get_dish(body.dish_id).await {
+        Ok(_) => {}
+        Err(ApiError::NotFound(_)) => {
+            return Err(ApiError::invalid("dish_id", "is not in the active menu"));
+        }
+        Err(err) => return Err(err),
+    }
+
+    let order_id = state.pg_db.send(OpenTableOrder(table.0)).await??;
+
+    // guests are not workers, their lines are audited without one
+    add_line(&state, Worker(None), order_id, body.dish_id, body.line).await?;
+
+    let order = state.pg_db.send(FetchOrder(order_id)).await??;
+
+    Ok(HttpResponse::Created().json(order))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(show_guest_menu, show_guest_order, add_guest_order_line))]
+pub struct ApiDoc;
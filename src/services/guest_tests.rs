@@ -0,0 +1,94 @@
+//! Checks signing of table tokens and the response of exceeded guest limits.
+
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+
+use crate::errors::ApiError;
+use crate::services::guest::TableTokens;
+
+use futures::future::join_all;
+
+use crate::services::messages::{ConfirmOrder, FetchTableOrder, OpenTableOrder};
+use crate::services::test_db::{add_dishes, setup, CONCURRENCY};
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+#[test]
+fn signed_token_tells_its_table() {
+    let tokens = TableTokens::new(SECRET);
+    let token = tokens.sign(12);
+
+    assert!(token.starts_with("12."), "{token}");
+    assert_eq!(tokens.verify(&token), Some(12));
+}
+
+#[test]
+fn forged_tokens_are_rejected() {
+    let tokens = TableTokens::new(SECRET);
+    let token = tokens.sign(12);
+    let (_, signature) = token.split_once('.').unwrap();
+
+    let other_table = format!("13.{signature}");
+    let padded_id = format!("012.{signature}");
+    let truncated = format!("12.{}", &signature[..4]);
+    let other_secret = TableTokens::new("fedcba9876543210fedcba9876543210").sign(12);
+
+    for forged in [
+        &other_table,
+        &padded_id,
+        &truncated,
+        &other_secret,
+        "12",
+        "12.",
+    ] {
+        assert_eq!(tokens.verify(forged), None, "{forged} must be rejected");
+    }
+}
+
+#[test]
+fn exceeded_limit_tells_when_to_retry() {
+    let resp = ApiError::TooManyRequests {
+        message: "Table 12 sent more than 60 requests in a minute".to_owned(),
+        retry_after_s: 17,
+    }
+    .error_response();
+
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "17");
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn guests_of_a_table_share_one_open_order() {
+    let fx = setup("guest orders").await;
+
+    add_dishes(&fx, 1).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let results =
+        join_all((0..CONCURRENCY).map(|_| fx.pg_db.send(OpenTableOrder(fx.table_id)))).await;
+    let order_ids = results
+        .into_iter()
+        .map(|res| res.unwrap().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_ne!(
+        order_ids[0], fx.order_id,
+        "confirmed order must not be extended"
+    );
+    assert!(order_ids.iter().all(|order_id| *order_id == order_ids[0]));
+
+    let info = fx
+        .pg_db
+        .send(FetchTableOrder(fx.table_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(info.order.id, order_ids[0]);
+}
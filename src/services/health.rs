@@ -0,0 +1,107 @@
+//! Probes for the container orchestrator. Liveness tells the process is able to respond,
+//! readiness tells it is able to serve requests, so traffic should be routed to it.
+
+use std::time::{Duration, Instant};
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web::Data;
+use actix_web::{get, HttpResponse};
+use serde_json::json;
+use utoipa::OpenApi;
+
+use crate::errors::ApiError;
+use crate::services::db_utils::AppState;
+use crate::services::messages::Ping;
+use crate::types::{DependencyHealth, HealthStatus, PoolStatus, ReadinessReport};
+
+#[utoipa::path(
+    responses(
+        (
+            status = 200,
+            description = "Process is running, dependencies are not checked",
+            body = Object,
+            example = json!({"status": "up"})
+        ),
+    )
+)]
+#[get("/live")]
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(json!({"status": HealthStatus::Up}))
+}
+
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Every dependency is up", body = ReadinessReport),
+        (status = 503, description = "Some dependency is down", body = ReadinessReport),
+    )
+)]
+#[get("/ready")]
+pub async fn readiness(state: Data<AppState>) -> HttpResponse {
+    let timeout = Duration::from_millis(state.health_timeout_ms);
+
+    let postgres = probe(|| async {
+        match tokio::time::timeout(timeout, state.pg_db.send(Ping { timeout })).await {
+            Ok(pool) => Ok(Some(pool??)),
+            Err(_) => Err(ApiError::Unavailable(format!(
+                "Database did not respond in {}ms",
+                timeout.as_millis()
+            ))),
+        }
+    })
+    .await;
+    let redis = probe(|| async {
+        state.redis_handler.ping(timeout).await?;
+        Ok(None)
+    })
+    .await;
+
+    let report = ReadinessReport {
+        status: if postgres.status == HealthStatus::Up && redis.status == HealthStatus::Up {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        },
+        postgres,
+        redis,
+    };
+
+    let mut resp = match report.status {
+        HealthStatus::Up => HttpResponse::Ok(),
+        HealthStatus::Down => HttpResponse::ServiceUnavailable(),
+    };
+
+    resp.insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(report)
+}
+
+/// Measures the check, its error is reported instead of failing the request
+async fn probe<F, Fut>(check: F) -> DependencyHealth
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<Option<PoolStatus>, ApiError>>,
+{
+    let started = Instant::now();
+    let result = check().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(pool) => DependencyHealth {
+            status: HealthStatus::Up,
+            latency_ms,
+            error: None,
+            pool,
+        },
+        Err(err) => DependencyHealth {
+            status: HealthStatus::Down,
+            latency_ms,
+            error: Some(err.to_string()),
+            pool: None,
+        },
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(liveness, readiness))]
+pub struct ApiDoc;
@@ -0,0 +1,72 @@
+//! Runs the readiness check against a real database and an unreachable redis.
+//!
+//! Runs against the database of [`crate::services::test_db`].
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::SyncArbiter;
+use actix_web::http::StatusCode;
+use actix_web::web::Data;
+use actix_web::{test, App};
+use chrono::NaiveTime;
+use serde_json::Value;
+
+use crate::services::db_utils::{get_db_pool, AppState, PgActor};
+use crate::services::guest::TableTokens;
+use crate::services::health::readiness;
+use crate::services::redis_handling::RedisHandler;
+use crate::services::test_db::db_url;
+use crate::settings::{RestaurantSettings, TipSettings};
+
+/// Nothing listens on the port, so connecting is refused right away
+const UNREACHABLE_REDIS: &str = "redis://127.0.0.1:1";
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn readiness_reports_every_dependency() {
+    let pool = get_db_pool(&db_url(), 2, Duration::from_secs(5))
+        .expect("Unable to connect to the test database");
+    let redis = redis::Client::open(UNREACHABLE_REDIS).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(AppState {
+                pg_db: SyncArbiter::start(1, move || PgActor(pool.clone())),
+                redis_handler: RedisHandler::new(redis, Duration::from_secs(1)),
+                course_order: vec![],
+                min_margin_pct: 60.0,
+                menu_max_age_s: 10,
+                idempotency_ttl_s: 60,
+                health_timeout_ms: 1_000,
+                table_tokens: TableTokens::new("0123456789abcdef0123456789abcdef"),
+                guest_app_url: "http://localhost/guest".to_owned(),
+                guest_requests_per_minute: 60,
+                tips: TipSettings {
+                    waiter_share_percent: 100,
+                    pool_roles: HashMap::new(),
+                },
+                restaurant: RestaurantSettings {
+                    name: "Test".to_owned(),
+                    currency: "USD".to_owned(),
+                    opening_hours: vec![],
+                    timezone: chrono_tz::UTC,
+                    business_day_starts_at: NaiveTime::MIN,
+                },
+            }))
+            .service(readiness),
+    )
+    .await;
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/ready").to_request()).await;
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let report: Value = test::read_body_json(resp).await;
+
+    assert_eq!(report["status"], "down");
+    assert_eq!(report["postgres"]["status"], "up");
+    assert_eq!(report["postgres"]["pool"]["max_size"], 2);
+    assert_eq!(report["redis"]["status"], "down");
+    assert!(report["redis"]["error"].is_string());
+}
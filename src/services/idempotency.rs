@@ -0,0 +1,278 @@
+use std::rc::Rc;
+
+use actix_web::body::{to_bytes, BoxBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE};
+use actix_web::http::{Method, StatusCode};
+use actix_web::web::{Bytes, Data};
+use actix_web::{Error, FromRequest, HttpResponse, ResponseError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use futures::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+use utoipa::IntoParams;
+
+use crate::errors::ApiError;
+use crate::services::audit::Worker;
+use crate::services::db_utils::AppState;
+use crate::types::{
+    IdempotencyRecord, IDEMPOTENCY_HEADER, IDEMPOTENT_REPLAY_HEADER, MAX_IDEMPOTENCY_KEY_LEN,
+};
+
+/// Middleware for scopes with mutating routes. A request carrying `Idempotency-Key` header
+/// is performed once, repeats with the same key get the stored response of the first one.
+///
+/// Keys live apart per caller, a worker or a guest table, so equal keys of two callers never
+/// meet. The key is bound to the method, path and body it was first used with. Server errors
+/// are not stored, so such requests may be repeated with the same key
+#[derive(Clone, Copy)]
+pub enum Idempotent {
+    /// keys of every worker of the `X-Worker-Id` header are kept apart, requests without it
+    /// share the keys. The name keeps them apart from the keys of other scopes
+    Route(&'static str),
+    /// keys of every table are kept apart, the table is taken from the token of the path
+    GuestTable,
+}
+
+impl<S> Transform<S, ServiceRequest> for Idempotent
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = IdempotentMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotentMiddleware {
+            service: Rc::new(service),
+            scope: *self,
+        }))
+    }
+}
+
+pub struct IdempotentMiddleware<S> {
+    service: Rc<S>,
+    scope: Idempotent,
+}
+
+impl<S> Service<ServiceRequest> for IdempotentMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let scope = self.scope;
+
+        Box::pin(async move {
+            let Some(key) = idempotency_key(&req) else {
+                return service.call(req).await;
+            };
+
+            let key = match key {
+                Ok(key) => key,
+                Err(err) => return Ok(req.into_response(err.error_response())),
+            };
+
+            let Some(state) = req.app_data::<Data<AppState>>().cloned() else {
+                return service.call(req).await;
+            };
+
+            // a guest token or a worker id that is not valid is left to the route to reject
+            let Some(caller) = scope.caller(&req, &state) else {
+                return service.call(req).await;
+            };
+
+            // the body is read for the fingerprint and handed back to the route
+            let body = match req.extract::<Bytes>().await {
+                Ok(body) => body,
+                Err(err) => return Ok(req.into_response(err.error_response())),
+            };
+            let fingerprint = fingerprint(&req, &body);
+            let payload = stream::once(ready(Ok::<_, PayloadError>(body))).boxed_local();
+            req.set_payload(Payload::from(payload));
+
+            let key = format!("{caller}_{key}");
+            let ttl_s = state.idempotency_ttl_s;
+
+            let earlier = state
+                .redis_handler
+                .reserve_idempotency_key(
+                    &key,
+                    &IdempotencyRecord::Pending {
+                        fingerprint: fingerprint.clone(),
+                    },
+                    ttl_s,
+                )
+                .await;
+
+            match earlier {
+                Ok(None) => {}
+                Ok(Some(record)) => return Ok(req.into_response(replay(record, &fingerprint))),
+                Err(err) => return Ok(req.into_response(err.error_response())),
+            }
+
+            let res = match service.call(req).await {
+                Ok(res) if !res.status().is_server_error() => res,
+                other => {
+                    let _ = state.redis_handler.release_idempotency_key(&key).await;
+                    return other;
+                }
+            };
+
+            let (req, resp) = res.into_parts();
+            let (resp, body) = resp.into_parts();
+            let body = match to_bytes(body).await {
+                Ok(body) => body,
+                Err(_) => {
+                    let _ = state.redis_handler.release_idempotency_key(&key).await;
+                    return Err(actix_web::error::ErrorInternalServerError(
+                        "Failed to read response body",
+                    ));
+                }
+            };
+
+            let completed = IdempotencyRecord::Completed {
+                fingerprint,
+                status: resp.status().as_u16(),
+                content_type: resp
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|val| val.to_str().ok())
+                    .map(|val| val.to_owned()),
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+
+            // the action is already performed, so it is responded even if it can't be stored
+            if state
+                .redis_handler
+                .complete_idempotency_key(&key, &completed, ttl_s)
+                .await
+                .is_err()
+            {
+                let _ = state.redis_handler.release_idempotency_key(&key).await;
+            }
+
+            Ok(ServiceResponse::new(
+                req,
+                resp.set_body(body).map_into_boxed_body(),
+            ))
+        })
+    }
+}
+
+/// `Idempotency-Key` header of the routes wrapped with [`Idempotent`], described for OpenAPI only
+#[derive(IntoParams)]
+#[into_params(names("Idempotency-Key"), parameter_in = Header)]
+pub struct IdempotencyKey(
+    /// any unique string up to 255 characters, repeats of the request with the same key
+    /// get the stored response instead of being performed again. The key of another request
+    /// is rejected with 422 `idempotency_key_reused`
+    Option<String>,
+);
+
+fn idempotency_key(req: &ServiceRequest) -> Option<Result<String, ApiError>> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return None;
+    }
+
+    let value = req.headers().get(IDEMPOTENCY_HEADER)?;
+
+    let invalid = |message: String| ApiError::invalid(IDEMPOTENCY_HEADER, message);
+
+    Some(match value.to_str().map(str::trim) {
+        Ok("") => Err(invalid("must not be empty".to_owned())),
+        Ok(key) if key.len() > MAX_IDEMPOTENCY_KEY_LEN => Err(invalid(format!(
+            "must be at most {MAX_IDEMPOTENCY_KEY_LEN} characters long"
+        ))),
+        Ok(key) => Ok(key.to_owned()),
+        Err(_) => Err(invalid(
+            "must contain only visible ASCII characters".to_owned(),
+        )),
+    })
+}
+
+impl Idempotent {
+    /// Names the caller the keys belong to, `None` when the request can't be told apart
+    fn caller(self, req: &ServiceRequest, state: &AppState) -> Option<String> {
+        match self {
+            Idempotent::Route(route) => {
+                // a malformed header is left to the route to reject
+                match Worker::from_request(req.request(), &mut Payload::None).into_inner() {
+                    Ok(Worker(Some(worker_id))) => Some(format!("{route}-worker-{worker_id}")),
+                    Ok(Worker(None)) => Some(route.to_owned()),
+                    Err(_) => None,
+                }
+            }
+            Idempotent::GuestTable => {
+                let token = req
+                    .match_info()
+                    .unprocessed()
+                    .strip_prefix("/tables/")?
+                    .split('/')
+                    .next()?;
+
+                state
+                    .table_tokens
+                    .verify(token)
+                    .map(|table_id| format!("table-{table_id}"))
+            }
+        }
+    }
+}
+
+/// Hash of the method, path and body, so that records don't keep the table tokens of guest
+/// paths or the bodies
+fn fingerprint(req: &ServiceRequest, body: &[u8]) -> String {
+    let digest = Sha256::new()
+        .chain_update(format!("{} {}\n", req.method(), req.path()))
+        .chain_update(body)
+        .finalize();
+
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+fn replay(record: IdempotencyRecord, fingerprint: &str) -> HttpResponse {
+    if record.fingerprint() != fingerprint {
+        return ApiError::IdempotencyKeyReused(
+            "Idempotency key is already used for a different request".to_owned(),
+        )
+        .error_response();
+    }
+
+    match record {
+        IdempotencyRecord::Pending { .. } => ApiError::conflict(
+            "request_in_progress",
+            "Request with this idempotency key is still in progress",
+        )
+        .error_response(),
+        IdempotencyRecord::Completed {
+            status,
+            content_type,
+            body,
+            ..
+        } => {
+            let mut resp = HttpResponse::build(
+                StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            );
+
+            if let Some(content_type) = content_type {
+                resp.insert_header((CONTENT_TYPE, content_type));
+            }
+
+            resp.insert_header((IDEMPOTENT_REPLAY_HEADER, HeaderValue::from_static("true")))
+                .body(body)
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Sends requests with idempotency keys through the middleware.
+//!
+//! Requires `TEST_REDIS_URL`, the tests are ignored unless run with `cargo test -- --ignored`.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix::SyncArbiter;
+use actix_web::http::StatusCode;
+use actix_web::web::{self, Data};
+use actix_web::{test, App, HttpResponse};
+use chrono::NaiveTime;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+
+use crate::services::audit::WORKER_HEADER;
+use crate::services::db_utils::{AppState, PgActor};
+use crate::services::guest::TableTokens;
+use crate::services::idempotency::Idempotent;
+use crate::services::redis_handling::RedisHandler;
+use crate::settings::{RestaurantSettings, TipSettings};
+use crate::types::{IDEMPOTENCY_HEADER, IDEMPOTENT_REPLAY_HEADER};
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+fn app_state(redis_url: &str) -> AppState {
+    // routes of the test don't reach the database, so the pool never connects
+    let pool = Pool::builder().build_unchecked(ConnectionManager::<PgConnection>::new(
+        "postgres://localhost/unused",
+    ));
+
+    AppState {
+        pg_db: SyncArbiter::start(1, move || PgActor(pool.clone())),
+        redis_handler: RedisHandler::new(
+            redis::Client::open(redis_url).unwrap(),
+            Duration::from_secs(1),
+        ),
+        course_order: vec![],
+        min_margin_pct: 60.0,
+        menu_max_age_s: 10,
+        idempotency_ttl_s: 60,
+        health_timeout_ms: 1_000,
+        table_tokens: TableTokens::new(SECRET),
+        guest_app_url: "http://localhost/guest".to_owned(),
+        guest_requests_per_minute: 60,
+        tips: TipSettings {
+            waiter_share_percent: 100,
+            pool_roles: HashMap::new(),
+        },
+        restaurant: RestaurantSettings {
+            name: "Test".to_owned(),
+            currency: "USD".to_owned(),
+            opening_hours: vec![],
+            timezone: chrono_tz::UTC,
+            business_day_starts_at: NaiveTime::MIN,
+        },
+    }
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_REDIS_URL"]
+async fn tables_never_meet_in_idempotency_keys() {
+    let redis_url =
+        std::env::var("TEST_REDIS_URL").expect("TEST_REDIS_URL must point to a test redis");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(app_state(&redis_url)))
+            .service(
+                web::scope("/api/v1/guest")
+                    .wrap(Idempotent::GuestTable)
+                    .route(
+                        "/tables/{token}/order/lines",
+                        web::post().to(|| async { HttpResponse::Created().body("added") }),
+                    )
+                    .route(
+                        "/tables/{token}/calls",
+                        web::post().to(|| async { HttpResponse::Created().body("called") }),
+                    ),
+            ),
+    )
+    .await;
+
+    let tokens = TableTokens::new(SECRET);
+    let (first_token, second_token) = (tokens.sign(1), tokens.sign(2));
+    let key = format!(
+        "key-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+
+    let send = |token: &str, route: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/guest/tables/{token}/{route}"))
+            .insert_header((IDEMPOTENCY_HEADER, key.as_str()))
+            .to_request()
+    };
+
+    for token in [&first_token, &second_token] {
+        let resp = test::call_service(&app, send(token, "order/lines")).await;
+
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(
+            resp.headers().get(IDEMPOTENT_REPLAY_HEADER).is_none(),
+            "the key of one table must not replay the request of another"
+        );
+    }
+
+    let resp = test::call_service(&app, send(&first_token, "order/lines")).await;
+
+    assert_eq!(
+        resp.headers().get(IDEMPOTENT_REPLAY_HEADER).unwrap(),
+        "true"
+    );
+
+    let resp = test::call_service(&app, send(&second_token, "calls")).await;
+
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    for token in [&first_token, &second_token] {
+        assert!(!body.contains(token.as_str()), "{body} reveals a token");
+    }
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_REDIS_URL"]
+async fn key_is_bound_to_the_body() {
+    let redis_url =
+        std::env::var("TEST_REDIS_URL").expect("TEST_REDIS_URL must point to a test redis");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(app_state(&redis_url)))
+            .service(
+                web::scope("/api/v1/orders")
+                    .wrap(Idempotent::Route("orders"))
+                    .route(
+                        "",
+                        web::post().to(|body: String| async { HttpResponse::Created().body(body) }),
+                    ),
+            ),
+    )
+    .await;
+
+    let key = format!(
+        "body-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    let send = |body: &'static str| {
+        test::TestRequest::post()
+            .uri("/api/v1/orders")
+            .insert_header((IDEMPOTENCY_HEADER, key.as_str()))
+            .set_payload(body)
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, send(r#"{"table_id":1}"#)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(test::read_body(resp).await, r#"{"table_id":1}"#);
+
+    let resp = test::call_service(&app, send(r#"{"table_id":1}"#)).await;
+    assert_eq!(
+        resp.headers().get(IDEMPOTENT_REPLAY_HEADER).unwrap(),
+        "true"
+    );
+
+    let resp = test::call_service(&app, send(r#"{"table_id":2}"#)).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "idempotency_key_reused");
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_REDIS_URL"]
+async fn workers_never_meet_in_idempotency_keys() {
+    let redis_url =
+        std::env::var("TEST_REDIS_URL").expect("TEST_REDIS_URL must point to a test redis");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(app_state(&redis_url)))
+            .service(
+                web::scope("/api/v1/orders")
+                    .wrap(Idempotent::Route("orders"))
+                    .route(
+                        "",
+                        web::post().to(|| async { HttpResponse::Created().body("created") }),
+                    ),
+            ),
+    )
+    .await;
+
+    let key = format!(
+        "worker-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    let send = |worker_id: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/orders")
+            .insert_header((IDEMPOTENCY_HEADER, key.as_str()))
+            .insert_header((WORKER_HEADER, worker_id))
+            .to_request()
+    };
+
+    for worker_id in ["1", "2"] {
+        let resp = test::call_service(&app, send(worker_id)).await;
+
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(
+            resp.headers().get(IDEMPOTENT_REPLAY_HEADER).is_none(),
+            "the key of one worker must not replay the request of another"
+        );
+    }
+
+    let resp = test::call_service(&app, send("1")).await;
+
+    assert_eq!(
+        resp.headers().get(IDEMPOTENT_REPLAY_HEADER).unwrap(),
+        "true"
+    );
+}
@@ -1,15 +1,19 @@
-use chrono::NaiveDate;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDate, Utc};
 use diesel::Insertable;
 use serde::Serialize;
 
 use crate::schema::dish_to_order;
 use crate::schema::dish_to_product;
 use crate::schema::dishes;
+use crate::schema::modifier_groups;
+use crate::schema::modifier_products;
+use crate::schema::modifiers;
+use crate::schema::order_line_modifiers;
 use crate::schema::orders;
+use crate::schema::product_costs;
 use crate::schema::stats;
 use crate::schema::waiters;
-use crate::types::{DishType, Ingredient};
+use crate::types::{DietaryLabel, DishType, Ingredient};
 
 #[derive(Insertable, Serialize, Clone)]
 #[diesel(table_name = waiters)]
@@ -24,6 +28,40 @@ pub struct OrderDish {
     pub dish_id: i64,
     pub order_id: i64,
     pub count: i32,
+    pub note: Option<String>,
+    pub line_key: String,
+}
+
+#[derive(Insertable, Serialize, Clone)]
+#[diesel(table_name = order_line_modifiers)]
+pub struct OrderLineModifier {
+    pub line_id: i64,
+    pub modifier_id: i64,
+}
+
+#[derive(Insertable, Serialize, Clone)]
+#[diesel(table_name = modifier_groups)]
+pub struct NewModifierGroup {
+    pub dish_id: i64,
+    pub name: String,
+    pub is_multiple: bool,
+    pub is_required: bool,
+}
+
+#[derive(Insertable, Serialize, Clone)]
+#[diesel(table_name = modifiers)]
+pub struct NewModifier {
+    pub group_id: i64,
+    pub name: String,
+    pub price_delta: i32,
+}
+
+#[derive(Insertable, Serialize, Clone)]
+#[diesel(table_name = modifier_products)]
+pub struct NewModifierProduct {
+    pub modifier_id: i64,
+    pub product_id: i64,
+    pub weight_delta_g: i32,
 }
 
 #[derive(Insertable, Serialize, Clone)]
@@ -31,7 +69,8 @@ pub struct OrderDish {
 pub struct NewOrder {
     pub table_id: i64,
     pub total_cost: i32,
-    pub created_at: NaiveDateTime,
+    pub created_at: DateTime<Utc>,
+    pub waiter_id: Option<i64>,
 }
 
 #[derive(Insertable, Serialize, Clone)]
@@ -49,6 +88,9 @@ pub struct NewDish {
     pub approx_cook_time_s: i32,
     pub portion_weight_g: i32,
     pub price: i32,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub dietary_labels: Vec<DietaryLabel>,
 }
 
 #[derive(Insertable, Serialize, Clone)]
@@ -58,3 +100,11 @@ pub struct DishProductMapping {
     pub product_id: i64,
     pub weight_g: i32,
 }
+
+#[derive(Insertable, Serialize, Clone)]
+#[diesel(table_name = product_costs)]
+pub struct NewProductCost {
+    pub product_id: i64,
+    pub cost_per_kg: i32,
+    pub valid_from: DateTime<Utc>,
+}
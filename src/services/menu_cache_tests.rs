@@ -0,0 +1,40 @@
+//! Checks how the menu cache report classifies keys found in redis.
+
+use crate::types::MenuCacheReport;
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+#[test]
+fn indexed_menus_are_consistent() {
+    let menus = keys(&["menu_2026-10-19", "menu_2026-10-20"]);
+
+    let report = MenuCacheReport::new(&menus, &menus, Some("menu_2026-10-19".to_owned()));
+
+    assert!(report.is_consistent(), "{report:?}");
+}
+
+#[test]
+fn every_problem_is_reported() {
+    let report = MenuCacheReport::new(
+        &keys(&[
+            "menu_2026-10-19_dish-1",
+            "menu_2026-10-19",
+            "menu_2026-10-21",
+        ]),
+        &keys(&["menu_2026-10-19", "menu_2026-10-20"]),
+        Some("menu_2026-10-22".to_owned()),
+    );
+
+    assert_eq!(
+        report,
+        MenuCacheReport {
+            orphaned_dish_keys: keys(&["menu_2026-10-19_dish-1"]),
+            missing_menus: keys(&["menu_2026-10-20"]),
+            unindexed_menus: keys(&["menu_2026-10-21"]),
+            dangling_active_menu: Some("menu_2026-10-22".to_owned()),
+        }
+    );
+    assert!(!report.is_consistent());
+}
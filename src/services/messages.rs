@@ -1,91 +1,364 @@
+use std::fmt;
+use std::time::Duration;
+
 use actix::Message;
-use diesel::QueryResult;
+use chrono::{DateTime, NaiveDate, Utc};
 
+use crate::errors::ApiError;
 use crate::services::db_models::Dish;
-use crate::services::db_models::Waiter;
-use crate::types::{DishType, Ingredient, OrderInfo};
+use crate::services::db_models::{AuditEntry, Product, ProductCost, Shift, Table, Waiter};
+use crate::types::{
+    Allergen, BatchEdit, DietaryLabel, DishType, Ingredient, ModifierGroupInfo, ModifierSpec,
+    OnShiftStaff, OrderInfo, OrderLineRef, PoolStatus, ShiftSummary, TipPayoutInputs,
+};
+
+/// handles `msg` on behalf of the worker, who is recorded in the audit log of its changes
+pub struct AsWorker<M> {
+    pub worker_id: Option<i32>,
+    pub msg: M,
+}
 
+impl<M: Message> Message for AsWorker<M> {
+    type Result = M::Result;
+}
+
+/// appends an entry for a change made outside the database, e.g. to menus in redis
 #[derive(Message)]
-#[rtype(result = "QueryResult<Vec<Waiter>>")]
+#[rtype(result = "Result<(), ApiError>")]
+pub struct RecordAudit {
+    pub entity: &'static str,
+    pub entity_id: String,
+    pub action: &'static str,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+/// fails when the worker of the `X-Worker-Id` header is unknown, so a change made outside the
+/// database is not left without its audit entry
+#[derive(Message)]
+#[rtype(result = "Result<(), ApiError>")]
+pub struct CheckActingWorker(pub i32);
+
+/// the latest entries matching every given filter, `to` is exclusive
+#[derive(Message, Default)]
+#[rtype(result = "Result<Vec<AuditEntry>, ApiError>")]
+pub struct FetchAuditLog {
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub action: Option<String>,
+    pub worker_id: Option<i32>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// entries older than this one, for paging
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+/// runs a trivial query, returns the state of the pool seen before taking a connection for it
+#[derive(Message)]
+#[rtype(result = "Result<PoolStatus, ApiError>")]
+pub struct Ping {
+    /// limits waiting for a free connection
+    pub timeout: Duration,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Waiter>, ApiError>")]
 pub struct FetchWaiters;
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<()>")]
+#[rtype(result = "Result<Waiter, ApiError>")]
 pub struct AddWaiter {
     pub first_name: String,
     pub last_name: String,
     pub is_admin: bool,
 }
 
+/// `None` unlinks the waiter from the staff record
+#[derive(Message)]
+#[rtype(result = "Result<Waiter, ApiError>")]
+pub struct LinkWaiterWorker {
+    pub waiter_id: i64,
+    pub worker_id: Option<i32>,
+}
+
+/// tips paid from `from` till `to` by waiter, grouped by the workers of the pooled roles
+/// who were on shift when the tips were paid
+#[derive(Message)]
+#[rtype(result = "Result<Vec<TipPayoutInputs>, ApiError>")]
+pub struct FetchTipPayoutInputs {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub pool_roles: Vec<String>,
+}
+
+/// the one clocking in or out. A waiter with a staff record is on shift by either id
+#[derive(Debug, Clone, Copy)]
+pub enum ShiftOf {
+    Waiter(i64),
+    Worker(i32),
+}
+
+impl fmt::Display for ShiftOf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShiftOf::Waiter(waiter_id) => write!(f, "Waiter {waiter_id}"),
+            ShiftOf::Worker(worker_id) => write!(f, "Worker {worker_id}"),
+        }
+    }
+}
+
+/// opens a shift of the waiter or worker
 #[derive(Message)]
-#[rtype(result = "QueryResult<Dish>")]
+#[rtype(result = "Result<Shift, ApiError>")]
+pub struct ClockIn(pub ShiftOf);
+
+/// closes the open shift and releases the tables assigned to the waiter
+#[derive(Message)]
+#[rtype(result = "Result<ShiftSummary, ApiError>")]
+pub struct ClockOut(pub ShiftOf);
+
+/// everyone clocked in, the earliest first
+#[derive(Message)]
+#[rtype(result = "Result<Vec<OnShiftStaff>, ApiError>")]
+pub struct FetchOnShiftStaff;
+
+/// only waiters on shift may be assigned, unpaid orders of the table are handed over to them.
+/// `None` leaves the table without a waiter, its orders stay with the previous one
+#[derive(Message)]
+#[rtype(result = "Result<Table, ApiError>")]
+pub struct AssignTableWaiter {
+    pub table_id: i64,
+    pub waiter_id: Option<i64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Dish, ApiError>")]
 pub struct FetchDish(pub i64);
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<Vec<Dish>>")]
+#[rtype(result = "Result<Vec<Dish>, ApiError>")]
 pub struct FetchDishes;
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<Vec<Dish>>")]
+#[rtype(result = "Result<Vec<Dish>, ApiError>")]
 pub struct FetchSpecificDishes(pub Vec<i64>);
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<Vec<(String, i32)>>")]
+#[rtype(result = "Result<Vec<(String, i32)>, ApiError>")]
 pub struct FetchDishIngredients(pub i64);
 
+/// allergens of all the products the dish is made of
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Allergen>, ApiError>")]
+pub struct FetchDishAllergens(pub i64);
+
+/// active dishes that use the product
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Dish>, ApiError>")]
+pub struct FetchDishesWithProduct(pub i64);
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Product>, ApiError>")]
+pub struct FetchProducts;
+
+/// updates current purchase cost and appends it to the cost history
+#[derive(Message)]
+#[rtype(result = "Result<Product, ApiError>")]
+pub struct SetProductCost {
+    pub product_id: i64,
+    pub cost_per_kg: i32,
+}
+
+/// newest first
+#[derive(Message)]
+#[rtype(result = "Result<Vec<ProductCost>, ApiError>")]
+pub struct FetchProductCostHistory(pub i64);
+
+/// every active dish with the cost of its ingredients
+#[derive(Message)]
+#[rtype(result = "Result<Vec<(Dish, i32)>, ApiError>")]
+pub struct FetchDishFoodCosts;
+
+#[derive(Message)]
+#[rtype(result = "Result<Product, ApiError>")]
+pub struct SetProductAllergens {
+    pub product_id: i64,
+    pub allergens: Vec<Allergen>,
+}
+
 /// returns id of newly created order
 #[derive(Message)]
-#[rtype(result = "QueryResult<i64>")]
+#[rtype(result = "Result<i64, ApiError>")]
 pub struct CreateOrder(pub i64);
 
+/// returns id of the unconfirmed order of the table, a new one is created when there is none
 #[derive(Message)]
-#[rtype(result = "QueryResult<OrderInfo>")]
+#[rtype(result = "Result<i64, ApiError>")]
+pub struct OpenTableOrder(pub i64);
+
+#[derive(Message)]
+#[rtype(result = "Result<OrderInfo, ApiError>")]
 pub struct FetchOrder(pub i64);
 
+/// the latest unpaid order of the table
+#[derive(Message)]
+#[rtype(result = "Result<OrderInfo, ApiError>")]
+pub struct FetchTableOrder(pub i64);
+
 #[derive(Message)]
-#[rtype(result = "QueryResult<Vec<OrderInfo>>")]
+#[rtype(result = "Result<Table, ApiError>")]
+pub struct FetchTable(pub i64);
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Table>, ApiError>")]
+pub struct FetchTables;
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<OrderInfo>, ApiError>")]
 pub struct FetchOrders;
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<i64>")]
+#[rtype(result = "Result<i64, ApiError>")]
 pub struct AddDishToOrder {
     pub order_id: i64,
     pub dish_id: i64,
+    pub modifier_ids: Vec<i64>,
+    pub note: Option<String>,
 }
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<i64>")]
+#[rtype(result = "Result<i64, ApiError>")]
 pub struct DecrementDishInOrder {
     pub order_id: i64,
-    pub dish_id: i64,
+    pub line: OrderLineRef,
 }
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<i64>")]
+#[rtype(result = "Result<i64, ApiError>")]
 pub struct DeleteDishFromOrder {
     pub order_id: i64,
-    pub dish_id: i64,
+    pub line: OrderLineRef,
 }
 
+/// zero count removes the line
 #[derive(Message)]
-#[rtype(result = "QueryResult<()>")]
+#[rtype(result = "Result<OrderInfo, ApiError>")]
+pub struct SetLineCount {
+    pub order_id: i64,
+    pub line_id: i64,
+    pub count: i32,
+}
+
+/// applies all the changes in one transaction
+#[derive(Message)]
+#[rtype(result = "Result<OrderInfo, ApiError>")]
+pub struct BatchEditOrder {
+    pub order_id: i64,
+    pub edit: BatchEdit,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), ApiError>")]
 pub struct ConfirmOrder(pub i64);
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<()>")]
+#[rtype(result = "Result<(), ApiError>")]
 pub struct CookOrder(pub i64);
 
+/// zero `tip` means the guest left no gratuity
+#[derive(Message)]
+#[rtype(result = "Result<(), ApiError>")]
+pub struct PayForOrder {
+    pub order_id: i64,
+    pub tip: i32,
+    /// business day the income is counted for
+    pub day: NaiveDate,
+}
+
+/// moves an unpaid order to another table
+#[derive(Message)]
+#[rtype(result = "Result<(), ApiError>")]
+pub struct TransferOrder {
+    pub order_id: i64,
+    pub table_id: i64,
+}
+
+/// moves lines of the unpaid `from_order_id` into `order_id` and deletes the emptied order
 #[derive(Message)]
-#[rtype(result = "QueryResult<()>")]
-pub struct PayForOrder(pub i64);
+#[rtype(result = "Result<(), ApiError>")]
+pub struct MergeOrders {
+    pub order_id: i64,
+    pub from_order_id: i64,
+}
 
 #[derive(Message)]
-#[rtype(result = "QueryResult<Dish>")]
+#[rtype(result = "Result<Dish, ApiError>")]
 pub struct CreateDish {
     pub dish_name: String,
     pub dish_type: DishType,
     pub price: i32,
     pub approx_cook_time_s: i32,
     pub portion_weight_g: i32,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub dietary_labels: Vec<DietaryLabel>,
     pub ingredients: Vec<Ingredient>,
 }
+
+#[derive(Message)]
+#[rtype(result = "Result<Dish, ApiError>")]
+pub struct UpdateDish {
+    pub dish_id: i64,
+    pub dish_name: Option<String>,
+    pub dish_type: Option<DishType>,
+    pub price: Option<i32>,
+    pub approx_cook_time_s: Option<i32>,
+    pub portion_weight_g: Option<i32>,
+    /// `Some(None)` clears the description
+    pub description: Option<Option<String>>,
+    /// `Some(None)` clears the image
+    pub image_url: Option<Option<String>>,
+    pub dietary_labels: Option<Vec<DietaryLabel>>,
+}
+
+/// replaces the whole ingredient list of the dish, returns new list
+#[derive(Message)]
+#[rtype(result = "Result<Vec<(String, i32)>, ApiError>")]
+pub struct ReplaceDishIngredients {
+    pub dish_id: i64,
+    pub ingredients: Vec<Ingredient>,
+}
+
+/// soft delete, the dish stays referenced by historic orders
+#[derive(Message)]
+#[rtype(result = "Result<Dish, ApiError>")]
+pub struct ArchiveDish(pub i64);
+
+/// returns ids from the list that have no matching product
+#[derive(Message)]
+#[rtype(result = "Result<Vec<i64>, ApiError>")]
+pub struct FindUnknownProducts(pub Vec<i64>);
+
+/// checks whether an active dish (other than `except_id`) already uses the name
+#[derive(Message)]
+#[rtype(result = "Result<bool, ApiError>")]
+pub struct IsDishNameTaken {
+    pub name: String,
+    pub except_id: Option<i64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<ModifierGroupInfo>, ApiError>")]
+pub struct FetchDishModifiers(pub i64);
+
+/// returns all the modifier groups of the dish
+#[derive(Message)]
+#[rtype(result = "Result<Vec<ModifierGroupInfo>, ApiError>")]
+pub struct CreateModifierGroup {
+    pub dish_id: i64,
+    pub name: String,
+    pub is_multiple: bool,
+    pub is_required: bool,
+    pub modifiers: Vec<ModifierSpec>,
+}
@@ -0,0 +1,101 @@
+//! Applies the embedded migrations to an empty schema of a real database.
+//!
+//! Runs against the database of [`crate::services::test_db`]. Every test works in its own
+//! schema, which is dropped afterwards.
+
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel_migrations::MigrationHarness;
+
+use crate::services::db_utils::{pending_migrations, run_migrations, MIGRATIONS};
+use crate::services::test_db::db_url;
+
+/// Connection with `search_path` set to a new empty schema, so the migrations and their
+/// bookkeeping table are created there
+struct ScratchSchema {
+    conn: PgConnection,
+    name: String,
+}
+
+impl ScratchSchema {
+    fn create(test: &str) -> Self {
+        let mut conn =
+            PgConnection::establish(&db_url()).expect("Unable to connect to the test database");
+        let suffix = chrono::Local::now().timestamp_nanos_opt().unwrap();
+        let name = format!("migrations_{test}_{suffix}");
+
+        sql_query(format!("CREATE SCHEMA {name}"))
+            .execute(&mut conn)
+            .unwrap();
+        sql_query(format!("SET search_path TO {name}"))
+            .execute(&mut conn)
+            .unwrap();
+
+        ScratchSchema { conn, name }
+    }
+}
+
+impl Drop for ScratchSchema {
+    fn drop(&mut self) {
+        let _ = sql_query(format!("DROP SCHEMA {} CASCADE", self.name)).execute(&mut self.conn);
+    }
+}
+
+/// Selects every column `schema.rs` declares, fails if one of them is missing
+macro_rules! assert_columns_exist {
+    ($conn:expr, $($table:ident),+ $(,)?) => {
+        $(
+            crate::schema::$table::table
+                .select(crate::schema::$table::all_columns)
+                .limit(0)
+                .execute($conn)
+                .unwrap_or_else(|err| panic!("{}: {err}", stringify!($table)));
+        )+
+    };
+}
+
+#[test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+fn migrations_create_every_table_of_schema() {
+    let mut scratch = ScratchSchema::create("schema");
+    let conn = &mut scratch.conn;
+
+    run_migrations(conn).unwrap();
+
+    assert!(pending_migrations(conn).unwrap().is_empty());
+    assert_columns_exist!(
+        conn,
+        audit_log,
+        dish_to_order,
+        dish_to_product,
+        dishes,
+        modifier_groups,
+        modifier_products,
+        modifiers,
+        order_line_modifiers,
+        orders,
+        product_costs,
+        products,
+        shifts,
+        stats,
+        tables,
+        tips,
+        waiters,
+        worker,
+        worker_auth,
+        worker_role,
+    );
+}
+
+#[test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+fn migrations_revert_and_apply_again() {
+    let mut scratch = ScratchSchema::create("revert");
+    let conn = &mut scratch.conn;
+
+    let applied = run_migrations(conn).unwrap();
+    conn.revert_all_migrations(MIGRATIONS).unwrap();
+
+    assert_eq!(pending_migrations(conn).unwrap().len(), applied.len());
+    assert_eq!(run_migrations(conn).unwrap(), applied);
+}
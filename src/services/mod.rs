@@ -1,12 +1,138 @@
+use actix_web::middleware::DefaultHeaders;
 use actix_web::{get, HttpResponse, Responder};
+use utoipa::openapi::Deprecated;
+use utoipa::{Modify, OpenApi};
 
+pub mod api_v1;
+pub mod audit;
+pub mod conditional;
 pub mod db_models;
 pub mod db_utils;
+pub mod guest;
+pub mod health;
+pub mod idempotency;
 pub mod insertable;
 pub mod messages;
 pub mod pg_handling;
+pub mod qr_codes;
 pub mod redis_handling;
+pub mod tips;
+pub mod validation;
 
+#[cfg(test)]
+mod audit_tests;
+#[cfg(test)]
+mod conditional_tests;
+#[cfg(test)]
+mod dishes_tests;
+#[cfg(test)]
+mod guest_tests;
+#[cfg(test)]
+mod health_tests;
+#[cfg(test)]
+mod idempotency_tests;
+#[cfg(test)]
+mod menu_cache_tests;
+#[cfg(test)]
+mod migrations_tests;
+#[cfg(test)]
+mod openapi_tests;
+#[cfg(test)]
+mod orders_tests;
+#[cfg(test)]
+mod qr_codes_tests;
+#[cfg(test)]
+mod shifts_tests;
+#[cfg(test)]
+mod test_db;
+#[cfg(test)]
+mod tips_tests;
+
+/// Tag of the routes that predate `/api/v1`
+const LEGACY_TAG: &str = "legacy";
+
+/// OpenAPI document of every route, nested paths mirror the scopes in `main.rs`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "NomNomNavigator", description = "Restaurant back office and ordering API"),
+    paths(home_page),
+    nest(
+        (
+            path = "/api/v1/restaurant",
+            api = api_v1::restaurant_route::ApiDoc,
+            tags = ["restaurant"]
+        ),
+        (path = "/api/v1/waiters", api = api_v1::waiters_route::ApiDoc, tags = ["waiters"]),
+        (path = "/api/v1/workers", api = api_v1::workers_route::ApiDoc, tags = ["workers"]),
+        (path = "/api/v1/menus", api = api_v1::menus_route::ApiDoc, tags = ["menus"]),
+        (path = "/api/v1/orders", api = api_v1::orders_route::ApiDoc, tags = ["orders"]),
+        (path = "/api/v1/dishes", api = api_v1::dishes_route::ApiDoc, tags = ["dishes"]),
+        (path = "/api/v1/products", api = api_v1::products_route::ApiDoc, tags = ["products"]),
+        (path = "/api/v1/tables", api = api_v1::tables_route::ApiDoc, tags = ["tables"]),
+        (path = "/api/v1/tips", api = api_v1::tips_route::ApiDoc, tags = ["tips"]),
+        (path = "/api/v1/audit", api = api_v1::audit_route::ApiDoc, tags = ["audit"]),
+        (path = "/api/v1/guest", api = guest::ApiDoc, tags = ["guest"]),
+        (path = "/waiters", api = waiters_route::ApiDoc, tags = [LEGACY_TAG]),
+        (path = "/menu", api = menu_route::ApiDoc, tags = [LEGACY_TAG]),
+        (path = "/order", api = order_route::ApiDoc, tags = [LEGACY_TAG]),
+        (path = "/dishes", api = dishes_route::ApiDoc, tags = [LEGACY_TAG]),
+        (path = "/products", api = products_route::ApiDoc, tags = [LEGACY_TAG]),
+        (path = "/test", api = test_route::ApiDoc, tags = ["test"]),
+        (path = "/health", api = health::ApiDoc, tags = ["health"]),
+    ),
+    modifiers(&DeprecateLegacyRoutes)
+)]
+pub struct ApiDoc;
+
+struct DeprecateLegacyRoutes;
+
+impl Modify for DeprecateLegacyRoutes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.patch,
+            ];
+
+            for operation in operations.into_iter().flatten() {
+                let is_legacy = operation
+                    .tags
+                    .as_ref()
+                    .is_some_and(|tags| tags.iter().any(|tag| tag == LEGACY_TAG));
+
+                if is_legacy {
+                    operation.deprecated = Some(Deprecated::True);
+                }
+            }
+        }
+    }
+}
+
+/// Headers of every response of the routes that predate `/api/v1`
+pub fn deprecation_headers() -> DefaultHeaders {
+    DefaultHeaders::new()
+        .add(("Deprecation", "true"))
+        .add(("Link", "</docs>; rel=\"deprecation\""))
+}
+
+#[get("/openapi.json")]
+pub async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[utoipa::path(
+    responses(
+        (
+            status = 200,
+            description = "Service greeting",
+            body = String,
+            content_type = "text/plain"
+        )
+    )
+)]
 #[get("/")]
 pub async fn home_page() -> impl Responder {
     HttpResponse::Ok().body("Rust service prototype")
@@ -18,394 +144,1674 @@ pub mod waiters_route {
     use actix_web::web::{Data, Json};
     use actix_web::{get, post, HttpResponse, Responder};
     use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
 
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::Waiter;
     use crate::services::db_utils::{AppState, PgActor};
     use crate::services::messages::{AddWaiter, FetchWaiters};
 
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All waiters", body = Vec<Waiter>),
+            (status = 503, description = "Database is unavailable", body = ErrorBody),
+        )
+    )]
     #[get("/all")]
-    pub async fn fetch_waiters(state: Data<AppState>) -> impl Responder {
-        match state.pg_db.send(FetchWaiters).await {
-            Ok(Ok(waiters)) => HttpResponse::Ok().json(waiters),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            _ => HttpResponse::InternalServerError().json("Unable to retrieve waiters"),
-        }
+    pub async fn fetch_waiters(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let waiters = state.pg_db.send(FetchWaiters).await??;
+
+        Ok(HttpResponse::Ok().json(waiters))
     }
 
-    #[derive(Deserialize)]
-    struct AddWaiterBody {
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct AddWaiterBody {
         first_name: String,
         last_name: String,
     }
 
-    #[post("/add")]
-    pub async fn add_waiter(state: Data<AppState>, body: Json<AddWaiterBody>) -> impl Responder {
-        match state
-            .pg_db
-            .send(AddWaiter {
-                first_name: body.first_name.clone(),
-                last_name: body.last_name.clone(),
-                is_admin: false,
-            })
-            .await
-        {
-            Ok(Ok(_)) => {
-                HttpResponse::Ok().json("New waiter is successfully added to the database")
-            }
-            Ok(Err(err)) => HttpResponse::InternalServerError().json(format!("Error: {err}")),
-            _ => HttpResponse::InternalServerError().json("Unable to insert new waiter"),
+    impl AddWaiterBody {
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+        ) -> Result<Waiter, ApiError> {
+            state
+                .pg_db
+                .send(worker.acting(AddWaiter {
+                    first_name: self.first_name,
+                    last_name: self.last_name,
+                    is_admin: false,
+                }))
+                .await?
         }
     }
+
+    #[utoipa::path(
+        request_body = AddWaiterBody,
+        responses(
+            (
+                status = 200,
+                description = "Waiter is added",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+        )
+    )]
+    #[post("/add")]
+    pub async fn add_waiter(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<AddWaiterBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        body.into_inner().save(&state, worker).await?;
+
+        Ok(HttpResponse::Ok().json("New waiter is successfully added to the database"))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(fetch_waiters, add_waiter))]
+    pub struct ApiDoc;
 }
 
 // sub-route "/dishes"
 pub mod dishes_route {
-    use crate::services::db_utils::AppState;
-    use crate::services::messages::{AddWaiter, CreateDish};
-    use crate::types::{DishType, Ingredient};
-    use actix_web::web::{Data, Json};
-    use actix_web::{post, HttpResponse, Responder};
-    use serde::{Deserialize, Serialize};
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::Dish;
+    use crate::services::db_utils::{AppState, PgActor};
+    use crate::services::messages::{
+        AddWaiter, ArchiveDish, CreateDish, CreateModifierGroup, FetchDish, FetchDishFoodCosts,
+        FetchDishModifiers, FindUnknownProducts, ReplaceDishIngredients, UpdateDish,
+    };
+    use crate::services::validation::Validator;
+    use crate::types::{
+        DietaryLabel, DishCosting, DishType, Ingredient, ModifierGroupInfo, ModifierSpec,
+    };
+    use actix::Addr;
+    use actix_web::web::{Data, Json, Path};
+    use actix_web::{delete, get, post, put, HttpResponse, Responder};
+    use serde::{Deserialize, Deserializer, Serialize};
+    use utoipa::{OpenApi, ToSchema};
 
-    #[derive(Deserialize)]
-    struct CreateDishBody {
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct CreateDishBody {
         dish_name: String,
         dish_type: DishType,
         price: i32,
         approx_cook_time_s: i32,
         portion_weight_g: i32,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        image_url: Option<String>,
+        #[serde(default)]
+        dietary_labels: Vec<DietaryLabel>,
         ingredients: Vec<Ingredient>,
     }
 
+    impl CreateDishBody {
+        async fn validate(&self, pg_db: &Addr<PgActor>) -> Result<(), ApiError> {
+            let mut validator = Validator::new();
+
+            validator.dish_name(&self.dish_name);
+            validator.price(self.price);
+            validator.cook_time(self.approx_cook_time_s);
+            validator.portion_weight(self.portion_weight_g);
+            validator.ingredients(&self.ingredients);
+            validator
+                .dish_references(pg_db, Some(&self.dish_name), None, &self.ingredients)
+                .await?;
+
+            validator.into_result()
+        }
+
+        pub(crate) async fn save(self, state: &AppState, worker: Worker) -> Result<Dish, ApiError> {
+            self.validate(&state.pg_db).await?;
+
+            state
+                .pg_db
+                .send(worker.acting(CreateDish {
+                    dish_name: self.dish_name,
+                    dish_type: self.dish_type,
+                    price: self.price,
+                    approx_cook_time_s: self.approx_cook_time_s,
+                    portion_weight_g: self.portion_weight_g,
+                    description: self.description,
+                    image_url: self.image_url,
+                    dietary_labels: self.dietary_labels,
+                    ingredients: self.ingredients,
+                }))
+                .await?
+        }
+    }
+
+    #[utoipa::path(
+        request_body = CreateDishBody,
+        responses(
+            (status = 200, description = "Created dish", body = Dish),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+        )
+    )]
     #[post("/add")]
-    pub async fn create_dish(state: Data<AppState>, body: Json<CreateDishBody>) -> impl Responder {
-        let body = body.into_inner();
+    pub async fn create_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Json<CreateDishBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = body.into_inner().save(&state, worker).await?;
 
-        match state
-            .pg_db
-            .send(CreateDish {
-                dish_name: body.dish_name,
-                dish_type: body.dish_type,
-                price: body.price,
-                approx_cook_time_s: body.approx_cook_time_s,
-                portion_weight_g: body.portion_weight_g,
-                ingredients: body.ingredients,
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Food cost and margin of every dish",
+                body = Vec<DishCosting>
+            ),
+        )
+    )]
+    #[get("/costing")]
+    pub async fn get_costing(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        Ok(HttpResponse::Ok().json(costings(&state).await?))
+    }
+
+    pub(crate) async fn costings(state: &AppState) -> Result<Vec<DishCosting>, ApiError> {
+        let food_costs = state.pg_db.send(FetchDishFoodCosts).await??;
+
+        Ok(food_costs
+            .iter()
+            .map(|(dish, food_cost)| DishCosting::new(dish, *food_cost, state.min_margin_pct))
+            .collect())
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct UpdateDishBody {
+        dish_name: Option<String>,
+        dish_type: Option<DishType>,
+        price: Option<i32>,
+        approx_cook_time_s: Option<i32>,
+        portion_weight_g: Option<i32>,
+        /// `null` clears the description
+        #[serde(default, deserialize_with = "nullable")]
+        #[schema(value_type = Option<String>, nullable)]
+        description: Option<Option<String>>,
+        /// `null` clears the image
+        #[serde(default, deserialize_with = "nullable")]
+        #[schema(value_type = Option<String>, nullable)]
+        image_url: Option<Option<String>>,
+        dietary_labels: Option<Vec<DietaryLabel>>,
+    }
+
+    /// Tells a missing field, which is kept as is, from `null`, which clears it
+    fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+
+    impl UpdateDishBody {
+        async fn validate(&self, pg_db: &Addr<PgActor>, dish_id: i64) -> Result<(), ApiError> {
+            let mut validator = Validator::new();
+
+            if let Some(dish_name) = &self.dish_name {
+                validator.dish_name(dish_name);
+            }
+            if let Some(price) = self.price {
+                validator.price(price);
+            }
+            if let Some(approx_cook_time_s) = self.approx_cook_time_s {
+                validator.cook_time(approx_cook_time_s);
+            }
+            if let Some(portion_weight_g) = self.portion_weight_g {
+                validator.portion_weight(portion_weight_g);
+            }
+            validator
+                .dish_references(pg_db, self.dish_name.as_deref(), Some(dish_id), &[])
+                .await?;
+
+            validator.into_result()
+        }
+
+        fn is_empty(&self) -> bool {
+            self.dish_name.is_none()
+                && self.dish_type.is_none()
+                && self.price.is_none()
+                && self.approx_cook_time_s.is_none()
+                && self.portion_weight_g.is_none()
+                && self.description.is_none()
+                && self.image_url.is_none()
+                && self.dietary_labels.is_none()
+        }
+
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+            dish_id: i64,
+        ) -> Result<Dish, ApiError> {
+            if self.is_empty() {
+                return Err(ApiError::BadRequest("Nothing to update".to_owned()));
+            }
+
+            self.validate(&state.pg_db, dish_id).await?;
+
+            let dish = state
+                .pg_db
+                .send(worker.acting(UpdateDish {
+                    dish_id,
+                    dish_name: self.dish_name,
+                    dish_type: self.dish_type,
+                    price: self.price,
+                    approx_cook_time_s: self.approx_cook_time_s,
+                    portion_weight_g: self.portion_weight_g,
+                    description: self.description,
+                    image_url: self.image_url,
+                    dietary_labels: self.dietary_labels,
+                }))
+                .await??;
+
+            refresh_cached_dish(state, dish.clone()).await?;
+
+            Ok(dish)
+        }
+    }
+
+    /// Updates cached copies of the dish in redis after it was changed in the database
+    async fn refresh_cached_dish(state: &AppState, dish: Dish) -> Result<(), ApiError> {
+        state
+            .redis_handler
+            .refresh_dish(state.pg_db.clone(), dish, &state.course_order)
+            .await
+            .map_err(|err| {
+                ApiError::CacheOutdated(format!(
+                    "Dish is saved, but cached menus are not refreshed: {err}"
+                ))
             })
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = UpdateDishBody,
+        responses(
+            (status = 200, description = "Updated dish", body = Dish),
+            (status = 400, description = "Malformed or empty body", body = ErrorBody),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+            (
+                status = 500,
+                description = "Dish is saved, cached menus are outdated",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[put("/{dish_id}")]
+    pub async fn update_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<UpdateDishBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = Vec<Ingredient>,
+        responses(
+            (
+                status = 200,
+                description = "New ingredients as (product name, grams) pairs",
+                body = Vec<(String, i32)>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid ingredients", body = ErrorBody),
+        )
+    )]
+    #[put("/{dish_id}/ingredients")]
+    pub async fn replace_dish_ingredients(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<Vec<Ingredient>>,
+    ) -> Result<HttpResponse, ApiError> {
+        let ingredients =
+            replace_ingredients(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(ingredients))
+    }
+
+    /// Returns the new ingredients as (product name, grams) pairs
+    pub(crate) async fn replace_ingredients(
+        state: &AppState,
+        worker: Worker,
+        dish_id: i64,
+        ingredients: Vec<Ingredient>,
+    ) -> Result<Vec<(String, i32)>, ApiError> {
+        let mut validator = Validator::new();
+        validator.ingredients(&ingredients);
+        validator
+            .dish_references(&state.pg_db, None, Some(dish_id), &ingredients)
+            .await?;
+        validator.into_result()?;
+
+        let ingredients = state
+            .pg_db
+            .send(worker.acting(ReplaceDishIngredients {
+                dish_id,
+                ingredients,
+            }))
+            .await??;
+
+        let dish = state.pg_db.send(FetchDish(dish_id)).await??;
+        refresh_cached_dish(state, dish).await?;
+
+        Ok(ingredients)
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (
+                status = 200,
+                description = "Modifier groups of the dish",
+                body = Vec<ModifierGroupInfo>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+        )
+    )]
+    #[get("/{dish_id}/modifiers")]
+    pub async fn get_dish_modifiers(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let groups = state
+            .pg_db
+            .send(FetchDishModifiers(path.into_inner()))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(groups))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct CreateModifierGroupBody {
+        name: String,
+        #[serde(default)]
+        is_multiple: bool,
+        #[serde(default)]
+        is_required: bool,
+        modifiers: Vec<ModifierSpec>,
+    }
+
+    impl CreateModifierGroupBody {
+        async fn validate(&self, pg_db: &Addr<PgActor>) -> Result<(), ApiError> {
+            let mut validator = Validator::new();
+
+            if self.name.trim().is_empty() {
+                validator.add("name", "must not be empty");
+            }
+            if self.modifiers.is_empty() {
+                validator.add("modifiers", "must contain at least one modifier");
+            }
+
+            let mut product_ids = vec![];
+
+            for (idx, spec) in self.modifiers.iter().enumerate() {
+                if spec.name.trim().is_empty() {
+                    validator.add(format!("modifiers[{idx}].name"), "must not be empty");
+                }
+                for (ing_idx, delta) in spec.ingredients.iter().enumerate() {
+                    if delta.delta_g == 0 {
+                        validator.add(
+                            format!("modifiers[{idx}].ingredients[{ing_idx}].delta_g"),
+                            "must not be zero",
+                        );
+                    }
+                    product_ids.push(delta.id);
+                }
+            }
+
+            if !product_ids.is_empty() {
+                let unknown = pg_db.send(FindUnknownProducts(product_ids)).await??;
+
+                for p_id in unknown {
+                    validator.add("modifiers", format!("product {p_id} does not exist"));
+                }
+            }
+
+            validator.into_result()
+        }
+
+        /// Returns all modifier groups of the dish, including the new one
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+            dish_id: i64,
+        ) -> Result<Vec<ModifierGroupInfo>, ApiError> {
+            self.validate(&state.pg_db).await?;
+
+            let groups = state
+                .pg_db
+                .send(worker.acting(CreateModifierGroup {
+                    dish_id,
+                    name: self.name.trim().to_owned(),
+                    is_multiple: self.is_multiple,
+                    is_required: self.is_required,
+                    modifiers: self.modifiers,
+                }))
+                .await??;
+
+            let dish = state.pg_db.send(FetchDish(dish_id)).await??;
+            refresh_cached_dish(state, dish).await?;
+
+            Ok(groups)
+        }
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        request_body = CreateModifierGroupBody,
+        responses(
+            (
+                status = 200,
+                description = "All modifier groups of the dish",
+                body = Vec<ModifierGroupInfo>
+            ),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+            (status = 422, description = "Invalid fields", body = ErrorBody),
+        )
+    )]
+    #[post("/{dish_id}/modifier-groups")]
+    pub async fn create_modifier_group(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<CreateModifierGroupBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let groups = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Ok().json(groups))
+    }
+
+    #[utoipa::path(
+        params(("dish_id" = i64, Path, description = "Dish id")),
+        responses(
+            (status = 200, description = "Archived dish", body = Dish),
+            (status = 404, description = "Dish is not found", body = ErrorBody),
+        )
+    )]
+    #[delete("/{dish_id}")]
+    pub async fn archive_dish(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish = archive(&state, worker, path.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(dish))
+    }
+
+    pub(crate) async fn archive(
+        state: &AppState,
+        worker: Worker,
+        dish_id: i64,
+    ) -> Result<Dish, ApiError> {
+        let dish = state
+            .pg_db
+            .send(worker.acting(ArchiveDish(dish_id)))
+            .await??;
+
+        refresh_cached_dish(state, dish.clone()).await?;
+
+        Ok(dish)
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        create_dish,
+        get_costing,
+        update_dish,
+        replace_dish_ingredients,
+        get_dish_modifiers,
+        create_modifier_group,
+        archive_dish,
+    ))]
+    pub struct ApiDoc;
+}
+
+// sub-route "/products"
+pub mod products_route {
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::db_models::{Product, ProductCost};
+    use crate::services::db_utils::AppState;
+    use crate::services::messages::{
+        FetchDishesWithProduct, FetchProductCostHistory, FetchProducts, SetProductAllergens,
+        SetProductCost,
+    };
+    use crate::services::validation::Validator;
+    use crate::types::Allergen;
+    use actix_web::web::{Data, Json, Path};
+    use actix_web::{get, put, HttpResponse, Responder};
+    use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
+
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All products", body = Vec<Product>),
+        )
+    )]
+    #[get("/all")]
+    pub async fn get_products(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let products = state.pg_db.send(FetchProducts).await??;
+
+        Ok(HttpResponse::Ok().json(products))
+    }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        request_body = Vec<Allergen>,
+        responses(
+            (status = 200, description = "Updated product", body = Product),
+            (status = 404, description = "Product is not found", body = ErrorBody),
+            (
+                status = 500,
+                description = "Product is saved, cached menus are outdated",
+                body = ErrorBody
+            ),
+        )
+    )]
+    #[put("/{product_id}/allergens")]
+    pub async fn set_product_allergens(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<Vec<Allergen>>,
+    ) -> Result<HttpResponse, ApiError> {
+        let product = set_allergens(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(product))
+    }
+
+    pub(crate) async fn set_allergens(
+        state: &AppState,
+        worker: Worker,
+        product_id: i64,
+        mut allergens: Vec<Allergen>,
+    ) -> Result<Product, ApiError> {
+        allergens.sort();
+        allergens.dedup();
+
+        let product = state
+            .pg_db
+            .send(worker.acting(SetProductAllergens {
+                product_id,
+                allergens,
+            }))
+            .await??;
+
+        let cache_outdated = |err: ApiError| {
+            ApiError::CacheOutdated(format!(
+                "Product is saved, but cached menus are not refreshed: {err}"
+            ))
+        };
+
+        // allergens of the dishes are derived from products, so cached ones are outdated now
+        let dishes = state
+            .pg_db
+            .send(FetchDishesWithProduct(product_id))
             .await
-        {
-            Ok(Ok(dish)) => HttpResponse::Ok().json(dish),
-            Ok(Err(err)) => HttpResponse::InternalServerError().json(format!("Error: {err}")),
-            _ => HttpResponse::InternalServerError().json("Unable to insert new waiter"),
+            .map_err(ApiError::from)
+            .and_then(|res| res)
+            .map_err(cache_outdated)?;
+
+        for dish in dishes {
+            state
+                .redis_handler
+                .refresh_dish(state.pg_db.clone(), dish, &state.course_order)
+                .await
+                .map_err(cache_outdated)?;
         }
+
+        Ok(product)
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct SetProductCostBody {
+        cost_per_kg: i32,
+    }
+
+    impl SetProductCostBody {
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+            product_id: i64,
+        ) -> Result<Product, ApiError> {
+            let mut validator = Validator::new();
+            if self.cost_per_kg < 0 {
+                validator.add("cost_per_kg", "must not be negative");
+            }
+            validator.into_result()?;
+
+            state
+                .pg_db
+                .send(worker.acting(SetProductCost {
+                    product_id,
+                    cost_per_kg: self.cost_per_kg,
+                }))
+                .await?
+        }
+    }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        request_body = SetProductCostBody,
+        responses(
+            (status = 200, description = "Updated product", body = Product),
+            (status = 404, description = "Product is not found", body = ErrorBody),
+            (status = 422, description = "Negative cost", body = ErrorBody),
+        )
+    )]
+    #[put("/{product_id}/cost")]
+    pub async fn set_product_cost(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<SetProductCostBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let product = body
+            .into_inner()
+            .save(&state, worker, path.into_inner())
+            .await?;
+
+        Ok(HttpResponse::Ok().json(product))
     }
+
+    #[utoipa::path(
+        params(("product_id" = i64, Path, description = "Product id")),
+        responses(
+            (status = 200, description = "Cost changes, newest first", body = Vec<ProductCost>),
+        )
+    )]
+    #[get("/{product_id}/cost-history")]
+    pub async fn get_product_cost_history(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let history = state
+            .pg_db
+            .send(FetchProductCostHistory(path.into_inner()))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(history))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        get_products,
+        set_product_allergens,
+        set_product_cost,
+        get_product_cost_history,
+    ))]
+    pub struct ApiDoc;
 }
 
 // sub-route "/menu"
 pub mod menu_route {
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
+    use crate::services::conditional::{conditional, Validators};
     use crate::services::db_models::Dish;
     use crate::services::db_utils::AppState;
-    use crate::services::messages::{CreateOrder, FetchDish, FetchDishes, FetchSpecificDishes};
+    use crate::services::messages::{
+        CheckActingWorker, CreateOrder, FetchDish, FetchDishes, FetchSpecificDishes, RecordAudit,
+    };
+    use crate::types::{Allergen, Menu, RedisDish};
     use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
-    use actix_web::web::{Bytes, Data, Json, Path};
-    use actix_web::{delete, get, post, put, HttpResponse, Responder};
+    use actix_web::web::{Bytes, Data, Json, Path, Query};
+    use actix_web::{delete, get, post, put, HttpRequest, HttpResponse, Responder};
     use chrono::NaiveDate;
     use redis::FromRedisValue;
     use serde::Deserialize;
+    use serde_json::{json, Value};
     use std::collections::HashSet;
+    use utoipa::{IntoParams, OpenApi, ToSchema};
+
+    /// Records a change of the menus, which live in redis, to the audit log of the database
+    pub(crate) async fn audit_menu(
+        state: &AppState,
+        worker: Worker,
+        date: &NaiveDate,
+        action: &'static str,
+        before: Value,
+        after: Value,
+    ) -> Result<(), ApiError> {
+        state
+            .pg_db
+            .send(worker.acting(RecordAudit {
+                entity: "menu",
+                entity_id: date.to_string(),
+                action,
+                before,
+                after,
+            }))
+            .await?
+    }
+
+    /// Checks the worker before a menu is changed in redis, which is not undone when the audit
+    /// entry is rejected
+    pub(crate) async fn check_menu_worker(
+        state: &AppState,
+        worker: Worker,
+    ) -> Result<(), ApiError> {
+        match worker.0 {
+            Some(worker_id) => state.pg_db.send(CheckActingWorker(worker_id)).await?,
+            None => Ok(()),
+        }
+    }
+
+    /// Dishes and title of the menu for the date as the audit log keeps them, `null` when
+    /// there is no menu
+    pub(crate) async fn menu_snapshot(
+        state: &AppState,
+        date: &NaiveDate,
+    ) -> Result<Value, ApiError> {
+        let menu = match state.redis_handler.get_menu_by_date(date).await {
+            Ok(menu) => serde_json::from_str::<Menu>(&menu.json)?,
+            Err(ApiError::NotFound(_)) => return Ok(Value::Null),
+            Err(err) => return Err(err),
+        };
+
+        let mut dish_ids = menu.dish_ids().into_iter().collect::<Vec<_>>();
+        dish_ids.sort_unstable();
+
+        Ok(json!({ "dish_ids": dish_ids, "title": menu.title }))
+    }
 
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub(crate) struct MenuFilter {
+        /// comma separated allergens, dishes containing any of them are hidden
+        exclude_allergens: Option<String>,
+    }
+
+    #[utoipa::path(
+        params(MenuFilter),
+        responses(
+            (status = 200, description = "Active menu", body = Menu),
+            (status = 304, description = "Client's copy is still current"),
+            (status = 404, description = "No menu is active", body = ErrorBody),
+            (status = 422, description = "Unknown allergen", body = ErrorBody),
+        )
+    )]
     #[get("")]
-    pub async fn view_menu(state: Data<AppState>) -> impl Responder {
-        match state.redis_handler.get_menu() {
-            Ok(menu_json) => HttpResponse::Ok()
+    pub async fn view_menu(
+        req: HttpRequest,
+        state: Data<AppState>,
+        filter: Query<MenuFilter>,
+    ) -> Result<HttpResponse, ApiError> {
+        let menu = state.redis_handler.get_menu().await?;
+        let validators = menu.revision.as_ref().map(Validators::of_menu);
+
+        conditional(&req, validators, state.menu_max_age_s, || {
+            filtered_menu(menu.json, &filter)
+        })
+    }
+
+    /// Responds with the cached menu as is, unless some allergens are excluded by the filter
+    pub(crate) fn filtered_menu(
+        menu_json: String,
+        filter: &MenuFilter,
+    ) -> Result<HttpResponse, ApiError> {
+        let excluded = match filter.exclude_allergens.as_deref() {
+            Some(list) => Allergen::parse_list(list)
+                .map_err(|err| ApiError::invalid("exclude_allergens", err))?,
+            None => vec![],
+        };
+
+        if excluded.is_empty() {
+            return Ok(HttpResponse::Ok()
                 .append_header(("Content-Type", "application/json"))
-                .body(menu_json),
-            Err(err) => HttpResponse::InternalServerError().json(err),
+                .body(menu_json));
         }
+
+        let menu = serde_json::from_str::<Menu>(&menu_json)?;
+
+        Ok(HttpResponse::Ok().json(menu.without_allergens(&excluded)))
     }
 
+    #[utoipa::path(
+        params(("id" = i64, Path, description = "Dish id")),
+        responses(
+            (status = 200, description = "Dish of the active menu", body = RedisDish),
+            (status = 304, description = "Client's copy is still current"),
+            (status = 404, description = "Dish is not in the active menu", body = ErrorBody),
+        )
+    )]
     #[get("/dish/{id}")]
-    pub async fn get_dish(state: Data<AppState>, path: Path<(i64)>) -> impl Responder {
-        match state.redis_handler.get_dish(path.into_inner()) {
-            Ok(redis_dish_json) => HttpResponse::Ok()
+    pub async fn get_dish(
+        req: HttpRequest,
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let dish_id = path.into_inner();
+        let redis_dish = state.redis_handler.get_dish(dish_id).await?;
+        let validators = redis_dish
+            .revision
+            .as_ref()
+            .map(|revision| Validators::of_dish(revision, dish_id));
+
+        conditional(&req, validators, state.menu_max_age_s, || {
+            Ok(HttpResponse::Ok()
                 .append_header(("Content-Type", "application/json"))
-                .body(redis_dish_json),
-            Err(err) => HttpResponse::NotFound().json(err),
-        }
+                .body(redis_dish.json))
+        })
     }
 
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All dishes", body = Vec<Dish>),
+        )
+    )]
     #[get("/all-dishes")]
-    pub async fn get_dishes(state: Data<AppState>) -> impl Responder {
-        match state.pg_db.send(FetchDishes).await {
-            Ok(Ok(dishes)) => HttpResponse::Ok().json(dishes),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to fetch dishes: {err}"))
-            }
-        }
+    pub async fn get_dishes(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let dishes = state.pg_db.send(FetchDishes).await??;
+
+        Ok(HttpResponse::Ok().json(dishes))
     }
 
-    #[derive(Deserialize)]
-    struct CreateMenuBody {
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct CreateMenuBody {
         dishes: Vec<i64>,
-        date: NaiveDate,
+        pub(crate) date: NaiveDate,
+        #[serde(default)]
+        title: Option<String>,
     }
 
-    #[post("/create-new")]
-    pub async fn create_menu(state: Data<AppState>, body: Bytes) -> impl Responder {
-        let json_input = match String::from_utf8(Vec::from(body.as_ref())) {
-            Ok(val) => val,
-            Err(err) => {
-                return HttpResponse::BadRequest()
-                    .json("Failed to parse request. Non utf-8 characters")
-            }
-        };
+    impl CreateMenuBody {
+        /// Composes the menu of the listed dishes and returns its redis key
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+        ) -> Result<String, ApiError> {
+            let mut unique_ids = HashSet::new();
+            let mut dish_ids = self.dishes;
 
-        let body: CreateMenuBody = match serde_json::from_str(json_input.as_str()) {
-            Ok(val) => val,
-            Err(err) => {
-                return HttpResponse::BadRequest()
-                    .json("Failed to parse request. Body is not a desired structure")
-            }
-        };
+            dish_ids.retain(|id| unique_ids.insert(*id));
 
-        let mut unique_ids = HashSet::new();
-        let mut dish_ids = body.dishes;
-
-        dish_ids.retain(|id| unique_ids.insert(id.clone()));
-
-        match state.pg_db.send(FetchSpecificDishes(dish_ids)).await {
-            Ok(Ok(dishes)) => {
-                match state
-                    .redis_handler
-                    .save_new_menu(state.pg_db.clone(), dishes, &body.date)
-                    .await
-                {
-                    Ok(menu_key) => HttpResponse::Ok().json(menu_key),
-                    Err(err) => HttpResponse::InternalServerError()
-                        .json(format!("Unable to perform action: {err}")),
-                }
-            }
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
+            check_menu_worker(state, worker).await?;
+
+            let dishes = state.pg_db.send(FetchSpecificDishes(dish_ids)).await??;
+            // a menu composed again for the date replaces the previous one
+            let before = menu_snapshot(state, &self.date).await?;
+
+            let menu_key = state
+                .redis_handler
+                .save_new_menu(
+                    state.pg_db.clone(),
+                    dishes,
+                    &self.date,
+                    self.title.clone(),
+                    &state.course_order,
+                )
+                .await?;
+
+            let after = menu_snapshot(state, &self.date).await?;
+            audit_menu(state, worker, &self.date, "create", before, after).await?;
+
+            Ok(menu_key)
         }
     }
 
+    #[utoipa::path(
+        request_body = CreateMenuBody,
+        responses(
+            (
+                status = 200,
+                description = "Redis key of the new menu",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 400, description = "Malformed body", body = ErrorBody),
+        )
+    )]
+    #[post("/create-new")]
+    pub async fn create_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        body: Bytes,
+    ) -> Result<HttpResponse, ApiError> {
+        let json_input = String::from_utf8(Vec::from(body.as_ref())).map_err(|_| {
+            ApiError::BadRequest("Failed to parse request. Non utf-8 characters".to_owned())
+        })?;
+
+        let body: CreateMenuBody = serde_json::from_str(json_input.as_str()).map_err(|_| {
+            ApiError::BadRequest(
+                "Failed to parse request. Body is not a desired structure".to_owned(),
+            )
+        })?;
+
+        let menu_key = body.save(&state, worker).await?;
+
+        Ok(HttpResponse::Ok().json(menu_key))
+    }
+
+    #[utoipa::path(
+        params(("date" = NaiveDate, Path, description = "Date of the menu")),
+        responses(
+            (
+                status = 200,
+                description = "Menu is active",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Menu is not found", body = ErrorBody),
+        )
+    )]
     #[put("/set-active/{date}")]
-    pub async fn set_active_menu(state: Data<AppState>, path: Path<NaiveDate>) -> impl Responder {
+    pub async fn set_active_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<NaiveDate>,
+    ) -> Result<HttpResponse, ApiError> {
         let date = path.into_inner();
 
-        match state.redis_handler.set_active_menu(&date) {
-            Ok(_) => HttpResponse::Ok().json(format!("Successfully set active menu to {date}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        check_menu_worker(&state, worker).await?;
+        state.redis_handler.set_active_menu(&date).await?;
+        let after = json!({ "is_active": true });
+        audit_menu(&state, worker, &date, "activate", Value::Null, after).await?;
+
+        Ok(HttpResponse::Ok().json(format!("Successfully set active menu to {date}")))
     }
 
+    #[utoipa::path(
+        params(("date" = NaiveDate, Path, description = "Date of the menu")),
+        responses(
+            (
+                status = 200,
+                description = "Menu is deleted",
+                body = String,
+                content_type = "application/json"
+            ),
+        )
+    )]
     #[delete("/{date}")]
-    pub async fn delete_menu(state: Data<AppState>, path: Path<NaiveDate>) -> impl Responder {
+    pub async fn delete_menu(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<NaiveDate>,
+    ) -> Result<HttpResponse, ApiError> {
         let date = path.into_inner();
 
-        match state.redis_handler.delete_menu(&date) {
-            Ok(_) => HttpResponse::Ok().json(format!("Successfully deleted menu for {date}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        check_menu_worker(&state, worker).await?;
+        state.redis_handler.delete_menu(&date).await?;
+        let before = json!({ "date": date });
+        audit_menu(&state, worker, &date, "delete", before, Value::Null).await?;
+
+        Ok(HttpResponse::Ok().json(format!("Successfully deleted menu for {date}")))
     }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        view_menu,
+        get_dish,
+        get_dishes,
+        create_menu,
+        set_active_menu,
+        delete_menu,
+    ))]
+    pub struct ApiDoc;
 }
 
 // sub-route "/order"
 pub mod order_route {
+    use crate::errors::{ApiError, ErrorBody};
+    use crate::services::audit::Worker;
     use crate::services::db_utils::AppState;
+    use crate::services::idempotency::IdempotencyKey;
     use crate::services::messages::{
-        AddDishToOrder, ConfirmOrder, CookOrder, CreateOrder, DecrementDishInOrder,
-        DeleteDishFromOrder, FetchDish, FetchOrder, FetchOrders, PayForOrder,
+        AddDishToOrder, BatchEditOrder, ConfirmOrder, CookOrder, CreateOrder, DecrementDishInOrder,
+        DeleteDishFromOrder, FetchDish, FetchOrder, FetchOrders, PayForOrder, SetLineCount,
     };
-    use actix_web::web::{Data, Path};
+    use crate::services::validation::Validator;
+    use crate::types::{BatchEdit, Menu, OrderInfo, OrderLineRef, MAX_LINE_COUNT};
+    use actix_web::web::{Data, Json, Path};
     use actix_web::{delete, get, post, put, HttpResponse, Responder};
+    use chrono::Utc;
     use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+    use utoipa::{OpenApi, ToSchema};
 
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id")),
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+        )
+    )]
     #[get("/get/{order_id}")]
-    pub async fn get_ordered_dishes(state: Data<AppState>, path: Path<i64>) -> impl Responder {
-        let order_id = path.into_inner();
+    pub async fn get_ordered_dishes(
+        state: Data<AppState>,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order = state.pg_db.send(FetchOrder(path.into_inner())).await??;
 
-        match state.pg_db.send(FetchOrder(order_id)).await {
-            Ok(Ok(order)) => HttpResponse::Ok().json(order),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        Ok(HttpResponse::Ok().json(order))
     }
 
+    #[utoipa::path(
+        responses(
+            (status = 200, description = "All orders with their lines", body = Vec<OrderInfo>),
+        )
+    )]
     #[get("/all")]
-    pub async fn get_all_orders(state: Data<AppState>) -> impl Responder {
-        match state.pg_db.send(FetchOrders).await {
-            Ok(Ok(orders)) => HttpResponse::Ok().json(orders),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+    pub async fn get_all_orders(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+        let orders = state.pg_db.send(FetchOrders).await??;
+
+        Ok(HttpResponse::Ok().json(orders))
     }
 
+    #[utoipa::path(
+        params(("table_id" = i64, Path, description = "Table id"), IdempotencyKey),
+        responses(
+            (
+                status = 200,
+                description = "Id of the new order",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 409, description = "Table does not exist", body = ErrorBody),
+        )
+    )]
     #[post("/create-for-table/{table_id}")]
-    pub async fn create_blank_order(state: Data<AppState>, path: Path<i64>) -> impl Responder {
-        let table_id = path.into_inner();
-
-        match state.pg_db.send(CreateOrder(table_id)).await {
-            Ok(Ok(id)) => HttpResponse::Ok().json(id),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+    pub async fn create_blank_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
+        let id = state
+            .pg_db
+            .send(worker.acting(CreateOrder(path.into_inner())))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(id))
     }
 
+    fn normalize_note(note: Option<String>) -> Option<String> {
+        note.map(|note| note.trim().to_owned())
+            .filter(|note| !note.is_empty())
+    }
+
+    #[derive(Deserialize, Default, ToSchema)]
+    pub(crate) struct AddDishBody {
+        #[serde(default)]
+        modifiers: Vec<i64>,
+        note: Option<String>,
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("dish_id" = i64, Path, description = "Dish id"),
+            IdempotencyKey,
+        ),
+        request_body(
+            content = Option<AddDishBody>,
+            description = "Optional modifiers and note of the line"
+        ),
+        responses(
+            (
+                status = 200,
+                description = "Id of the order",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order or dish is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is confirmed or dish is archived",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Invalid modifiers or note", body = ErrorBody),
+        )
+    )]
     #[post("/{order_id}/add/{dish_id}")]
     pub async fn add_dish_to_order(
         state: Data<AppState>,
+        worker: Worker,
         path: Path<(i64, i64)>,
-    ) -> impl Responder {
+        body: Option<Json<AddDishBody>>,
+    ) -> Result<HttpResponse, ApiError> {
         let (order_id, dish_id) = path.into_inner();
+        let body = body.map(Json::into_inner).unwrap_or_default();
 
-        match state.pg_db.send(AddDishToOrder { order_id, dish_id }).await {
-            Ok(Ok(id)) => HttpResponse::Ok().json(id),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        let id = add_line(&state, worker, order_id, dish_id, body).await?;
+
+        Ok(HttpResponse::Ok().json(id))
     }
 
+    /// Adds one portion of the dish to the order, returns id of the order
+    pub(crate) async fn add_line(
+        state: &AppState,
+        worker: Worker,
+        order_id: i64,
+        dish_id: i64,
+        body: AddDishBody,
+    ) -> Result<i64, ApiError> {
+        let note = normalize_note(body.note);
+
+        let mut validator = Validator::new();
+        validator.line_note("note", note.as_deref());
+        validator.into_result()?;
+
+        state
+            .pg_db
+            .send(worker.acting(AddDishToOrder {
+                order_id,
+                dish_id,
+                modifier_ids: body.modifiers,
+                note,
+            }))
+            .await?
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        responses(
+            (
+                status = 200,
+                description = "Order is confirmed",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (status = 409, description = "Order is already confirmed", body = ErrorBody),
+        )
+    )]
     #[post("/{order_id}/confirm")]
-    pub async fn confirm_order(state: Data<AppState>, path: Path<i64>) -> impl Responder {
+    pub async fn confirm_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
         let order_id = path.into_inner();
 
-        match state.pg_db.send(ConfirmOrder(order_id)).await {
-            Ok(Ok(_)) => HttpResponse::Ok().json(format!(
-                "Order with id {order_id} is successfully confirmed"
-            )),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        state
+            .pg_db
+            .send(worker.acting(ConfirmOrder(order_id)))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(format!(
+            "Order with id {order_id} is successfully confirmed"
+        )))
     }
 
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        responses(
+            (
+                status = 200,
+                description = "Order is paid",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is not confirmed or already paid",
+                body = ErrorBody
+            ),
+        )
+    )]
     #[post("/{order_id}/pay")]
-    pub async fn pay_for_order(state: Data<AppState>, path: Path<i64>) -> impl Responder {
+    pub async fn pay_for_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
         let order_id = path.into_inner();
 
-        match state.pg_db.send(PayForOrder(order_id)).await {
-            Ok(Ok(_)) => {
-                HttpResponse::Ok().json(format!("Order with id {order_id} is successfully paid"))
-            }
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        state
+            .pg_db
+            .send(worker.acting(PayForOrder {
+                order_id,
+                tip: 0,
+                day: state.restaurant.business_day(Utc::now()),
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(format!("Order with id {order_id} is successfully paid")))
     }
 
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        responses(
+            (
+                status = 200,
+                description = "Order is cooked",
+                body = String,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is not confirmed or already cooked",
+                body = ErrorBody
+            ),
+        )
+    )]
     #[post("/{order_id}/mark-cooked")]
-    pub async fn cook_order(state: Data<AppState>, path: Path<i64>) -> impl Responder {
+    pub async fn cook_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+    ) -> Result<HttpResponse, ApiError> {
         let order_id = path.into_inner();
 
-        match state.pg_db.send(CookOrder(order_id)).await {
-            Ok(Ok(_)) => {
-                HttpResponse::Ok().json(format!("Order with id {order_id} is successfully cooked"))
-            }
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+        state
+            .pg_db
+            .send(worker.acting(CookOrder(order_id)))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(format!("Order with id {order_id} is successfully cooked")))
     }
 
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("dish_id" = i64, Path, description = "Dish id"),
+            IdempotencyKey,
+        ),
+        responses(
+            (
+                status = 200,
+                description = "Id of the order",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+        )
+    )]
     #[put("/{order_id}/decrement/{dish_id}")]
     pub async fn decrement_dish_in_order(
         state: Data<AppState>,
+        worker: Worker,
         path: Path<(i64, i64)>,
-    ) -> impl Responder {
+    ) -> Result<HttpResponse, ApiError> {
         let (order_id, dish_id) = path.into_inner();
 
-        match state
+        let id = state
             .pg_db
-            .send(DecrementDishInOrder { order_id, dish_id })
-            .await
-        {
-            Ok(Ok(id)) => HttpResponse::Ok().json(id),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
-            }
-        }
+            .send(worker.acting(DecrementDishInOrder {
+                order_id,
+                line: OrderLineRef::Dish(dish_id),
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(id))
     }
 
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("dish_id" = i64, Path, description = "Dish id"),
+            IdempotencyKey,
+        ),
+        responses(
+            (
+                status = 200,
+                description = "Id of the deleted line",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+        )
+    )]
     #[delete("/{order_id}/{dish_id}")]
     pub async fn delete_dish_from_order(
         state: Data<AppState>,
+        worker: Worker,
         path: Path<(i64, i64)>,
-    ) -> impl Responder {
+    ) -> Result<HttpResponse, ApiError> {
         let (order_id, dish_id) = path.into_inner();
 
-        match state
+        let id = state
             .pg_db
-            .send(DeleteDishFromOrder { order_id, dish_id })
-            .await
-        {
-            Ok(Ok(id)) => HttpResponse::Ok().json(id),
-            Ok(Err(err)) => HttpResponse::NotFound().json(format!("Error: {err}")),
-            Err(err) => {
-                HttpResponse::InternalServerError().json(format!("Unable to perform action: {err}"))
+            .send(worker.acting(DeleteDishFromOrder {
+                order_id,
+                line: OrderLineRef::Dish(dish_id),
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(id))
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("line_id" = i64, Path, description = "Order line id"),
+            IdempotencyKey,
+        ),
+        responses(
+            (
+                status = 200,
+                description = "Id of the order",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+        )
+    )]
+    #[put("/{order_id}/line/{line_id}/decrement")]
+    pub async fn decrement_order_line(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<(i64, i64)>,
+    ) -> Result<HttpResponse, ApiError> {
+        let (order_id, line_id) = path.into_inner();
+
+        let id = state
+            .pg_db
+            .send(worker.acting(DecrementDishInOrder {
+                order_id,
+                line: OrderLineRef::Line(line_id),
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(id))
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("line_id" = i64, Path, description = "Order line id"),
+            IdempotencyKey,
+        ),
+        responses(
+            (
+                status = 200,
+                description = "Id of the deleted line",
+                body = i64,
+                content_type = "application/json"
+            ),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+        )
+    )]
+    #[delete("/{order_id}/line/{line_id}")]
+    pub async fn delete_order_line(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<(i64, i64)>,
+    ) -> Result<HttpResponse, ApiError> {
+        let (order_id, line_id) = path.into_inner();
+
+        let id = state
+            .pg_db
+            .send(worker.acting(DeleteDishFromOrder {
+                order_id,
+                line: OrderLineRef::Line(line_id),
+            }))
+            .await??;
+
+        Ok(HttpResponse::Ok().json(id))
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    pub(crate) struct SetLineCountBody {
+        count: i32,
+    }
+
+    impl SetLineCountBody {
+        pub(crate) async fn save(
+            self,
+            state: &AppState,
+            worker: Worker,
+            order_id: i64,
+            line_id: i64,
+        ) -> Result<OrderInfo, ApiError> {
+            let mut validator = Validator::new();
+            validator.line_count("count", self.count, 0);
+            validator.into_result()?;
+
+            state
+                .pg_db
+                .send(worker.acting(SetLineCount {
+                    order_id,
+                    line_id,
+                    count: self.count,
+                }))
+                .await?
+        }
+    }
+
+    #[utoipa::path(
+        params(
+            ("order_id" = i64, Path, description = "Order id"),
+            ("line_id" = i64, Path, description = "Order line id"),
+            IdempotencyKey,
+        ),
+        request_body = SetLineCountBody,
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (status = 409, description = "Order is confirmed", body = ErrorBody),
+            (status = 422, description = "Count is out of range", body = ErrorBody),
+        )
+    )]
+    #[put("/{order_id}/line/{line_id}")]
+    pub async fn set_line_count(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<(i64, i64)>,
+        body: Json<SetLineCountBody>,
+    ) -> Result<HttpResponse, ApiError> {
+        let (order_id, line_id) = path.into_inner();
+
+        let order_info = body
+            .into_inner()
+            .save(&state, worker, order_id, line_id)
+            .await?;
+
+        Ok(HttpResponse::Ok().json(order_info))
+    }
+
+    #[utoipa::path(
+        params(("order_id" = i64, Path, description = "Order id"), IdempotencyKey),
+        request_body = BatchEdit,
+        responses(
+            (status = 200, description = "Order with its lines", body = OrderInfo),
+            (status = 404, description = "Order or line is not found", body = ErrorBody),
+            (
+                status = 409,
+                description = "Order is confirmed or dish is archived",
+                body = ErrorBody
+            ),
+            (status = 422, description = "Invalid lines", body = ErrorBody),
+        )
+    )]
+    #[put("/{order_id}/lines")]
+    pub async fn batch_edit_order(
+        state: Data<AppState>,
+        worker: Worker,
+        path: Path<i64>,
+        body: Json<BatchEdit>,
+    ) -> Result<HttpResponse, ApiError> {
+        let order_info = batch_edit(&state, worker, path.into_inner(), body.into_inner()).await?;
+
+        Ok(HttpResponse::Ok().json(order_info))
+    }
+
+    pub(crate) async fn batch_edit(
+        state: &AppState,
+        worker: Worker,
+        order_id: i64,
+        mut edit: BatchEdit,
+    ) -> Result<OrderInfo, ApiError> {
+        let menu_dishes =
+            serde_json::from_str::<Menu>(&state.redis_handler.get_menu().await?.json)?.dish_ids();
+
+        let mut validator = Validator::new();
+
+        match &mut edit {
+            BatchEdit::Replace(lines) => {
+                for (idx, line) in lines.iter_mut().enumerate() {
+                    line.note = normalize_note(line.note.take());
+
+                    validator.line_count(format!("replace[{idx}].count"), line.count, 0);
+                    validator.line_note(format!("replace[{idx}].note"), line.note.as_deref());
+                    if line.count > 0 && !menu_dishes.contains(&line.dish_id) {
+                        validator.add(
+                            format!("replace[{idx}].dish_id"),
+                            format!("dish {} is not in the active menu", line.dish_id),
+                        );
+                    }
+                }
+            }
+            BatchEdit::Deltas(deltas) => {
+                for (idx, line) in deltas.iter_mut().enumerate() {
+                    line.note = normalize_note(line.note.take());
+
+                    validator.line_count(
+                        format!("deltas[{idx}].delta"),
+                        line.delta,
+                        -MAX_LINE_COUNT,
+                    );
+                    validator.line_note(format!("deltas[{idx}].note"), line.note.as_deref());
+                    if line.delta == 0 {
+                        validator.add(format!("deltas[{idx}].delta"), "must not be zero");
+                    }
+                    if line.delta > 0 && !menu_dishes.contains(&line.dish_id) {
+                        validator.add(
+                            format!("deltas[{idx}].dish_id"),
+                            format!("dish {} is not in the active menu", line.dish_id),
+                        );
+                    }
+                }
             }
         }
+
+        validator.into_result()?;
+
+        state
+            .pg_db
+            .send(worker.acting(BatchEditOrder { order_id, edit }))
+            .await?
     }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(
+        get_ordered_dishes,
+        get_all_orders,
+        create_blank_order,
+        add_dish_to_order,
+        confirm_order,
+        pay_for_order,
+        cook_order,
+        decrement_dish_in_order,
+        delete_dish_from_order,
+        decrement_order_line,
+        delete_order_line,
+        set_line_count,
+        batch_edit_order,
+    ))]
+    pub struct ApiDoc;
 }
 
 // sub-route "/test"
 pub mod test_route {
+    use crate::errors::ApiError;
+    use crate::services::audit::Worker;
     use crate::services::db_utils::AppState;
+    use crate::services::menu_route::{audit_menu, check_menu_worker, menu_snapshot};
     use crate::services::messages::{AddWaiter, FetchDishes};
     use actix_web::web::Data;
     use actix_web::{get, post, HttpResponse, Responder};
     use redis::Commands;
     use std::collections::HashSet;
+    use utoipa::OpenApi;
 
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Service is up",
+                body = String,
+                content_type = "text/plain"
+            )
+        )
+    )]
     #[get("/healthcheck")]
     pub async fn healthcheck() -> impl Responder {
         HttpResponse::Ok().body("I'm alive!")
     }
 
+    #[utoipa::path(
+        responses(
+            (
+                status = 200,
+                description = "Menu of one dish per type is created for today",
+                body = String,
+                content_type = "application/json"
+            ),
+        )
+    )]
     #[post("/create-mock-menu")]
-    pub async fn create_mock_menu(state: Data<AppState>) -> impl Responder {
-        let mut dishes = match state.pg_db.send(FetchDishes).await {
-            Ok(Ok(resp)) => resp,
-            _ => {
-                return HttpResponse::InternalServerError()
-                    .json("Unable to get dishes from sql database")
-            }
-        };
+    pub async fn create_mock_menu(
+        state: Data<AppState>,
+        worker: Worker,
+    ) -> Result<HttpResponse, ApiError> {
+        check_menu_worker(&state, worker).await?;
+
+        let mut dishes = state.pg_db.send(FetchDishes).await??;
 
         let mut unique_dish_types = HashSet::new();
-        dishes.retain(|dish| unique_dish_types.insert(dish.type_.clone()));
+        dishes.retain(|dish| unique_dish_types.insert(dish.type_));
+
+        let date = state.restaurant.business_day(chrono::Utc::now());
+        let before = menu_snapshot(&state, &date).await?;
 
-        match state
+        let key = state
             .redis_handler
             .save_new_menu(
                 state.pg_db.clone(),
                 dishes,
-                &chrono::Local::now().date_naive(),
+                &date,
+                None,
+                &state.course_order,
             )
-            .await
-        {
-            Ok(key) => HttpResponse::Ok().json(format!(
-                "Menu is successfully composed and placed into redis db by the key '{key}'"
-            )),
-            Err(err) => HttpResponse::InternalServerError().json(err),
-        }
+            .await?;
+
+        let after = menu_snapshot(&state, &date).await?;
+        audit_menu(&state, worker, &date, "create", before, after).await?;
+
+        Ok(HttpResponse::Ok().json(format!(
+            "Menu is successfully composed and placed into redis db by the key '{key}'"
+        )))
     }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(healthcheck, create_mock_menu))]
+    pub struct ApiDoc;
 }
@@ -0,0 +1,77 @@
+//! Checks that the OpenAPI document describes every route registered in `main.rs`.
+
+use utoipa::openapi::path::Operation;
+use utoipa::OpenApi;
+
+use crate::services::ApiDoc;
+
+/// Handlers serving the document itself
+const NOT_DESCRIBED: &[&str] = &["openapi_json"];
+
+/// `(scope, handler name)` of every `.service(services::...)` registration in `main.rs`
+fn registered_handlers() -> Vec<(String, String)> {
+    let mut scope = String::new();
+    let mut handlers = vec![];
+
+    for line in include_str!("../main.rs").lines().map(str::trim) {
+        if let Some((_, rest)) = line.split_once("web::scope(\"") {
+            scope = rest.split('"').next().unwrap_or_default().to_owned();
+        } else if let Some(rest) = line.strip_prefix(".service(services::") {
+            let path = rest.trim_end_matches([')', ',']);
+            let name = path.rsplit("::").next().unwrap_or(path);
+
+            handlers.push((scope.clone(), name.to_owned()));
+        }
+    }
+
+    handlers
+}
+
+/// `(path, operation id)` of every operation in the document
+fn described_operations() -> Vec<(String, String)> {
+    ApiDoc::openapi()
+        .paths
+        .paths
+        .into_iter()
+        .flat_map(|(path, item)| {
+            [
+                item.get,
+                item.put,
+                item.post,
+                item.delete,
+                item.patch,
+                item.head,
+            ]
+            .into_iter()
+            .flatten()
+            .filter_map(move |op: Operation| Some((path.clone(), op.operation_id?)))
+        })
+        .collect()
+}
+
+#[test]
+fn every_registered_route_is_described() {
+    let handlers = registered_handlers();
+    let operations = described_operations();
+
+    assert!(
+        handlers.len() > NOT_DESCRIBED.len(),
+        "no routes are found in main.rs"
+    );
+
+    let missing = handlers
+        .iter()
+        .filter(|(_, name)| !NOT_DESCRIBED.contains(&name.as_str()))
+        .filter(|(scope, name)| {
+            !operations
+                .iter()
+                .any(|(path, op_id)| op_id == name && path.starts_with(scope.as_str()))
+        })
+        .map(|(scope, name)| format!("{scope} {name}"))
+        .collect::<Vec<_>>();
+
+    assert!(
+        missing.is_empty(),
+        "routes are not described in the OpenAPI document: {missing:?}"
+    );
+}
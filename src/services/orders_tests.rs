@@ -0,0 +1,224 @@
+//! Runs order mutations concurrently against a real database, see [`crate::services::test_db`].
+
+use diesel::prelude::*;
+use futures::future::join_all;
+
+use crate::errors::ApiError;
+use crate::services::messages::{
+    AddDishToOrder, BatchEditOrder, ConfirmOrder, CreateOrder, FetchOrder, MergeOrders,
+    PayForOrder, TransferOrder,
+};
+use crate::services::test_db::{add_dishes, setup, CONCURRENCY, DISH_PRICE, PRODUCT_STOCK_G};
+use crate::types::{BatchEdit, LineDelta};
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn concurrent_additions_are_not_lost() {
+    let fx = setup("concurrent additions").await;
+
+    add_dishes(&fx, CONCURRENCY).await;
+
+    let info = fx
+        .pg_db
+        .send(FetchOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(info.dishes.len(), 1);
+    assert_eq!(info.dishes[0].count, CONCURRENCY as i32);
+    assert_eq!(info.order.total_cost, DISH_PRICE * CONCURRENCY as i32);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn line_is_removed_when_delta_exceeds_count() {
+    let fx = setup("excessive delta").await;
+
+    add_dishes(&fx, 2).await;
+
+    let info = fx
+        .pg_db
+        .send(BatchEditOrder {
+            order_id: fx.order_id,
+            edit: BatchEdit::Deltas(vec![LineDelta {
+                dish_id: fx.dish_id,
+                delta: -5,
+                modifiers: vec![],
+                note: None,
+            }]),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(info.dishes.is_empty());
+    assert_eq!(info.order.total_cost, 0);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn order_is_confirmed_once() {
+    use crate::schema::products::{dsl::products, in_stock_g};
+
+    let fx = setup("concurrent confirmations").await;
+
+    add_dishes(&fx, 2).await;
+
+    let results =
+        join_all((0..CONCURRENCY).map(|_| fx.pg_db.send(ConfirmOrder(fx.order_id)))).await;
+    let succeeded = results
+        .into_iter()
+        .filter(|res| matches!(res, Ok(Ok(()))))
+        .count();
+
+    assert_eq!(succeeded, 1);
+
+    let stock = products
+        .find(fx.product_id)
+        .select(in_stock_g)
+        .get_result::<i32>(&mut fx.pool.get().unwrap())
+        .unwrap();
+
+    assert_eq!(stock, PRODUCT_STOCK_G - 2 * 100);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn order_is_paid_once() {
+    use crate::schema::stats::{day, dsl::stats, income};
+
+    let fx = setup("concurrent payments").await;
+
+    add_dishes(&fx, 1).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mut conn = fx.pool.get().unwrap();
+    let today = chrono::Utc::now().date_naive();
+    let income_before = stats
+        .filter(day.eq(today))
+        .select(income)
+        .get_result::<i32>(&mut conn)
+        .optional()
+        .unwrap()
+        .unwrap_or(0);
+
+    let results = join_all((0..CONCURRENCY).map(|_| {
+        fx.pg_db.send(PayForOrder {
+            order_id: fx.order_id,
+            tip: 0,
+            day: today,
+        })
+    }))
+    .await;
+    let succeeded = results
+        .into_iter()
+        .filter(|res| matches!(res, Ok(Ok(()))))
+        .count();
+
+    assert_eq!(succeeded, 1);
+
+    let income_after = stats
+        .filter(day.eq(today))
+        .select(income)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+
+    assert_eq!(income_after, income_before + DISH_PRICE);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn merged_orders_sum_lines_of_same_dish() {
+    use crate::schema::{audit_log, orders, tables};
+
+    let fx = setup("order merge").await;
+
+    let other_table_id = diesel::insert_into(tables::table)
+        .values(tables::seat_count.eq(2))
+        .returning(tables::id)
+        .get_result::<i64>(&mut fx.pool.get().unwrap())
+        .unwrap();
+    let other_order_id = fx
+        .pg_db
+        .send(CreateOrder(other_table_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    add_dishes(&fx, 2).await;
+    for _ in 0..3 {
+        fx.pg_db
+            .send(AddDishToOrder {
+                order_id: other_order_id,
+                dish_id: fx.dish_id,
+                modifier_ids: vec![],
+                note: None,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    // merges in opposite directions must not deadlock, the second one finds its order gone
+    let (into_first, into_other) = futures::join!(
+        fx.pg_db.send(MergeOrders {
+            order_id: fx.order_id,
+            from_order_id: other_order_id,
+        }),
+        fx.pg_db.send(MergeOrders {
+            order_id: other_order_id,
+            from_order_id: fx.order_id,
+        }),
+    );
+    let (kept, gone) = match (into_first.unwrap(), into_other.unwrap()) {
+        (Ok(()), Err(ApiError::NotFound(_))) => (fx.order_id, other_order_id),
+        (Err(ApiError::NotFound(_)), Ok(())) => (other_order_id, fx.order_id),
+        results => panic!("exactly one merge must succeed: {results:?}"),
+    };
+
+    let info = fx.pg_db.send(FetchOrder(kept)).await.unwrap().unwrap();
+
+    assert_eq!(info.dishes.len(), 1);
+    assert_eq!(info.dishes[0].count, 5);
+    assert_eq!(info.order.total_cost, 5 * DISH_PRICE);
+
+    let mut conn = fx.pool.get().unwrap();
+    let remaining = orders::table
+        .find(gone)
+        .count()
+        .get_result::<i64>(&mut conn)
+        .unwrap();
+    assert_eq!(remaining, 0);
+
+    // the kept order may sit at either table, a third one is never a no-op
+    let third_table_id = diesel::insert_into(tables::table)
+        .values(tables::seat_count.eq(2))
+        .returning(tables::id)
+        .get_result::<i64>(&mut conn)
+        .unwrap();
+    fx.pg_db
+        .send(TransferOrder {
+            order_id: kept,
+            table_id: third_table_id,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let actions = audit_log::table
+        .filter(audit_log::entity.eq("order"))
+        .filter(audit_log::entity_id.eq_any([kept.to_string(), gone.to_string()]))
+        .select(audit_log::action)
+        .order(audit_log::id)
+        .load::<String>(&mut conn)
+        .unwrap();
+    assert_eq!(
+        actions[actions.len() - 3..],
+        ["merge", "merged_into", "transfer"]
+    );
+}
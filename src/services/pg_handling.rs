@@ -1,71 +1,295 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
 use super::messages::{
-    AddDishToOrder, AddWaiter, ConfirmOrder, CookOrder, CreateDish, CreateOrder,
-    DecrementDishInOrder, DeleteDishFromOrder, FetchDish, FetchDishIngredients, FetchDishes,
-    FetchOrder, FetchOrders, FetchSpecificDishes, FetchWaiters, PayForOrder,
+    AddDishToOrder, AddWaiter, ArchiveDish, AsWorker, AssignTableWaiter, BatchEditOrder,
+    CheckActingWorker, ClockIn, ClockOut, ConfirmOrder, CookOrder, CreateDish, CreateModifierGroup,
+    CreateOrder, DecrementDishInOrder, DeleteDishFromOrder, FetchAuditLog, FetchDish,
+    FetchDishAllergens, FetchDishFoodCosts, FetchDishIngredients, FetchDishModifiers, FetchDishes,
+    FetchDishesWithProduct, FetchOnShiftStaff, FetchOrder, FetchOrders, FetchProductCostHistory,
+    FetchProducts, FetchSpecificDishes, FetchTable, FetchTableOrder, FetchTables,
+    FetchTipPayoutInputs, FetchWaiters, FindUnknownProducts, IsDishNameTaken, LinkWaiterWorker,
+    MergeOrders, OpenTableOrder, PayForOrder, Ping, RecordAudit, ReplaceDishIngredients,
+    SetLineCount, SetProductAllergens, SetProductCost, ShiftOf, TransferOrder, UpdateDish,
+};
+use crate::errors::ApiError;
+use crate::schema::{dishes, orders};
+use crate::services::audit::{changes, WORKER_HEADER};
+use crate::services::db_models::{
+    AuditEntry, Dish, Modifier, ModifierGroup, Order, Product, ProductCost, Shift, Table, Waiter,
 };
-use crate::schema::orders;
-use crate::services::db_models::{Dish, Order, Waiter};
 use crate::services::db_utils::PgActor;
 use crate::services::insertable::DishProductMapping;
-use crate::types::{DishType, DishWithCount, OrderInfo};
-use actix::Handler;
-use chrono::{Local, NaiveDateTime};
+use crate::types::{
+    Allergen, BatchEdit, DietaryLabel, DishType, DishWithCount, ModifierGroupInfo, OnShiftStaff,
+    OrderInfo, OrderLineRef, PoolStatus, ShiftSummary, StaffMember, TipPayoutInputs, WaiterTips,
+};
+use actix::{Handler, Message};
+use chrono::{DateTime, NaiveDate, Utc};
 use diesel::connection::SimpleConnection;
 use diesel::expression::AsExpression;
 use diesel::query_builder::AsChangeset;
 use diesel::{
     r2d2::{ConnectionManager, Pool, PooledConnection},
     result::{DatabaseErrorKind, Error},
-    EqAll, ExpressionMethods, Insertable, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    BoolExpressionMethods, EqAll, ExpressionMethods, Insertable, OptionalExtension, PgConnection,
+    QueryDsl, QueryResult, RunQueryDsl,
 };
+use serde::Serialize;
+use serde_json::{json, Value};
 
 fn establish_connection(
     pool: &Pool<ConnectionManager<PgConnection>>,
-) -> Result<PooledConnection<ConnectionManager<PgConnection>>, Error> {
-    match pool.get() {
-        Ok(val) => Ok(val),
-        Err(_) => Err(connection_err()),
+) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ApiError> {
+    pool.get().map_err(|err| {
+        ApiError::Unavailable(format!(
+            "Failed to establish connection with database: {err}"
+        ))
+    })
+}
+
+thread_local! {
+    /// Worker on whose behalf this thread of the arbiter handles the current message
+    static ACTING_WORKER: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+/// Lets the messages be sent on behalf of a worker, see [`AsWorker`]
+macro_rules! handle_as_worker {
+    ($($msg:ty),+ $(,)?) => {$(
+        impl Handler<AsWorker<$msg>> for PgActor {
+            type Result = <$msg as Message>::Result;
+
+            fn handle(&mut self, msg: AsWorker<$msg>, ctx: &mut Self::Context) -> Self::Result {
+                // a message is handled start to end on one thread, so the worker is never seen
+                // by another one
+                ACTING_WORKER.set(msg.worker_id);
+                let res = Handler::<$msg>::handle(self, msg.msg, ctx);
+                ACTING_WORKER.set(None);
+
+                res
+            }
+        }
+    )+};
+}
+
+handle_as_worker!(
+    AddWaiter,
+    ClockIn,
+    ClockOut,
+    AssignTableWaiter,
+    LinkWaiterWorker,
+    CreateDish,
+    UpdateDish,
+    ReplaceDishIngredients,
+    ArchiveDish,
+    CreateModifierGroup,
+    SetProductAllergens,
+    SetProductCost,
+    CreateOrder,
+    OpenTableOrder,
+    AddDishToOrder,
+    DecrementDishInOrder,
+    DeleteDishFromOrder,
+    SetLineCount,
+    BatchEditOrder,
+    ConfirmOrder,
+    CookOrder,
+    PayForOrder,
+    TransferOrder,
+    MergeOrders,
+    RecordAudit,
+);
+
+fn to_json(value: &impl Serialize) -> Result<Value, ApiError> {
+    serde_json::to_value(value)
+        .map_err(|err| ApiError::Internal(format!("Failed to compose audit entry: {err}")))
+}
+
+/// Appends an entry with the changed values to the audit log, in the transaction of the change
+/// itself. `Value::Null` stands for the missing side of a created or deleted entity
+fn audit(
+    conn: &mut PgConnection,
+    entity: &str,
+    entity_id: impl ToString,
+    action: &str,
+    before: Value,
+    after: Value,
+) -> Result<(), ApiError> {
+    use crate::schema::audit_log;
+
+    let worker_id = ACTING_WORKER.get();
+
+    diesel::insert_into(audit_log::table)
+        .values((
+            audit_log::worker_id.eq(worker_id),
+            audit_log::entity.eq(entity),
+            audit_log::entity_id.eq(entity_id.to_string()),
+            audit_log::action.eq(action),
+            audit_log::diff.eq(changes(before, after)),
+            audit_log::created_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .map_err(|err| match err {
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                unknown_acting_worker(worker_id.unwrap_or_default())
+            }
+            err => err.into(),
+        })?;
+
+    Ok(())
+}
+
+fn unknown_acting_worker(wr_id: i32) -> ApiError {
+    ApiError::BadRequest(format!(
+        "Worker {wr_id} of {WORKER_HEADER} header is not found"
+    ))
+}
+
+/// Order with its lines, as the audit log keeps it
+fn order_snapshot(conn: &mut PgConnection, ord_id: i64) -> Result<Value, ApiError> {
+    use crate::schema::dish_to_order;
+
+    let order = orders::table.find(ord_id).get_result::<Order>(conn)?;
+    let lines = dish_to_order::table
+        .filter(dish_to_order::order_id.eq(ord_id))
+        .order(dish_to_order::id)
+        .select((
+            dish_to_order::id,
+            dish_to_order::dish_id,
+            dish_to_order::count,
+            dish_to_order::note,
+        ))
+        .load::<(i64, i64, i32, Option<String>)>(conn)?;
+
+    let mut snapshot = to_json(&order)?;
+    snapshot["lines"] = lines
+        .into_iter()
+        .map(|(line_id, dish_id, count, note)| {
+            json!({ "line_id": line_id, "dish_id": dish_id, "count": count, "note": note })
+        })
+        .collect();
+
+    Ok(snapshot)
+}
+
+impl Handler<RecordAudit> for PgActor {
+    type Result = Result<(), ApiError>;
+
+    fn handle(&mut self, msg: RecordAudit, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = establish_connection(&self.0)?;
+
+        audit(
+            &mut conn,
+            msg.entity,
+            msg.entity_id,
+            msg.action,
+            msg.before,
+            msg.after,
+        )
     }
 }
 
-fn connection_err() -> Error {
-    Error::DatabaseError(
-        DatabaseErrorKind::ClosedConnection,
-        Box::new("Failed to establish connection".to_owned()),
-    )
+impl Handler<CheckActingWorker> for PgActor {
+    type Result = Result<(), ApiError>;
+
+    fn handle(&mut self, msg: CheckActingWorker, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::worker;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        // the audit log refers to deleted workers as well
+        worker::table
+            .find(msg.0)
+            .select(worker::id)
+            .get_result::<i32>(&mut conn)
+            .optional()?
+            .ok_or_else(|| unknown_acting_worker(msg.0))?;
+
+        Ok(())
+    }
 }
 
-fn get_dish_price(mut conn: &mut PgConnection, dish_id: i64) -> Result<i32, Error> {
-    use crate::schema::dishes::dsl::dishes;
-    use crate::schema::dishes::price;
+impl Handler<FetchAuditLog> for PgActor {
+    type Result = Result<Vec<AuditEntry>, ApiError>;
+
+    fn handle(&mut self, msg: FetchAuditLog, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::audit_log;
+
+        let mut conn = establish_connection(&self.0)?;
 
-    match dishes.select(price).find(dish_id).first::<i32>(conn) {
-        Ok(val) => Ok(val),
-        Err(_) => Err(get_db_err("Failed to get dish's price")),
+        let mut query = audit_log::table.into_boxed();
+
+        if let Some(entity) = msg.entity {
+            query = query.filter(audit_log::entity.eq(entity));
+        }
+        if let Some(entity_id) = msg.entity_id {
+            query = query.filter(audit_log::entity_id.eq(entity_id));
+        }
+        if let Some(action) = msg.action {
+            query = query.filter(audit_log::action.eq(action));
+        }
+        if let Some(worker_id) = msg.worker_id {
+            query = query.filter(audit_log::worker_id.eq(worker_id));
+        }
+        if let Some(from) = msg.from {
+            query = query.filter(audit_log::created_at.ge(from));
+        }
+        if let Some(to) = msg.to {
+            query = query.filter(audit_log::created_at.lt(to));
+        }
+        if let Some(before_id) = msg.before_id {
+            query = query.filter(audit_log::id.lt(before_id));
+        }
+
+        query
+            .order(audit_log::id.desc())
+            .limit(msg.limit)
+            .load::<AuditEntry>(&mut conn)
+            .map_err(ApiError::from)
     }
 }
 
-fn get_db_err(msg: &str) -> Error {
-    Error::DatabaseError(
-        DatabaseErrorKind::UnableToSendCommand,
-        Box::new(msg.to_owned()),
-    )
+impl Handler<Ping> for PgActor {
+    type Result = Result<PoolStatus, ApiError>;
+
+    fn handle(&mut self, msg: Ping, _: &mut Self::Context) -> Self::Result {
+        let state = self.0.state();
+        let max_size = self.0.max_size();
+        let in_use = state.connections - state.idle_connections;
+
+        let mut conn = self.0.get_timeout(msg.timeout).map_err(|err| {
+            ApiError::Unavailable(format!(
+                "Failed to establish connection with database: {err}"
+            ))
+        })?;
+
+        conn.batch_execute("SELECT 1")?;
+
+        Ok(PoolStatus {
+            max_size,
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+            saturation: in_use as f64 / max_size as f64,
+        })
+    }
 }
 
 impl Handler<FetchWaiters> for PgActor {
-    type Result = QueryResult<Vec<Waiter>>;
+    type Result = Result<Vec<Waiter>, ApiError>;
 
     fn handle(&mut self, _msg: FetchWaiters, _ctx: &mut Self::Context) -> Self::Result {
         use crate::schema::waiters::dsl::waiters;
 
         let mut conn = establish_connection(&self.0)?;
 
-        waiters.get_results::<Waiter>(&mut conn)
+        waiters
+            .get_results::<Waiter>(&mut conn)
+            .map_err(ApiError::from)
     }
 }
 
 impl Handler<AddWaiter> for PgActor {
-    type Result = QueryResult<()>;
+    type Result = Result<Waiter, ApiError>;
 
     fn handle(&mut self, msg: AddWaiter, _ctx: &mut Self::Context) -> Self::Result {
         use crate::schema::waiters::dsl::waiters;
@@ -74,31 +298,497 @@ impl Handler<AddWaiter> for PgActor {
 
         let mut conn = establish_connection(&self.0)?;
 
-        diesel::insert_into(waiters)
-            .values(NewWaiter {
-                first_name: msg.first_name,
-                last_name: msg.last_name,
+        conn.build_transaction().run(|trx_conn| {
+            let waiter = diesel::insert_into(waiters)
+                .values(NewWaiter {
+                    first_name: msg.first_name,
+                    last_name: msg.last_name,
+                })
+                .get_result::<Waiter>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "waiter",
+                waiter.id,
+                "create",
+                Value::Null,
+                to_json(&waiter)?,
+            )?;
+
+            Ok(waiter)
+        })
+    }
+}
+
+fn find_waiter(conn: &mut PgConnection, w_id: i64) -> Result<Waiter, ApiError> {
+    use crate::schema::waiters::dsl::waiters;
+
+    waiters
+        .find(w_id)
+        .get_result::<Waiter>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound(format!("Waiter {w_id} is not found")))
+}
+
+fn find_worker(conn: &mut PgConnection, wr_id: i32) -> Result<(), ApiError> {
+    use crate::schema::worker;
+
+    worker::table
+        .find(wr_id)
+        .filter(worker::deleted_at.is_null())
+        .select(worker::id)
+        .get_result::<i32>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound(format!("Worker {wr_id} is not found")))?;
+
+    Ok(())
+}
+
+/// Waiter and staff record of the one clocking in or out, at least one of them is present
+fn find_shift_owner(
+    conn: &mut PgConnection,
+    of: ShiftOf,
+) -> Result<(Option<i64>, Option<i32>), ApiError> {
+    use crate::schema::waiters;
+
+    match of {
+        ShiftOf::Waiter(w_id) => {
+            let waiter = find_waiter(conn, w_id)?;
+
+            Ok((Some(waiter.id), waiter.worker_id))
+        }
+        ShiftOf::Worker(wr_id) => {
+            find_worker(conn, wr_id)?;
+
+            let w_id = waiters::table
+                .filter(waiters::worker_id.eq(wr_id))
+                .select(waiters::id)
+                .get_result::<i64>(conn)
+                .optional()?;
+
+            Ok((w_id, Some(wr_id)))
+        }
+    }
+}
+
+/// Active workers by id with titles of their roles
+fn find_staff_members(
+    conn: &mut PgConnection,
+    worker_ids: &[i32],
+) -> Result<BTreeMap<i32, StaffMember>, ApiError> {
+    use crate::schema::{worker, worker_role};
+
+    let members = worker::table
+        .inner_join(worker_role::table)
+        .filter(worker::id.eq_any(worker_ids))
+        .filter(worker::deleted_at.is_null())
+        .select((
+            worker::id,
+            worker::first_name,
+            worker::last_name,
+            worker_role::title,
+        ))
+        .load::<(i32, String, String, String)>(conn)?;
+
+    Ok(members
+        .into_iter()
+        .map(|(worker_id, first_name, last_name, role)| {
+            let member = StaffMember {
+                worker_id,
+                name: format!("{first_name} {last_name}"),
+                role,
+            };
+
+            (worker_id, member)
+        })
+        .collect())
+}
+
+impl Handler<ClockIn> for PgActor {
+    type Result = Result<Shift, ApiError>;
+
+    fn handle(&mut self, msg: ClockIn, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::shifts::{dsl::shifts, started_at, waiter_id, worker_id};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            let (w_id, wr_id) = find_shift_owner(trx_conn, msg.0)?;
+
+            let shift = diesel::insert_into(shifts)
+                .values((
+                    waiter_id.eq(w_id),
+                    worker_id.eq(wr_id),
+                    started_at.eq(Utc::now()),
+                ))
+                .get_result::<Shift>(trx_conn)
+                .map_err(|err| match err {
+                    Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                        ApiError::conflict(
+                            "already_on_shift",
+                            format!("{} is already on shift", msg.0),
+                        )
+                    }
+                    err => ApiError::from(err),
+                })?;
+
+            audit(
+                trx_conn,
+                "shift",
+                shift.id,
+                "clock_in",
+                Value::Null,
+                to_json(&shift)?,
+            )?;
+
+            Ok(shift)
+        })
+    }
+}
+
+impl Handler<ClockOut> for PgActor {
+    type Result = Result<ShiftSummary, ApiError>;
+
+    fn handle(&mut self, msg: ClockOut, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::{shifts, tables};
+        use diesel::dsl::count_star;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            let (w_id, wr_id) = find_shift_owner(trx_conn, msg.0)?;
+
+            let ended_at = Utc::now();
+
+            // a missing id compares as `= NULL`, which matches no row
+            let shift = diesel::update(
+                shifts::table
+                    .filter(shifts::waiter_id.eq(w_id).or(shifts::worker_id.eq(wr_id)))
+                    .filter(shifts::ended_at.is_null()),
+            )
+            .set(shifts::ended_at.eq(ended_at))
+            .get_result::<Shift>(trx_conn)
+            .optional()?
+            .ok_or_else(|| {
+                ApiError::conflict("not_on_shift", format!("{} is not on shift", msg.0))
+            })?;
+
+            let released_table_ids =
+                diesel::update(tables::table.filter(tables::waiter_id.eq(shift.waiter_id)))
+                    .set(tables::waiter_id.eq(None::<i64>))
+                    .returning(tables::id)
+                    .get_results::<i64>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "shift",
+                shift.id,
+                "clock_out",
+                json!({ "ended_at": null, "table_ids": released_table_ids }),
+                json!({ "ended_at": shift.ended_at, "table_ids": [] }),
+            )?;
+
+            let (orders_served, revenue) = orders::table
+                .filter(orders::waiter_id.eq(shift.waiter_id))
+                .filter(orders::paid_at.between(shift.started_at, ended_at))
+                .select((count_star(), diesel::dsl::sum(orders::total_cost)))
+                .get_result::<(i64, Option<i64>)>(trx_conn)?;
+
+            let open_orders = orders::table
+                .filter(orders::waiter_id.eq(shift.waiter_id))
+                .filter(orders::is_paid.eq(false))
+                .order(orders::id)
+                .load::<Order>(trx_conn)?;
+
+            Ok(ShiftSummary {
+                shift,
+                orders_served,
+                revenue: revenue.unwrap_or_default(),
+                open_orders,
+                released_table_ids,
+            })
+        })
+    }
+}
+
+impl Handler<FetchOnShiftStaff> for PgActor {
+    type Result = Result<Vec<OnShiftStaff>, ApiError>;
+
+    fn handle(&mut self, _msg: FetchOnShiftStaff, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::{shifts, waiters};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        let on_shift = shifts::table
+            .left_join(waiters::table)
+            .filter(shifts::ended_at.is_null())
+            .order(shifts::started_at)
+            .load::<(Shift, Option<Waiter>)>(&mut conn)?;
+
+        let worker_ids = on_shift
+            .iter()
+            .filter_map(|(shift, _)| shift.worker_id)
+            .collect::<Vec<_>>();
+        let mut staff = find_staff_members(&mut conn, &worker_ids)?;
+
+        Ok(on_shift
+            .into_iter()
+            .map(|(shift, waiter)| OnShiftStaff {
+                worker: shift
+                    .worker_id
+                    .and_then(|worker_id| staff.remove(&worker_id)),
+                waiter,
+                shift,
             })
-            .execute(&mut conn)?;
+            .collect())
+    }
+}
 
-        Ok(())
+impl Handler<AssignTableWaiter> for PgActor {
+    type Result = Result<Table, ApiError>;
+
+    fn handle(&mut self, msg: AssignTableWaiter, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::{shifts, tables};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            if let Some(w_id) = msg.waiter_id {
+                // the lock makes a concurrent clock-out wait and release the table afterwards
+                shifts::table
+                    .filter(shifts::waiter_id.eq(w_id))
+                    .filter(shifts::ended_at.is_null())
+                    .for_update()
+                    .first::<Shift>(trx_conn)
+                    .optional()?
+                    .ok_or_else(|| {
+                        ApiError::conflict(
+                            "waiter_not_on_shift",
+                            format!("Waiter {w_id} is not on shift"),
+                        )
+                    })?;
+            }
+
+            let before = tables::table
+                .find(msg.table_id)
+                .for_update()
+                .get_result::<Table>(trx_conn)
+                .optional()?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Table {} is not found", msg.table_id))
+                })?;
+
+            let table = diesel::update(tables::table.find(msg.table_id))
+                .set(tables::waiter_id.eq(msg.waiter_id))
+                .get_result::<Table>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "table",
+                table.id,
+                "assign_waiter",
+                to_json(&before)?,
+                to_json(&table)?,
+            )?;
+
+            if msg.waiter_id.is_some() {
+                diesel::update(
+                    orders::table
+                        .filter(orders::table_id.eq(msg.table_id))
+                        .filter(orders::is_paid.eq(false)),
+                )
+                .set(orders::waiter_id.eq(msg.waiter_id))
+                .execute(trx_conn)?;
+            }
+
+            Ok(table)
+        })
+    }
+}
+
+impl Handler<LinkWaiterWorker> for PgActor {
+    type Result = Result<Waiter, ApiError>;
+
+    fn handle(&mut self, msg: LinkWaiterWorker, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::{shifts, waiters};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            if let Some(wr_id) = msg.worker_id {
+                find_worker(trx_conn, wr_id)?;
+            }
+
+            let before = find_waiter(trx_conn, msg.waiter_id)?;
+
+            let waiter = diesel::update(waiters::table.find(msg.waiter_id))
+                .set(waiters::worker_id.eq(msg.worker_id))
+                .get_result::<Waiter>(trx_conn)
+                .map_err(|err| match err {
+                    Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                        ApiError::conflict(
+                            "worker_linked",
+                            format!(
+                                "Worker {} is linked to another waiter",
+                                msg.worker_id.unwrap_or_default()
+                            ),
+                        )
+                    }
+                    err => err.into(),
+                })?;
+
+            // the open shift of the waiter is the shift of the worker as well
+            diesel::update(
+                shifts::table
+                    .filter(shifts::waiter_id.eq(waiter.id))
+                    .filter(shifts::ended_at.is_null()),
+            )
+            .set(shifts::worker_id.eq(waiter.worker_id))
+            .execute(trx_conn)
+            .map_err(|err| match err {
+                Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => ApiError::conflict(
+                    "already_on_shift",
+                    format!(
+                        "Worker {} is already on shift",
+                        msg.worker_id.unwrap_or_default()
+                    ),
+                ),
+                err => err.into(),
+            })?;
+
+            audit(
+                trx_conn,
+                "waiter",
+                waiter.id,
+                "link_worker",
+                to_json(&before)?,
+                to_json(&waiter)?,
+            )?;
+
+            Ok(waiter)
+        })
+    }
+}
+
+impl Handler<FetchTipPayoutInputs> for PgActor {
+    type Result = Result<Vec<TipPayoutInputs>, ApiError>;
+
+    fn handle(&mut self, msg: FetchTipPayoutInputs, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::{shifts, tips, waiters, worker, worker_role};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        let paid = tips::table
+            .filter(tips::created_at.ge(msg.from))
+            .filter(tips::created_at.lt(msg.to))
+            .select((tips::waiter_id, tips::amount, tips::created_at))
+            .load::<(Option<i64>, i32, DateTime<Utc>)>(&mut conn)?;
+
+        // any worker clocks in, kitchen staff has no waiter record
+        let pool_shifts = shifts::table
+            .inner_join(worker::table.inner_join(worker_role::table))
+            .filter(worker::deleted_at.is_null())
+            .filter(worker_role::title.eq_any(&msg.pool_roles))
+            .filter(shifts::started_at.lt(msg.to))
+            .filter(shifts::ended_at.is_null().or(shifts::ended_at.ge(msg.from)))
+            .order(worker::id)
+            .select((
+                worker::id,
+                worker::first_name,
+                worker::last_name,
+                worker_role::title,
+                shifts::started_at,
+                shifts::ended_at,
+            ))
+            .load::<(
+                i32,
+                String,
+                String,
+                String,
+                DateTime<Utc>,
+                Option<DateTime<Utc>>,
+            )>(&mut conn)?;
+
+        // tips paid while the same staff was on shift share one pool
+        let mut crews = BTreeMap::<Vec<i32>, (BTreeMap<i64, i64>, i64)>::new();
+
+        for (w_id, amount, paid_at) in paid {
+            let mut on_shift = pool_shifts
+                .iter()
+                .filter(|(.., started_at, ended_at)| {
+                    *started_at <= paid_at && ended_at.is_none_or(|ended_at| paid_at <= ended_at)
+                })
+                .map(|(worker_id, ..)| *worker_id)
+                .collect::<Vec<_>>();
+            on_shift.dedup();
+
+            let (by_waiter, unattributed) = crews.entry(on_shift).or_default();
+
+            match w_id {
+                Some(w_id) => *by_waiter.entry(w_id).or_default() += amount as i64,
+                None => *unattributed += amount as i64,
+            }
+        }
+
+        let waiter_ids = crews
+            .values()
+            .flat_map(|(by_waiter, _)| by_waiter.keys())
+            .collect::<Vec<_>>();
+        let tipped_waiters = waiters::table
+            .filter(waiters::id.eq_any(waiter_ids))
+            .load::<Waiter>(&mut conn)?
+            .into_iter()
+            .map(|waiter| (waiter.id, waiter))
+            .collect::<BTreeMap<_, _>>();
+
+        let staff = pool_shifts
+            .into_iter()
+            .map(|(worker_id, first_name, last_name, role, ..)| {
+                let member = StaffMember {
+                    worker_id,
+                    name: format!("{first_name} {last_name}"),
+                    role,
+                };
+
+                (worker_id, member)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(crews
+            .into_iter()
+            .map(|(on_shift, (by_waiter, unattributed))| TipPayoutInputs {
+                waiter_tips: by_waiter
+                    .into_iter()
+                    .filter_map(|(w_id, amount)| {
+                        Some(WaiterTips {
+                            waiter: tipped_waiters.get(&w_id)?.clone(),
+                            amount,
+                        })
+                    })
+                    .collect(),
+                unattributed,
+                pool_staff: on_shift
+                    .iter()
+                    .map(|worker_id| staff[worker_id].clone())
+                    .collect(),
+            })
+            .collect())
     }
 }
 
 impl Handler<CreateDish> for PgActor {
-    type Result = QueryResult<Dish>;
+    type Result = Result<Dish, ApiError>;
 
     fn handle(&mut self, msg: CreateDish, _ctx: &mut Self::Context) -> Self::Result {
         use crate::schema::dish_to_product::dsl::dish_to_product;
-        use crate::schema::dishes::{
-            approx_cook_time_s, dsl::dishes, id, name, portion_weight_g, price, type_,
-        };
+        use crate::schema::dishes::dsl::dishes;
         use crate::services::insertable::DishProductMapping;
         use crate::services::insertable::NewDish;
 
         let mut conn = establish_connection(&self.0)?;
 
         conn.build_transaction().run(move |trx_conn| {
+            let dish_name = msg.dish_name.clone();
             let new_dish = diesel::insert_into(dishes)
                 .values(NewDish {
                     name: msg.dish_name,
@@ -106,11 +796,14 @@ impl Handler<CreateDish> for PgActor {
                     approx_cook_time_s: msg.approx_cook_time_s,
                     portion_weight_g: msg.portion_weight_g,
                     price: msg.price,
+                    description: msg.description,
+                    image_url: msg.image_url,
+                    dietary_labels: msg.dietary_labels,
                 })
-                .returning((id, name, type_, portion_weight_g, price, approx_cook_time_s))
-                .get_result::<Dish>(trx_conn)?;
+                .get_result::<Dish>(trx_conn)
+                .map_err(|err| name_taken(err, &dish_name))?;
 
-            for ing in msg.ingredients {
+            for ing in &msg.ingredients {
                 diesel::insert_into(dish_to_product)
                     .values(DishProductMapping {
                         dish_id: new_dish.id,
@@ -119,189 +812,1065 @@ impl Handler<CreateDish> for PgActor {
                     })
                     .execute(trx_conn)?;
             }
-
-            Ok(new_dish)
+
+            let mut after = to_json(&new_dish)?;
+            after["ingredients"] = dish_ingredients(trx_conn, new_dish.id)?;
+
+            audit(trx_conn, "dish", new_dish.id, "create", Value::Null, after)?;
+
+            Ok(new_dish)
+        })
+    }
+}
+
+impl Handler<FetchDish> for PgActor {
+    type Result = Result<Dish, ApiError>;
+
+    fn handle(&mut self, msg: FetchDish, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::dsl::dishes;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        dishes.find(msg.0).first(&mut conn).map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchDishes> for PgActor {
+    type Result = Result<Vec<Dish>, ApiError>;
+
+    fn handle(&mut self, msg: FetchDishes, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::{archived_at, dsl::dishes};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        dishes
+            .filter(archived_at.is_null())
+            .get_results::<Dish>(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchSpecificDishes> for PgActor {
+    type Result = Result<Vec<Dish>, ApiError>;
+
+    fn handle(&mut self, msg: FetchSpecificDishes, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::{archived_at, dsl::dishes, id};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        dishes
+            .filter(id.eq_any(msg.0))
+            .filter(archived_at.is_null())
+            .get_results(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchDishIngredients> for PgActor {
+    type Result = Result<Vec<(String, i32)>, ApiError>;
+
+    fn handle(&mut self, msg: FetchDishIngredients, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product, weight_g};
+        use crate::schema::products::{dsl::products, name};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        dish_to_product
+            .inner_join(products)
+            .select((name, weight_g))
+            .filter(dish_id.eq(msg.0))
+            .get_results(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchDishAllergens> for PgActor {
+    type Result = Result<Vec<Allergen>, ApiError>;
+
+    fn handle(&mut self, msg: FetchDishAllergens, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product};
+        use crate::schema::products::{allergens, dsl::products};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        let mut dish_allergens: Vec<Allergen> = dish_to_product
+            .inner_join(products)
+            .select(allergens)
+            .filter(dish_id.eq(msg.0))
+            .get_results::<Vec<Allergen>>(&mut conn)?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        dish_allergens.sort();
+        dish_allergens.dedup();
+
+        Ok(dish_allergens)
+    }
+}
+
+impl Handler<FetchDishesWithProduct> for PgActor {
+    type Result = Result<Vec<Dish>, ApiError>;
+
+    fn handle(&mut self, msg: FetchDishesWithProduct, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_product::{dsl::dish_to_product, product_id};
+        use crate::schema::dishes::{all_columns as dish_columns, archived_at, dsl::dishes};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        dish_to_product
+            .inner_join(dishes)
+            .select(dish_columns)
+            .filter(product_id.eq(msg.0))
+            .filter(archived_at.is_null())
+            .distinct()
+            .get_results::<Dish>(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchProducts> for PgActor {
+    type Result = Result<Vec<Product>, ApiError>;
+
+    fn handle(&mut self, _msg: FetchProducts, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::products::dsl::products;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        products
+            .get_results::<Product>(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+fn lock_product(conn: &mut PgConnection, p_id: i64) -> Result<Product, ApiError> {
+    use crate::schema::products::dsl::products;
+
+    products
+        .find(p_id)
+        .for_update()
+        .get_result::<Product>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound(format!("Product {p_id} is not found")))
+}
+
+impl Handler<SetProductAllergens> for PgActor {
+    type Result = Result<Product, ApiError>;
+
+    fn handle(&mut self, msg: SetProductAllergens, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::products::{allergens, dsl::products};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            let before = lock_product(trx_conn, msg.product_id)?;
+
+            let product = diesel::update(products.find(msg.product_id))
+                .set(allergens.eq(msg.allergens))
+                .get_result::<Product>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "product",
+                product.id,
+                "set_allergens",
+                to_json(&before)?,
+                to_json(&product)?,
+            )?;
+
+            Ok(product)
+        })
+    }
+}
+
+impl Handler<SetProductCost> for PgActor {
+    type Result = Result<Product, ApiError>;
+
+    fn handle(&mut self, msg: SetProductCost, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::product_costs::dsl::product_costs;
+        use crate::schema::products::{cost_per_kg, dsl::products};
+        use crate::services::insertable::NewProductCost;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            let before = lock_product(trx_conn, msg.product_id)?;
+
+            let product = diesel::update(products.find(msg.product_id))
+                .set(cost_per_kg.eq(msg.cost_per_kg))
+                .get_result::<Product>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "product",
+                product.id,
+                "set_cost",
+                to_json(&before)?,
+                to_json(&product)?,
+            )?;
+
+            diesel::insert_into(product_costs)
+                .values(NewProductCost {
+                    product_id: msg.product_id,
+                    cost_per_kg: msg.cost_per_kg,
+                    valid_from: Utc::now(),
+                })
+                .execute(trx_conn)?;
+
+            Ok(product)
+        })
+    }
+}
+
+impl Handler<FetchProductCostHistory> for PgActor {
+    type Result = Result<Vec<ProductCost>, ApiError>;
+
+    fn handle(&mut self, msg: FetchProductCostHistory, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::product_costs::{dsl::product_costs, id, product_id, valid_from};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        product_costs
+            .filter(product_id.eq(msg.0))
+            .order((valid_from.desc(), id.desc()))
+            .get_results::<ProductCost>(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
+impl Handler<FetchDishFoodCosts> for PgActor {
+    type Result = Result<Vec<(Dish, i32)>, ApiError>;
+
+    fn handle(&mut self, _msg: FetchDishFoodCosts, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product, weight_g};
+        use crate::schema::dishes::{archived_at, dsl::dishes, id as dish_pk};
+        use crate::schema::products::{cost_per_kg, dsl::products};
+        use std::collections::HashMap;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            let active_dishes = dishes
+                .filter(archived_at.is_null())
+                .order(dish_pk)
+                .get_results::<Dish>(trx_conn)?;
+
+            let usage = dish_to_product
+                .inner_join(products)
+                .select((dish_id, weight_g, cost_per_kg))
+                .filter(dish_id.eq_any(active_dishes.iter().map(|dish| dish.id)))
+                .get_results::<(i64, i32, i32)>(trx_conn)?;
+
+            // weight is in grams and cost is per kilogram
+            let mut milli_costs: HashMap<i64, i64> = HashMap::new();
+
+            for (dish, weight, cost) in usage {
+                *milli_costs.entry(dish).or_insert(0) += weight as i64 * cost as i64;
+            }
+
+            Ok(active_dishes
+                .into_iter()
+                .map(|dish| {
+                    let milli_cost = milli_costs.get(&dish.id).copied().unwrap_or(0);
+                    let food_cost = ((milli_cost + 500) / 1000) as i32;
+
+                    (dish, food_cost)
+                })
+                .collect())
+        })
+    }
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = dishes)]
+struct DishChangeSet {
+    pub name: Option<String>,
+    pub type_: Option<String>,
+    pub price: Option<i32>,
+    pub approx_cook_time_s: Option<i32>,
+    pub portion_weight_g: Option<i32>,
+    /// `Some(None)` clears the column
+    pub description: Option<Option<String>>,
+    pub image_url: Option<Option<String>>,
+    pub dietary_labels: Option<Vec<DietaryLabel>>,
+}
+
+/// Names of active dishes are unique, the validation checks it before the change,
+/// the index catches the dishes named so concurrently
+fn name_taken(err: Error, dish_name: &str) -> ApiError {
+    match err {
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => ApiError::conflict(
+            "dish_name_taken",
+            format!("Dish named '{dish_name}' already exists"),
+        ),
+        err => err.into(),
+    }
+}
+
+fn ensure_dish_not_archived(conn: &mut PgConnection, dish_id: i64) -> Result<(), ApiError> {
+    use crate::schema::dishes::{archived_at, dsl::dishes};
+
+    let is_archived = dishes
+        .find(dish_id)
+        .select(archived_at.is_not_null())
+        .first::<bool>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound(format!("Dish {dish_id} is not found")))?;
+
+    if is_archived {
+        return Err(ApiError::conflict(
+            "dish_archived",
+            format!("Dish {dish_id} is archived"),
+        ));
+    }
+
+    Ok(())
+}
+
+impl Handler<UpdateDish> for PgActor {
+    type Result = Result<Dish, ApiError>;
+
+    fn handle(&mut self, msg: UpdateDish, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::dsl::dishes;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            ensure_dish_not_archived(trx_conn, msg.dish_id)?;
+
+            let before = dishes.find(msg.dish_id).get_result::<Dish>(trx_conn)?;
+            let dish_name = msg.dish_name.clone().unwrap_or_default();
+
+            let dish = diesel::update(dishes.find(msg.dish_id))
+                .set(DishChangeSet {
+                    name: msg.dish_name,
+                    type_: msg.dish_type.map(|dish_type| dish_type.to_string()),
+                    price: msg.price,
+                    approx_cook_time_s: msg.approx_cook_time_s,
+                    portion_weight_g: msg.portion_weight_g,
+                    description: msg.description,
+                    image_url: msg.image_url,
+                    dietary_labels: msg.dietary_labels,
+                })
+                .get_result::<Dish>(trx_conn)
+                .map_err(|err| name_taken(err, &dish_name))?;
+
+            audit(
+                trx_conn,
+                "dish",
+                dish.id,
+                "update",
+                to_json(&before)?,
+                to_json(&dish)?,
+            )?;
+
+            Ok(dish)
+        })
+    }
+}
+
+/// Products of the dish with their weights, as the audit log keeps them
+fn dish_ingredients(conn: &mut PgConnection, d_id: i64) -> Result<Value, ApiError> {
+    use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product, product_id, weight_g};
+
+    let ingredients = dish_to_product
+        .filter(dish_id.eq(d_id))
+        .order(product_id)
+        .select((product_id, weight_g))
+        .load::<(i64, i32)>(conn)?;
+
+    Ok(ingredients
+        .into_iter()
+        .map(|(p_id, weight)| json!({ "product_id": p_id, "weight_g": weight }))
+        .collect())
+}
+
+impl Handler<ReplaceDishIngredients> for PgActor {
+    type Result = Result<Vec<(String, i32)>, ApiError>;
+
+    fn handle(&mut self, msg: ReplaceDishIngredients, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product, weight_g};
+        use crate::schema::products::{dsl::products, name};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            ensure_dish_not_archived(trx_conn, msg.dish_id)?;
+
+            let before = dish_ingredients(trx_conn, msg.dish_id)?;
+
+            diesel::delete(dish_to_product.filter(dish_id.eq(msg.dish_id))).execute(trx_conn)?;
+
+            diesel::insert_into(dish_to_product)
+                .values(
+                    msg.ingredients
+                        .iter()
+                        .map(|ing| DishProductMapping {
+                            dish_id: msg.dish_id,
+                            product_id: ing.id,
+                            weight_g: ing.used_g,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(trx_conn)?;
+
+            let after = dish_ingredients(trx_conn, msg.dish_id)?;
+            audit(
+                trx_conn,
+                "dish",
+                msg.dish_id,
+                "replace_ingredients",
+                json!({ "ingredients": before }),
+                json!({ "ingredients": after }),
+            )?;
+
+            dish_to_product
+                .inner_join(products)
+                .select((name, weight_g))
+                .filter(dish_id.eq(msg.dish_id))
+                .get_results(trx_conn)
+                .map_err(ApiError::from)
+        })
+    }
+}
+
+impl Handler<ArchiveDish> for PgActor {
+    type Result = Result<Dish, ApiError>;
+
+    fn handle(&mut self, msg: ArchiveDish, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::{archived_at, dsl::dishes};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            ensure_dish_not_archived(trx_conn, msg.0)?;
+
+            let before = dishes.find(msg.0).get_result::<Dish>(trx_conn)?;
+
+            let dish = diesel::update(dishes.find(msg.0))
+                .set(archived_at.eq(Utc::now()))
+                .get_result::<Dish>(trx_conn)?;
+
+            audit(
+                trx_conn,
+                "dish",
+                dish.id,
+                "archive",
+                to_json(&before)?,
+                to_json(&dish)?,
+            )?;
+
+            Ok(dish)
+        })
+    }
+}
+
+fn fetch_modifier_groups(
+    conn: &mut PgConnection,
+    d_id: i64,
+) -> Result<Vec<ModifierGroupInfo>, ApiError> {
+    use crate::schema::modifier_groups::{dish_id, dsl::modifier_groups, id as group_pk};
+    use crate::schema::modifiers::{dsl::modifiers, group_id, id as modifier_pk};
+
+    let groups = modifier_groups
+        .filter(dish_id.eq(d_id))
+        .order(group_pk)
+        .get_results::<ModifierGroup>(conn)?;
+
+    let mut group_modifiers = modifiers
+        .filter(group_id.eq_any(groups.iter().map(|group| group.id)))
+        .order(modifier_pk)
+        .get_results::<Modifier>(conn)?;
+
+    Ok(groups
+        .into_iter()
+        .map(|group| {
+            let (of_group, rest) = group_modifiers
+                .drain(..)
+                .partition(|modifier| modifier.group_id == group.id);
+            group_modifiers = rest;
+
+            ModifierGroupInfo {
+                group,
+                modifiers: of_group,
+            }
+        })
+        .collect())
+}
+
+impl Handler<FetchDishModifiers> for PgActor {
+    type Result = Result<Vec<ModifierGroupInfo>, ApiError>;
+
+    fn handle(&mut self, msg: FetchDishModifiers, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = establish_connection(&self.0)?;
+
+        fetch_modifier_groups(&mut conn, msg.0)
+    }
+}
+
+impl Handler<CreateModifierGroup> for PgActor {
+    type Result = Result<Vec<ModifierGroupInfo>, ApiError>;
+
+    fn handle(&mut self, msg: CreateModifierGroup, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::modifier_groups::{dsl::modifier_groups, id as group_pk};
+        use crate::schema::modifier_products::dsl::modifier_products;
+        use crate::schema::modifiers::{dsl::modifiers, id as modifier_pk};
+        use crate::services::insertable::{NewModifier, NewModifierGroup, NewModifierProduct};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            ensure_dish_not_archived(trx_conn, msg.dish_id)?;
+
+            let new_group_id = diesel::insert_into(modifier_groups)
+                .values(NewModifierGroup {
+                    dish_id: msg.dish_id,
+                    name: msg.name,
+                    is_multiple: msg.is_multiple,
+                    is_required: msg.is_required,
+                })
+                .returning(group_pk)
+                .get_result::<i64>(trx_conn)?;
+
+            for spec in msg.modifiers {
+                let new_modifier_id = diesel::insert_into(modifiers)
+                    .values(NewModifier {
+                        group_id: new_group_id,
+                        name: spec.name,
+                        price_delta: spec.price_delta,
+                    })
+                    .returning(modifier_pk)
+                    .get_result::<i64>(trx_conn)?;
+
+                diesel::insert_into(modifier_products)
+                    .values(
+                        spec.ingredients
+                            .iter()
+                            .map(|delta| NewModifierProduct {
+                                modifier_id: new_modifier_id,
+                                product_id: delta.id,
+                                weight_delta_g: delta.delta_g,
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .execute(trx_conn)?;
+            }
+
+            let groups = fetch_modifier_groups(trx_conn, msg.dish_id)?;
+
+            if let Some(group) = groups.iter().find(|info| info.group.id == new_group_id) {
+                audit(
+                    trx_conn,
+                    "modifier_group",
+                    new_group_id,
+                    "create",
+                    Value::Null,
+                    to_json(group)?,
+                )?;
+            }
+
+            Ok(groups)
+        })
+    }
+}
+
+impl Handler<FindUnknownProducts> for PgActor {
+    type Result = Result<Vec<i64>, ApiError>;
+
+    fn handle(&mut self, msg: FindUnknownProducts, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::products::{dsl::products, id};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        let known = products
+            .select(id)
+            .filter(id.eq_any(&msg.0))
+            .get_results::<i64>(&mut conn)?;
+
+        Ok(msg
+            .0
+            .into_iter()
+            .filter(|p_id| !known.contains(p_id))
+            .collect())
+    }
+}
+
+impl Handler<IsDishNameTaken> for PgActor {
+    type Result = Result<bool, ApiError>;
+
+    fn handle(&mut self, msg: IsDishNameTaken, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dishes::{archived_at, dsl::dishes, id, name};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        let same_name = dishes
+            .select(id)
+            .filter(name.eq(msg.name))
+            .filter(archived_at.is_null())
+            .get_results::<i64>(&mut conn)?;
+
+        Ok(same_name.iter().any(|d_id| Some(*d_id) != msg.except_id))
+    }
+}
+
+impl Handler<CreateOrder> for PgActor {
+    type Result = Result<i64, ApiError>;
+
+    fn handle(&mut self, msg: CreateOrder, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction()
+            .run(|trx_conn| insert_order(trx_conn, msg.0))
+    }
+}
+
+/// The order is served by the waiter of its table
+fn insert_order(conn: &mut PgConnection, tbl_id: i64) -> Result<i64, ApiError> {
+    use crate::schema::orders::{dsl::orders, id};
+    use crate::schema::tables::{dsl::tables, waiter_id};
+    use crate::services::insertable::NewOrder;
+
+    // a missing table is reported by the foreign key of the order
+    let table_waiter_id = tables
+        .find(tbl_id)
+        .select(waiter_id)
+        .get_result::<Option<i64>>(conn)
+        .optional()?
+        .flatten();
+
+    let ord_id = diesel::insert_into(orders)
+        .values(NewOrder {
+            table_id: tbl_id,
+            total_cost: 0,
+            created_at: Utc::now(),
+            waiter_id: table_waiter_id,
+        })
+        .returning(id)
+        .get_result::<i64>(conn)?;
+
+    let after = order_snapshot(conn, ord_id)?;
+    audit(conn, "order", ord_id, "create", Value::Null, after)?;
+
+    Ok(ord_id)
+}
+
+impl Handler<OpenTableOrder> for PgActor {
+    type Result = Result<i64, ApiError>;
+
+    fn handle(&mut self, msg: OpenTableOrder, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::orders::{created_at, dsl::orders, id, is_confirmed, table_id};
+        use crate::schema::tables::dsl::tables;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            // the lock keeps guests of one table from opening two orders at once
+            tables
+                .find(msg.0)
+                .for_update()
+                .get_result::<Table>(trx_conn)
+                .optional()?
+                .ok_or_else(|| ApiError::NotFound(format!("Table {} is not found", msg.0)))?;
+
+            let open_order = orders
+                .filter(table_id.eq(msg.0))
+                .filter(is_confirmed.eq(false))
+                .order((created_at.desc(), id.desc()))
+                .select(id)
+                .first::<i64>(trx_conn)
+                .optional()?;
+
+            match open_order {
+                Some(ord_id) => Ok(ord_id),
+                None => insert_order(trx_conn, msg.0),
+            }
         })
     }
 }
 
-impl Handler<FetchDish> for PgActor {
-    type Result = QueryResult<Dish>;
+/// Identifies lines that may be merged into one by increasing the count:
+/// the same dish with the same set of modifiers and the same note
+fn order_line_key(modifier_ids: &[i64], note: Option<&str>) -> String {
+    if modifier_ids.is_empty() && note.is_none() {
+        return String::new();
+    }
 
-    fn handle(&mut self, msg: FetchDish, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dishes::dsl::dishes;
+    let mut modifier_ids = modifier_ids.to_vec();
+    modifier_ids.sort();
+    modifier_ids.dedup();
 
-        let mut conn = establish_connection(&self.0)?;
+    let modifier_ids: Vec<String> = modifier_ids.iter().map(|m_id| m_id.to_string()).collect();
 
-        dishes.find(msg.0).first(&mut conn)
-    }
+    format!("{}|{}", modifier_ids.join(","), note.unwrap_or_default())
 }
 
-impl Handler<FetchDishes> for PgActor {
-    type Result = QueryResult<Vec<Dish>>;
+fn fetch_order_lines(conn: &mut PgConnection, ord_id: i64) -> Result<Vec<DishWithCount>, ApiError> {
+    use crate::schema::dish_to_order::{count, dsl::dish_to_order, id, note, order_id};
+    use crate::schema::dishes::{all_columns as dish_columns, dsl::dishes};
+    use crate::schema::modifiers::{all_columns as modifier_columns, dsl::modifiers};
+    use crate::schema::order_line_modifiers::{dsl::order_line_modifiers, line_id};
+
+    let lines = dish_to_order
+        .inner_join(dishes)
+        .filter(order_id.eq(ord_id))
+        .select((id, dish_columns, count, note))
+        .order(id)
+        .get_results::<(i64, Dish, i32, Option<String>)>(conn)?;
+
+    let line_modifiers = order_line_modifiers
+        .inner_join(modifiers)
+        .filter(line_id.eq_any(lines.iter().map(|line| line.0)))
+        .select((line_id, modifier_columns))
+        .get_results::<(i64, Modifier)>(conn)?;
+
+    Ok(lines
+        .into_iter()
+        .map(|(l_id, dish, dish_count, line_note)| {
+            let modifiers_of_line: Vec<Modifier> = line_modifiers
+                .iter()
+                .filter(|(mod_line_id, _)| *mod_line_id == l_id)
+                .map(|(_, modifier)| modifier.clone())
+                .collect();
+
+            DishWithCount {
+                line_id: l_id,
+                unit_price: dish.price
+                    + modifiers_of_line
+                        .iter()
+                        .map(|modifier| modifier.price_delta)
+                        .sum::<i32>(),
+                dish,
+                count: dish_count,
+                modifiers: modifiers_of_line,
+                note: line_note,
+            }
+        })
+        .collect())
+}
 
-    fn handle(&mut self, msg: FetchDishes, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dishes::dsl::dishes;
+/// Keeps `orders.total_cost` in line with the ordered dishes and their modifiers
+fn recalculate_total_cost(conn: &mut PgConnection, ord_id: i64) -> Result<i32, ApiError> {
+    use crate::schema::orders::{dsl::orders, total_cost};
 
-        let mut conn = establish_connection(&self.0)?;
+    let order_cost = fetch_order_lines(conn, ord_id)?
+        .iter()
+        .map(|line| line.unit_price * line.count)
+        .sum();
 
-        dishes.get_results::<Dish>(&mut conn)
-    }
+    diesel::update(orders.find(ord_id))
+        .set(total_cost.eq(order_cost))
+        .execute(conn)?;
+
+    Ok(order_cost)
 }
 
-impl Handler<FetchSpecificDishes> for PgActor {
-    type Result = QueryResult<Vec<Dish>>;
+/// Finds the line the request refers to. A dish reference points to its latest line
+fn find_order_line(
+    conn: &mut PgConnection,
+    ord_id: i64,
+    line: &OrderLineRef,
+) -> Result<(i64, i32), ApiError> {
+    use crate::schema::dish_to_order::{count, dish_id, dsl::dish_to_order, id, order_id};
 
-    fn handle(&mut self, msg: FetchSpecificDishes, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dishes::{dsl::dishes, id};
+    let found = match line {
+        OrderLineRef::Dish(d_id) => dish_to_order
+            .select((id, count))
+            .filter(order_id.eq(ord_id))
+            .filter(dish_id.eq(d_id))
+            .order(id.desc())
+            .first::<(i64, i32)>(conn),
+        OrderLineRef::Line(l_id) => dish_to_order
+            .select((id, count))
+            .filter(order_id.eq(ord_id))
+            .filter(id.eq(l_id))
+            .first::<(i64, i32)>(conn),
+    };
+
+    found.optional()?.ok_or_else(|| match line {
+        OrderLineRef::Dish(d_id) => {
+            ApiError::NotFound(format!("Dish {d_id} is not in order {ord_id}"))
+        }
+        OrderLineRef::Line(l_id) => {
+            ApiError::NotFound(format!("Line {l_id} is not in order {ord_id}"))
+        }
+    })
+}
 
-        let mut conn = establish_connection(&self.0)?;
+/// Checks that chosen modifiers belong to the dish and follow the rules of their groups
+fn check_modifiers(
+    conn: &mut PgConnection,
+    d_id: i64,
+    modifier_ids: &[i64],
+) -> Result<(), ApiError> {
+    use crate::schema::modifier_groups::{
+        dish_id, dsl::modifier_groups, id as group_pk, is_multiple, is_required, name,
+    };
+    use crate::schema::modifiers::{dsl::modifiers, group_id, id as modifier_pk};
+
+    let groups = modifier_groups
+        .filter(dish_id.eq(d_id))
+        .select((group_pk, name, is_multiple, is_required))
+        .get_results::<(i64, String, bool, bool)>(conn)?;
+
+    let chosen = modifiers
+        .filter(modifier_pk.eq_any(modifier_ids))
+        .select((modifier_pk, group_id))
+        .get_results::<(i64, i64)>(conn)?;
+
+    for m_id in modifier_ids {
+        match chosen.iter().find(|(modifier, _)| modifier == m_id) {
+            Some((_, g_id)) if groups.iter().any(|group| group.0 == *g_id) => {}
+            _ => {
+                return Err(ApiError::invalid(
+                    "modifiers",
+                    format!("modifier {m_id} is not available for the dish"),
+                ))
+            }
+        }
+    }
+
+    for (g_id, group_name, multiple, required) in groups {
+        let chosen_in_group = chosen.iter().filter(|(_, group)| *group == g_id).count();
+
+        if required && chosen_in_group == 0 {
+            return Err(ApiError::invalid(
+                "modifiers",
+                format!("a modifier from '{group_name}' must be chosen"),
+            ));
+        }
 
-        dishes.filter(id.eq_any(msg.0)).get_results(&mut conn)
+        if !multiple && chosen_in_group > 1 {
+            return Err(ApiError::invalid(
+                "modifiers",
+                format!("only one modifier from '{group_name}' may be chosen"),
+            ));
+        }
     }
+
+    Ok(())
 }
 
-impl Handler<FetchDishIngredients> for PgActor {
-    type Result = QueryResult<Vec<(String, i32)>>;
+/// Adds `line_count` to the line with the same dish, modifiers and note, or creates
+/// such line. Relies on the unique (order_id, dish_id, line_key) constraint, so that
+/// concurrent requests never lose an increment
+fn upsert_order_line(
+    conn: &mut PgConnection,
+    ord_id: i64,
+    d_id: i64,
+    modifier_ids: &[i64],
+    line_note: Option<String>,
+    line_count: i32,
+) -> Result<i64, ApiError> {
+    use crate::schema::dish_to_order::{
+        count, dish_id, dsl::dish_to_order, id, line_key, order_id,
+    };
+    use crate::schema::order_line_modifiers::dsl::order_line_modifiers;
+    use crate::services::insertable::{OrderDish, OrderLineModifier};
+
+    let key = order_line_key(modifier_ids, line_note.as_deref());
+
+    let (line_id, new_count) = diesel::insert_into(dish_to_order)
+        .values(OrderDish {
+            dish_id: d_id,
+            order_id: ord_id,
+            count: line_count,
+            note: line_note,
+            line_key: key,
+        })
+        .on_conflict((order_id, dish_id, line_key))
+        .do_update()
+        .set(count.eq(count + line_count))
+        .returning((id, count))
+        .get_result::<(i64, i32)>(conn)?;
+
+    // existing line only had its count increased, its modifiers are already there
+    if new_count != line_count {
+        return Ok(line_id);
+    }
 
-    fn handle(&mut self, msg: FetchDishIngredients, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_product::{dish_id, dsl::dish_to_product, weight_g};
-        use crate::schema::products::{dsl::products, name};
+    let mut modifier_ids = modifier_ids.to_vec();
+    modifier_ids.sort();
+    modifier_ids.dedup();
+
+    diesel::insert_into(order_line_modifiers)
+        .values(
+            modifier_ids
+                .into_iter()
+                .map(|modifier_id| OrderLineModifier {
+                    line_id,
+                    modifier_id,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .execute(conn)?;
 
-        let mut conn = establish_connection(&self.0)?;
+    Ok(line_id)
+}
 
-        dish_to_product
-            .inner_join(products)
-            .select((name, weight_g))
-            .filter(dish_id.eq(msg.0))
-            .get_results(&mut conn)
-    }
+/// Takes a row lock on the order for the rest of the transaction, so that mutations
+/// of the same order are applied one after another
+fn lock_order(conn: &mut PgConnection, ord_id: i64) -> Result<Order, ApiError> {
+    use crate::schema::orders::dsl::orders;
+
+    orders
+        .find(ord_id)
+        .for_update()
+        .get_result::<Order>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {ord_id} is not found")))
 }
 
-impl Handler<CreateOrder> for PgActor {
-    type Result = QueryResult<i64>;
+fn lock_unconfirmed_order(conn: &mut PgConnection, ord_id: i64) -> Result<Order, ApiError> {
+    let order = lock_order(conn, ord_id)?;
 
-    fn handle(&mut self, msg: CreateOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_order::dsl::dish_to_order;
-        use crate::schema::orders::{dsl::orders, id};
-        use crate::services::insertable::{NewOrder, OrderDish};
-        use chrono::Local;
+    if order.is_confirmed {
+        return Err(ApiError::conflict(
+            "order_confirmed",
+            format!("Order {ord_id} is already confirmed"),
+        ));
+    }
 
-        let mut conn = establish_connection(&self.0)?;
+    Ok(order)
+}
 
-        diesel::insert_into(orders)
-            .values(NewOrder {
-                table_id: msg.0,
-                total_cost: 0,
-                created_at: Local::now().naive_local(),
-            })
-            .returning(id)
-            .get_result::<i64>(&mut conn)
+/// Adds `delta` to the count of the matching line, creating or removing the line when needed
+fn change_line_count(
+    conn: &mut PgConnection,
+    ord_id: i64,
+    d_id: i64,
+    modifier_ids: &[i64],
+    line_note: Option<String>,
+    delta: i32,
+) -> Result<(), ApiError> {
+    use crate::schema::dish_to_order::{
+        count, dish_id, dsl::dish_to_order, id, line_key, order_id,
+    };
+
+    if delta > 0 {
+        upsert_order_line(conn, ord_id, d_id, modifier_ids, line_note, delta)?;
+
+        return Ok(());
     }
+
+    // callers hold the order lock, so the line can't change between the read and the write
+    let key = order_line_key(modifier_ids, line_note.as_deref());
+    let (line_id, line_count) = dish_to_order
+        .filter(order_id.eq(ord_id))
+        .filter(dish_id.eq(d_id))
+        .filter(line_key.eq(&key))
+        .select((id, count))
+        .first::<(i64, i32)>(conn)
+        .optional()?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Dish {d_id} with such modifiers is not in the order"
+            ))
+        })?;
+
+    if line_count + delta <= 0 {
+        diesel::delete(dish_to_order.find(line_id)).execute(conn)?;
+    } else {
+        diesel::update(dish_to_order.find(line_id))
+            .set(count.eq(line_count + delta))
+            .execute(conn)?;
+    }
+
+    Ok(())
 }
 
 impl Handler<FetchOrder> for PgActor {
-    type Result = QueryResult<OrderInfo>;
+    type Result = Result<OrderInfo, ApiError>;
 
     fn handle(&mut self, msg: FetchOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_order::{count, dish_id, dsl::dish_to_order, order_id};
-        use crate::schema::dishes::{
-            approx_cook_time_s, dsl::dishes, id as dish_pk, name, portion_weight_g, price, type_,
-        };
-        use crate::schema::orders::{dsl::orders, id as order_pk};
+        use crate::schema::orders::dsl::orders;
 
         let mut conn = establish_connection(&self.0)?;
 
         conn.build_transaction().run(|trx_conn| {
-            let order = orders.find(msg.0).get_result::<Order>(trx_conn)?;
+            let order = orders
+                .find(msg.0)
+                .get_result::<Order>(trx_conn)
+                .optional()?
+                .ok_or_else(|| ApiError::NotFound(format!("Order {} is not found", msg.0)))?;
+            let dishes = fetch_order_lines(trx_conn, order.id)?;
 
-            let dishes_in_order = dish_to_order
-                .filter(order_id.eq(msg.0))
-                .inner_join(dishes)
-                .select((
-                    dish_pk,
-                    name,
-                    type_,
-                    portion_weight_g,
-                    price,
-                    approx_cook_time_s,
-                ))
-                .filter(dish_pk.eq(dish_id))
-                .get_results::<Dish>(trx_conn)?;
+            Ok(OrderInfo { order, dishes })
+        })
+    }
+}
 
-            let mut dish_array = vec![];
+impl Handler<FetchTableOrder> for PgActor {
+    type Result = Result<OrderInfo, ApiError>;
 
-            for dish in dishes_in_order {
-                let dish_count = dish_to_order
-                    .filter(order_id.eq(order.id))
-                    .filter(dish_id.eq(dish.id))
-                    .select(count)
-                    .first::<i32>(trx_conn)?;
+    fn handle(&mut self, msg: FetchTableOrder, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::orders::{created_at, dsl::orders, id, is_paid, table_id};
 
-                dish_array.push(DishWithCount {
-                    dish,
-                    count: dish_count,
-                });
-            }
+        let mut conn = establish_connection(&self.0)?;
 
-            Ok(OrderInfo {
-                order,
-                dishes: dish_array,
-            })
+        conn.build_transaction().run(|trx_conn| {
+            let order = orders
+                .filter(table_id.eq(msg.0))
+                .filter(is_paid.eq(false))
+                .order((created_at.desc(), id.desc()))
+                .first::<Order>(trx_conn)
+                .optional()?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Table {} has no unpaid order", msg.0))
+                })?;
+            let dishes = fetch_order_lines(trx_conn, order.id)?;
+
+            Ok(OrderInfo { order, dishes })
         })
     }
 }
 
+impl Handler<FetchTable> for PgActor {
+    type Result = Result<Table, ApiError>;
+
+    fn handle(&mut self, msg: FetchTable, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::tables::dsl::tables;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        tables
+            .find(msg.0)
+            .get_result::<Table>(&mut conn)
+            .optional()?
+            .ok_or_else(|| ApiError::NotFound(format!("Table {} is not found", msg.0)))
+    }
+}
+
+impl Handler<FetchTables> for PgActor {
+    type Result = Result<Vec<Table>, ApiError>;
+
+    fn handle(&mut self, _msg: FetchTables, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::tables::{dsl::tables, id};
+
+        let mut conn = establish_connection(&self.0)?;
+
+        tables
+            .order(id)
+            .load::<Table>(&mut conn)
+            .map_err(ApiError::from)
+    }
+}
+
 impl Handler<FetchOrders> for PgActor {
-    type Result = QueryResult<Vec<OrderInfo>>;
+    type Result = Result<Vec<OrderInfo>, ApiError>;
 
     fn handle(&mut self, msg: FetchOrders, _ctx: &mut Self::Context) -> Self::Result {
         use super::db_models::Order;
-        use crate::schema::dish_to_order::{count, dish_id, dsl::dish_to_order, order_id};
-        use crate::schema::dishes::{
-            approx_cook_time_s, dsl::dishes, id as dish_pk, name, portion_weight_g, price, type_,
-        };
-        use crate::schema::orders::{
-            confirmed_at, cooked_at, created_at, dsl::orders, id as order_pk, is_confirmed,
-            is_cooked, is_paid, table_id, total_cost,
-        };
+        use crate::schema::orders::{dsl::orders, id as order_pk};
 
         let mut conn = establish_connection(&self.0)?;
 
         conn.build_transaction().run(|trx_conn| {
-            let all_orders = orders.get_results::<Order>(trx_conn)?;
+            let all_orders = orders.order(order_pk).get_results::<Order>(trx_conn)?;
 
             let mut order_infos = vec![];
 
             for ord in all_orders {
-                let mut dishes_of_order = dish_to_order
-                    .inner_join(dishes)
-                    .filter(order_id.eq(ord.id))
-                    .select((
-                        dish_pk,
-                        name,
-                        type_,
-                        portion_weight_g,
-                        price,
-                        approx_cook_time_s,
-                        count,
-                    ))
-                    .get_results::<(i64, String, DishType, i32, i32, i32, i32)>(trx_conn)?;
-
-                let dishes_of_order = dishes_of_order
-                    .iter_mut()
-                    .map(|data| DishWithCount {
-                        dish: Dish {
-                            id: data.0,
-                            name: data.1.clone(),
-                            type_: data.2.clone(),
-                            portion_weight_g: data.3,
-                            price: data.4,
-                            approx_cook_time_s: data.5,
-                        },
-                        count: data.6,
-                    })
-                    .collect();
+                let dishes_of_order = fetch_order_lines(trx_conn, ord.id)?;
 
                 order_infos.push(OrderInfo {
                     order: ord,
@@ -315,44 +1884,31 @@ impl Handler<FetchOrders> for PgActor {
 }
 
 impl Handler<AddDishToOrder> for PgActor {
-    type Result = QueryResult<i64>;
+    type Result = Result<i64, ApiError>;
 
     fn handle(&mut self, msg: AddDishToOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_order::{count, dish_id, dsl::dish_to_order, id, order_id};
-        use crate::schema::orders::{dsl::orders, is_confirmed};
-        use crate::services::insertable::OrderDish;
-
         let mut conn = establish_connection(&self.0)?;
 
-        if orders
-            .find(msg.order_id)
-            .select(is_confirmed)
-            .first::<bool>(&mut conn)?
-        {
-            return Err(get_db_err("The order is already confirmed"));
-        };
+        conn.build_transaction().run(|trx_conn| {
+            lock_unconfirmed_order(trx_conn, msg.order_id)?;
+            ensure_dish_not_archived(trx_conn, msg.dish_id)?;
+            check_modifiers(trx_conn, msg.dish_id, &msg.modifier_ids)?;
 
-        if let Ok((mapping_id, dish_count)) = dish_to_order
-            .select((id, count))
-            .filter(order_id.eq(msg.order_id))
-            .filter(dish_id.eq(msg.dish_id))
-            .first::<(i64, i32)>(&mut conn)
-        {
-            diesel::update(dish_to_order.find(mapping_id))
-                .set(count.eq(dish_count + 1))
-                .execute(&mut conn);
-
-            return Ok(msg.order_id);
-        };
+            let before = order_snapshot(trx_conn, msg.order_id)?;
 
-        conn.build_transaction().run(|trx_conn| {
-            diesel::insert_into(dish_to_order)
-                .values(OrderDish {
-                    dish_id: msg.dish_id,
-                    order_id: msg.order_id,
-                    count: 1,
-                })
-                .execute(trx_conn)?;
+            upsert_order_line(
+                trx_conn,
+                msg.order_id,
+                msg.dish_id,
+                &msg.modifier_ids,
+                msg.note.clone(),
+                1,
+            )?;
+
+            recalculate_total_cost(trx_conn, msg.order_id)?;
+
+            let after = order_snapshot(trx_conn, msg.order_id)?;
+            audit(trx_conn, "order", msg.order_id, "add_line", before, after)?;
 
             Ok(msg.order_id)
         })
@@ -360,92 +1916,215 @@ impl Handler<AddDishToOrder> for PgActor {
 }
 
 impl Handler<DecrementDishInOrder> for PgActor {
-    type Result = QueryResult<i64>;
+    type Result = Result<i64, ApiError>;
 
     fn handle(&mut self, msg: DecrementDishInOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_order::{count, dish_id, dsl::dish_to_order, id, order_id};
-        use crate::schema::orders::{dsl::orders, is_confirmed};
+        use crate::schema::dish_to_order::{count, dsl::dish_to_order};
 
         let mut conn = establish_connection(&self.0)?;
 
-        match orders
-            .find(msg.order_id)
-            .select(is_confirmed)
-            .first::<bool>(&mut conn)
-        {
-            Ok(val) => {
-                if val {
-                    return Err(get_db_err("The order is already confirmed"));
-                }
-            }
-            Err(err) => return Err(err),
-        };
-
         conn.build_transaction().run(|trx_conn| {
-            let (mapping_id, dish_count) = dish_to_order
-                .select((id, count))
-                .filter(order_id.eq(msg.order_id))
-                .filter(dish_id.eq(msg.dish_id))
-                .first::<(i64, i32)>(trx_conn)?;
+            lock_unconfirmed_order(trx_conn, msg.order_id)?;
+
+            let before = order_snapshot(trx_conn, msg.order_id)?;
+            let (mapping_id, dish_count) = find_order_line(trx_conn, msg.order_id, &msg.line)?;
 
             if dish_count == 1 {
-                diesel::delete(dish_to_order.find(mapping_id)).execute(trx_conn);
+                diesel::delete(dish_to_order.find(mapping_id)).execute(trx_conn)?;
             } else {
                 diesel::update(dish_to_order.find(mapping_id))
-                    .set(count.eq(dish_count - 1))
-                    .execute(trx_conn);
+                    .set(count.eq(count - 1))
+                    .execute(trx_conn)?;
             }
 
+            recalculate_total_cost(trx_conn, msg.order_id)?;
+
+            let after = order_snapshot(trx_conn, msg.order_id)?;
+            audit(
+                trx_conn,
+                "order",
+                msg.order_id,
+                "decrement_line",
+                before,
+                after,
+            )?;
+
             Ok(msg.order_id)
         })
     }
 }
 
 impl Handler<DeleteDishFromOrder> for PgActor {
-    type Result = QueryResult<i64>;
+    type Result = Result<i64, ApiError>;
 
     fn handle(&mut self, msg: DeleteDishFromOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::dish_to_order::{dish_id, dsl::dish_to_order, order_id};
-        use crate::schema::orders::{dsl::orders, is_confirmed};
-        use crate::services::db_models::Order;
+        use crate::schema::dish_to_order::{dish_id, dsl::dish_to_order, id, order_id};
 
         let mut conn = establish_connection(&self.0)?;
 
         conn.build_transaction().run(|trx_conn| {
-            match orders
-                .find(msg.order_id)
-                .select(is_confirmed)
-                .first::<bool>(trx_conn)
-            {
-                Ok(val) => {
-                    if val {
-                        return Err(get_db_err("The order is already confirmed"));
-                    }
-                }
-                Err(err) => return Err(err),
+            lock_unconfirmed_order(trx_conn, msg.order_id)?;
+
+            let before = order_snapshot(trx_conn, msg.order_id)?;
+
+            // a dish reference removes every line of the dish
+            let deleted = match msg.line {
+                OrderLineRef::Dish(d_id) => diesel::delete(
+                    dish_to_order
+                        .filter(dish_id.eq(d_id))
+                        .filter(order_id.eq(msg.order_id)),
+                )
+                .execute(trx_conn)?,
+                OrderLineRef::Line(l_id) => diesel::delete(
+                    dish_to_order
+                        .filter(id.eq(l_id))
+                        .filter(order_id.eq(msg.order_id)),
+                )
+                .execute(trx_conn)?,
             };
 
-            diesel::delete(
-                dish_to_order
-                    .filter(dish_id.eq(msg.dish_id))
-                    .filter(order_id.eq(msg.order_id)),
-            )
-            .execute(trx_conn)?;
+            if deleted == 0 {
+                return Err(ApiError::NotFound(
+                    "There is no such dish or line in the order".to_owned(),
+                ));
+            }
+
+            recalculate_total_cost(trx_conn, msg.order_id)?;
+
+            let after = order_snapshot(trx_conn, msg.order_id)?;
+            audit(
+                trx_conn,
+                "order",
+                msg.order_id,
+                "delete_line",
+                before,
+                after,
+            )?;
 
             Ok(msg.order_id)
         })
     }
 }
 
+impl Handler<SetLineCount> for PgActor {
+    type Result = Result<OrderInfo, ApiError>;
+
+    fn handle(&mut self, msg: SetLineCount, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_order::{count, dsl::dish_to_order};
+        use crate::schema::orders::dsl::orders;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            lock_unconfirmed_order(trx_conn, msg.order_id)?;
+
+            let before = order_snapshot(trx_conn, msg.order_id)?;
+            let (mapping_id, _) =
+                find_order_line(trx_conn, msg.order_id, &OrderLineRef::Line(msg.line_id))?;
+
+            if msg.count == 0 {
+                diesel::delete(dish_to_order.find(mapping_id)).execute(trx_conn)?;
+            } else {
+                diesel::update(dish_to_order.find(mapping_id))
+                    .set(count.eq(msg.count))
+                    .execute(trx_conn)?;
+            }
+
+            recalculate_total_cost(trx_conn, msg.order_id)?;
+
+            let after = order_snapshot(trx_conn, msg.order_id)?;
+            audit(
+                trx_conn,
+                "order",
+                msg.order_id,
+                "set_line_count",
+                before,
+                after,
+            )?;
+
+            Ok(OrderInfo {
+                order: orders.find(msg.order_id).get_result::<Order>(trx_conn)?,
+                dishes: fetch_order_lines(trx_conn, msg.order_id)?,
+            })
+        })
+    }
+}
+
+impl Handler<BatchEditOrder> for PgActor {
+    type Result = Result<OrderInfo, ApiError>;
+
+    fn handle(&mut self, msg: BatchEditOrder, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_order::{dsl::dish_to_order, order_id};
+        use crate::schema::orders::dsl::orders;
+
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            lock_unconfirmed_order(trx_conn, msg.order_id)?;
+
+            let before = order_snapshot(trx_conn, msg.order_id)?;
+
+            match msg.edit {
+                BatchEdit::Replace(lines) => {
+                    diesel::delete(dish_to_order.filter(order_id.eq(msg.order_id)))
+                        .execute(trx_conn)?;
+
+                    for line in lines.into_iter().filter(|line| line.count > 0) {
+                        ensure_dish_not_archived(trx_conn, line.dish_id)?;
+                        check_modifiers(trx_conn, line.dish_id, &line.modifiers)?;
+
+                        // equal lines of the desired set are merged by summing their counts
+                        change_line_count(
+                            trx_conn,
+                            msg.order_id,
+                            line.dish_id,
+                            &line.modifiers,
+                            line.note,
+                            line.count,
+                        )?;
+                    }
+                }
+                BatchEdit::Deltas(deltas) => {
+                    for line in deltas {
+                        if line.delta > 0 {
+                            ensure_dish_not_archived(trx_conn, line.dish_id)?;
+                            check_modifiers(trx_conn, line.dish_id, &line.modifiers)?;
+                        }
+
+                        change_line_count(
+                            trx_conn,
+                            msg.order_id,
+                            line.dish_id,
+                            &line.modifiers,
+                            line.note,
+                            line.delta,
+                        )?;
+                    }
+                }
+            }
+
+            recalculate_total_cost(trx_conn, msg.order_id)?;
+
+            let after = order_snapshot(trx_conn, msg.order_id)?;
+            audit(trx_conn, "order", msg.order_id, "batch_edit", before, after)?;
+
+            Ok(OrderInfo {
+                order: orders.find(msg.order_id).get_result::<Order>(trx_conn)?,
+                dishes: fetch_order_lines(trx_conn, msg.order_id)?,
+            })
+        })
+    }
+}
+
 #[derive(AsChangeset)]
 #[diesel(table_name = orders)]
 struct ConfirmOrderChangeSet {
     pub is_confirmed: bool,
-    pub confirmed_at: NaiveDateTime,
+    pub confirmed_at: DateTime<Utc>,
 }
 
 impl Handler<ConfirmOrder> for PgActor {
-    type Result = QueryResult<()>;
+    type Result = Result<(), ApiError>;
 
     fn handle(&mut self, msg: ConfirmOrder, _ctx: &mut Self::Context) -> Self::Result {
         use crate::schema::dish_to_order::{
@@ -454,50 +2133,62 @@ impl Handler<ConfirmOrder> for PgActor {
         use crate::schema::dish_to_product::{
             dish_id as dtp_dish_id, dsl::dish_to_product, product_id, weight_g,
         };
-        use crate::schema::orders::{dsl::orders, id as ord_pk, is_confirmed};
+        use crate::schema::modifier_products::{
+            dsl::modifier_products, modifier_id as mp_modifier_id, product_id as mp_product_id,
+            weight_delta_g,
+        };
+        use crate::schema::order_line_modifiers::{
+            dsl::order_line_modifiers, modifier_id as olm_modifier_id,
+        };
+        use crate::schema::orders::dsl::orders;
         use crate::schema::products::{dsl::products, id as prod_pk, in_stock_g};
+        use diesel::JoinOnDsl;
         use std::collections::HashMap;
 
         let mut conn = establish_connection(&self.0)?;
 
-        match orders
-            .find(msg.0)
-            .select(is_confirmed)
-            .first::<bool>(&mut conn)
-        {
-            Ok(val) => {
-                if val {
-                    return Err(get_db_err("The order is already confirmed"));
-                }
-            }
-            Err(err) => return Err(err),
-        };
+        // the check, the stock deduction and the confirmation happen under one row lock,
+        // so the second of two concurrent confirmations sees the order already confirmed
+        conn.build_transaction().run(|trx_conn| {
+            lock_unconfirmed_order(trx_conn, msg.0)?;
 
-        let ordered_dishes = orders
-            .find(msg.0)
-            .inner_join(dish_to_order)
-            .filter(ord_pk.eq(order_id))
-            .select((dto_dish_id, count))
-            .get_results::<(i64, i32)>(&mut conn)?;
+            let before = order_snapshot(trx_conn, msg.0)?;
+
+            let ordered_dishes = dish_to_order
+                .filter(order_id.eq(msg.0))
+                .select((dto_dish_id, count))
+                .get_results::<(i64, i32)>(trx_conn)?;
 
-        let mut dishes_to_count = HashMap::new();
-        let mut products_to_weight: HashMap<i64, i32> = HashMap::new();
+            let mut dishes_to_count = HashMap::new();
+            let mut products_to_weight: HashMap<i64, i32> = HashMap::new();
 
-        for (id, dish_count) in ordered_dishes {
-            dishes_to_count.insert(id, dish_count);
-        }
+            // the same dish may be ordered in several lines with different modifiers
+            for (id, dish_count) in ordered_dishes {
+                *dishes_to_count.entry(id).or_insert(0) += dish_count;
+            }
 
-        conn.build_transaction().run(|trx_conn| {
             let dish_to_products_usage = dish_to_product
                 .filter(dtp_dish_id.eq_any(dishes_to_count.keys()))
                 .select((dtp_dish_id, product_id, weight_g))
                 .get_results::<(i64, i64, i32)>(trx_conn)?;
 
             for (dish, product, weight) in dish_to_products_usage {
-                let already_used = products_to_weight.entry(product.clone()).or_insert(0);
+                let already_used = products_to_weight.entry(product).or_insert(0);
                 *already_used += weight * dishes_to_count.get(&dish).unwrap();
             }
 
+            let modifier_usage = order_line_modifiers
+                .inner_join(dish_to_order)
+                .inner_join(modifier_products.on(mp_modifier_id.eq(olm_modifier_id)))
+                .filter(order_id.eq(msg.0))
+                .select((mp_product_id, weight_delta_g, count))
+                .get_results::<(i64, i32, i32)>(trx_conn)?;
+
+            for (product, weight_delta, line_count) in modifier_usage {
+                let already_used = products_to_weight.entry(product).or_insert(0);
+                *already_used += weight_delta * line_count;
+            }
+
             for (p_id, weight_used) in products_to_weight {
                 diesel::update(products.find(p_id))
                     .set(in_stock_g.eq(in_stock_g - weight_used))
@@ -507,10 +2198,13 @@ impl Handler<ConfirmOrder> for PgActor {
             diesel::update(orders.find(msg.0))
                 .set(ConfirmOrderChangeSet {
                     is_confirmed: true,
-                    confirmed_at: Local::now().naive_local(),
+                    confirmed_at: Utc::now(),
                 })
                 .execute(trx_conn)?;
 
+            let after = order_snapshot(trx_conn, msg.0)?;
+            audit(trx_conn, "order", msg.0, "confirm", before, after)?;
+
             Ok(())
         })
     }
@@ -520,109 +2214,277 @@ impl Handler<ConfirmOrder> for PgActor {
 #[diesel(table_name = orders)]
 struct CookOrderChangeSet {
     pub is_cooked: bool,
-    pub cooked_at: NaiveDateTime,
+    pub cooked_at: DateTime<Utc>,
 }
 
 impl Handler<CookOrder> for PgActor {
-    type Result = QueryResult<()>;
+    type Result = Result<(), ApiError>;
 
     fn handle(&mut self, msg: CookOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::orders::{cooked_at, dsl::orders, is_confirmed, is_cooked};
+        use crate::schema::orders::dsl::orders;
 
         let mut conn = establish_connection(&self.0)?;
 
-        match orders
-            .find(msg.0)
-            .select(is_confirmed)
-            .first::<bool>(&mut conn)
-        {
-            Ok(val) => {
-                if !val {
-                    return Err(get_db_err("The order is not confirmed yet"));
-                }
+        conn.build_transaction().run(|trx_conn| {
+            let order = lock_order(trx_conn, msg.0)?;
+
+            if !order.is_confirmed {
+                return Err(ApiError::conflict(
+                    "order_not_confirmed",
+                    format!("Order {} is not confirmed yet", msg.0),
+                ));
+            }
+            if order.is_cooked {
+                return Err(ApiError::conflict(
+                    "order_cooked",
+                    format!("Order {} is already cooked", msg.0),
+                ));
             }
-            Err(err) => return Err(err),
-        };
 
-        diesel::update(orders.find(msg.0))
-            .set(CookOrderChangeSet {
-                is_cooked: true,
-                cooked_at: Local::now().naive_local(),
-            })
-            .execute(&mut conn)?;
+            let before = order_snapshot(trx_conn, msg.0)?;
 
-        Ok(())
+            diesel::update(orders.find(msg.0))
+                .set(CookOrderChangeSet {
+                    is_cooked: true,
+                    cooked_at: Utc::now(),
+                })
+                .execute(trx_conn)?;
+
+            let after = order_snapshot(trx_conn, msg.0)?;
+            audit(trx_conn, "order", msg.0, "cook", before, after)?;
+
+            Ok(())
+        })
     }
 }
 
 impl Handler<PayForOrder> for PgActor {
-    type Result = QueryResult<()>;
+    type Result = Result<(), ApiError>;
 
     fn handle(&mut self, msg: PayForOrder, _ctx: &mut Self::Context) -> Self::Result {
-        use crate::schema::orders::{dsl::orders, is_confirmed, is_paid, total_cost};
+        use crate::schema::orders::{dsl::orders, is_paid, paid_at};
         use crate::schema::stats::{day, dsl::stats, income};
-        use crate::services::db_models::Order;
+        use crate::schema::{tables, tips};
         use crate::services::insertable::NewStats;
-        use chrono::NaiveDate;
 
         let mut conn = establish_connection(&self.0)?;
 
-        match orders
-            .find(msg.0)
-            .select(is_confirmed)
-            .first::<bool>(&mut conn)
-        {
-            Ok(val) => {
-                if !val {
-                    return Err(get_db_err("The order is not confirmed yet"));
-                }
+        conn.build_transaction().run(|trx_conn| {
+            let order = lock_order(trx_conn, msg.order_id)?;
+
+            if !order.is_confirmed {
+                return Err(ApiError::conflict(
+                    "order_not_confirmed",
+                    format!("Order {} is not confirmed yet", msg.order_id),
+                ));
             }
-            Err(err) => return Err(err),
-        };
+            if order.is_paid {
+                return Err(ApiError::conflict(
+                    "order_paid",
+                    format!("Order {} is already paid", msg.order_id),
+                ));
+            }
+
+            let before = order_snapshot(trx_conn, msg.order_id)?;
+
+            diesel::update(orders.find(msg.order_id))
+                .set((is_paid.eq(true), paid_at.eq(Utc::now())))
+                .execute(trx_conn)?;
+
+            let mut after = order_snapshot(trx_conn, msg.order_id)?;
+
+            if msg.tip > 0 {
+                // the tip goes to whoever serves the table now, the waiter of the order
+                // may have clocked out since
+                let table_waiter = tables::table
+                    .find(order.table_id)
+                    .select(tables::waiter_id)
+                    .get_result::<Option<i64>>(trx_conn)
+                    .optional()?
+                    .flatten();
+
+                diesel::insert_into(tips::table)
+                    .values((
+                        tips::order_id.eq(msg.order_id),
+                        tips::waiter_id.eq(table_waiter.or(order.waiter_id)),
+                        tips::amount.eq(msg.tip),
+                        tips::created_at.eq(Utc::now()),
+                    ))
+                    .execute(trx_conn)?;
+
+                after["tip"] = json!(msg.tip);
+            }
+
+            audit(trx_conn, "order", msg.order_id, "pay", before, after)?;
+
+            // relies on the unique day constraint, so the first payment of a day
+            // can't create two records
+            diesel::insert_into(stats)
+                .values(NewStats {
+                    day: msg.day,
+                    income: order.total_cost,
+                })
+                .on_conflict(day)
+                .do_update()
+                .set(income.eq(income + order.total_cost))
+                .execute(trx_conn)?;
+
+            Ok(())
+        })
+    }
+}
+
+fn ensure_unpaid(order: &Order) -> Result<(), ApiError> {
+    if order.is_paid {
+        return Err(ApiError::conflict(
+            "order_paid",
+            format!("Order {} is already paid", order.id),
+        ));
+    }
+
+    Ok(())
+}
+
+impl Handler<TransferOrder> for PgActor {
+    type Result = Result<(), ApiError>;
+
+    fn handle(&mut self, msg: TransferOrder, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::tables;
+
+        let mut conn = establish_connection(&self.0)?;
 
         conn.build_transaction().run(|trx_conn| {
-            let order_cost = match orders
-                .find(msg.0)
-                .select((is_paid, total_cost))
-                .first::<(bool, i32)>(trx_conn)
-            {
-                Ok(val) => {
-                    if val.0 {
-                        return Err(get_db_err("The order is already paid"));
-                    } else {
-                        val.1
-                    }
-                }
-                Err(err) => return Err(err),
-            };
+            let order = lock_order(trx_conn, msg.order_id)?;
+            ensure_unpaid(&order)?;
 
-            diesel::update(orders.find(msg.0))
-                .set(is_paid.eq(true))
+            if order.table_id == msg.table_id {
+                return Ok(());
+            }
+
+            let table = tables::table
+                .find(msg.table_id)
+                .get_result::<Table>(trx_conn)
+                .optional()?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Table {} is not found", msg.table_id))
+                })?;
+
+            // the waiter of the new table takes the order over, if the table has one
+            let new_waiter_id = table.waiter_id.or(order.waiter_id);
+            let before = order_snapshot(trx_conn, order.id)?;
+
+            diesel::update(orders::table.find(order.id))
+                .set((
+                    orders::table_id.eq(table.id),
+                    orders::waiter_id.eq(new_waiter_id),
+                ))
                 .execute(trx_conn)?;
 
-            let today = chrono::Local::now().date_naive();
+            let after = order_snapshot(trx_conn, order.id)?;
+            audit(trx_conn, "order", order.id, "transfer", before, after)
+        })
+    }
+}
+
+impl Handler<MergeOrders> for PgActor {
+    type Result = Result<(), ApiError>;
 
-            let is_first_record = stats
-                .select(day)
-                .filter(day.eq(today))
-                .first::<NaiveDate>(trx_conn)
-                .err()
-                != None;
+    fn handle(&mut self, msg: MergeOrders, _ctx: &mut Self::Context) -> Self::Result {
+        use crate::schema::dish_to_order;
 
-            if is_first_record {
-                diesel::insert_into(stats)
-                    .values(NewStats {
-                        day: today,
-                        income: order_cost,
-                    })
-                    .execute(trx_conn)
+        let mut conn = establish_connection(&self.0)?;
+
+        conn.build_transaction().run(|trx_conn| {
+            // locks are taken in the order of ids, so merges of the same two orders in
+            // opposite directions can't deadlock
+            let (target, source) = if msg.order_id < msg.from_order_id {
+                let target = lock_order(trx_conn, msg.order_id)?;
+                (target, lock_order(trx_conn, msg.from_order_id)?)
             } else {
-                diesel::update(stats.filter(day.eq(today)))
-                    .set(income.eq(income + order_cost))
-                    .execute(trx_conn)
-            }?;
+                let source = lock_order(trx_conn, msg.from_order_id)?;
+                (lock_order(trx_conn, msg.order_id)?, source)
+            };
 
-            Ok(())
+            ensure_unpaid(&target)?;
+            ensure_unpaid(&source)?;
+
+            // stock is deducted on confirmation, a merged order must be deducted entirely or not
+            // at all
+            if target.is_confirmed != source.is_confirmed {
+                return Err(ApiError::conflict(
+                    "order_state_mismatch",
+                    format!(
+                        "Orders {} and {} must be both confirmed or both unconfirmed",
+                        target.id, source.id
+                    ),
+                ));
+            }
+
+            let target_before = order_snapshot(trx_conn, target.id)?;
+            let source_before = order_snapshot(trx_conn, source.id)?;
+
+            let lines = dish_to_order::table
+                .filter(dish_to_order::order_id.eq(source.id))
+                .select((
+                    dish_to_order::id,
+                    dish_to_order::dish_id,
+                    dish_to_order::line_key,
+                    dish_to_order::count,
+                ))
+                .load::<(i64, i64, String, i32)>(trx_conn)?;
+
+            for (l_id, d_id, key, line_count) in lines {
+                let merged = diesel::update(
+                    dish_to_order::table
+                        .filter(dish_to_order::order_id.eq(target.id))
+                        .filter(dish_to_order::dish_id.eq(d_id))
+                        .filter(dish_to_order::line_key.eq(&key)),
+                )
+                .set(dish_to_order::count.eq(dish_to_order::count + line_count))
+                .execute(trx_conn)?;
+
+                // modifiers of the removed line are deleted with it, the kept line has the same
+                if merged > 0 {
+                    diesel::delete(dish_to_order::table.find(l_id)).execute(trx_conn)?;
+                } else {
+                    diesel::update(dish_to_order::table.find(l_id))
+                        .set(dish_to_order::order_id.eq(target.id))
+                        .execute(trx_conn)?;
+                }
+            }
+
+            let total_cost = target.total_cost + source.total_cost;
+            let is_cooked = target.is_cooked && source.is_cooked;
+
+            diesel::update(orders::table.find(target.id))
+                .set((
+                    orders::total_cost.eq(total_cost),
+                    orders::is_cooked.eq(is_cooked),
+                    orders::cooked_at.eq(target.cooked_at.filter(|_| is_cooked)),
+                ))
+                .execute(trx_conn)?;
+
+            diesel::delete(orders::table.find(source.id)).execute(trx_conn)?;
+
+            let mut target_after = order_snapshot(trx_conn, target.id)?;
+            target_after["merged_order_id"] = json!(source.id);
+
+            audit(
+                trx_conn,
+                "order",
+                target.id,
+                "merge",
+                target_before,
+                target_after,
+            )?;
+            audit(
+                trx_conn,
+                "order",
+                source.id,
+                "merged_into",
+                source_before,
+                json!({ "merged_into_order_id": target.id }),
+            )
         })
     }
 }
@@ -0,0 +1,142 @@
+//! Printable QR codes linking guests to the ordering page of their table, see [`super::guest`].
+//! Codes are rendered locally, so table links never leave the service
+
+use std::io::Cursor;
+
+use image::{ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::ApiError;
+
+pub const DEFAULT_QR_SIZE_PX: u32 = 256;
+pub const MIN_QR_SIZE_PX: u32 = 64;
+pub const MAX_QR_SIZE_PX: u32 = 2048;
+
+/// Columns of the sheet with codes of every table, three fit an A4 page
+const SHEET_COLUMNS: usize = 3;
+/// Room under each code of the sheet for the table label
+const LABEL_HEIGHT_PX: u32 = 40;
+const SHEET_GAP_PX: u32 = 24;
+
+#[derive(Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QrQuery {
+    /// `svg` by default
+    format: Option<QrFormat>,
+    /// approximate width and height of each code in pixels, 64 to 2048, 256 by default
+    size: Option<u32>,
+}
+
+impl QrQuery {
+    pub fn format(&self) -> QrFormat {
+        self.format.unwrap_or_default()
+    }
+
+    pub fn size(&self) -> Result<u32, ApiError> {
+        match self.size.unwrap_or(DEFAULT_QR_SIZE_PX) {
+            size @ MIN_QR_SIZE_PX..=MAX_QR_SIZE_PX => Ok(size),
+            _ => Err(ApiError::invalid(
+                "size",
+                format!("must be in range {MIN_QR_SIZE_PX}..={MAX_QR_SIZE_PX}"),
+            )),
+        }
+    }
+}
+
+/// Link a code of the table leads to
+pub fn table_url(app_url: &str, token: &str) -> String {
+    format!("{app_url}/{token}")
+}
+
+/// Code of the table with its label, an entry of the sheet
+pub struct SheetEntry {
+    pub table_id: i64,
+    pub url: String,
+}
+
+fn encode(url: &str) -> Result<QrCode, ApiError> {
+    // the medium level survives a scratched or stained code on a table
+    QrCode::with_error_correction_level(url, EcLevel::M)
+        .map_err(|err| ApiError::Internal(format!("Failed to encode QR code of {url}: {err}")))
+}
+
+pub fn render_svg(url: &str, size: u32) -> Result<String, ApiError> {
+    Ok(encode(url)?
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .build())
+}
+
+pub fn render_png(url: &str, size: u32) -> Result<Vec<u8>, ApiError> {
+    let image = encode(url)?
+        .render::<Luma<u8>>()
+        .min_dimensions(size, size)
+        .build();
+
+    let mut png = Cursor::new(vec![]);
+    image
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|err| ApiError::Internal(format!("Failed to render PNG QR code: {err}")))?;
+
+    Ok(png.into_inner())
+}
+
+/// One SVG document with a labeled code of each table, laid out in a grid for printing
+pub fn render_sheet(entries: &[SheetEntry], size: u32) -> Result<String, ApiError> {
+    let cell_width = size + SHEET_GAP_PX;
+    let cell_height = size + LABEL_HEIGHT_PX + SHEET_GAP_PX;
+    let rows = entries.len().div_ceil(SHEET_COLUMNS) as u32;
+    let columns = entries.len().min(SHEET_COLUMNS) as u32;
+
+    let mut cells = String::new();
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let x = (idx % SHEET_COLUMNS) as u32 * cell_width + SHEET_GAP_PX / 2;
+        let y = (idx / SHEET_COLUMNS) as u32 * cell_height + SHEET_GAP_PX / 2;
+
+        // every code fits its cell, the nested documents are stripped of the XML prolog
+        let code = encode(&entry.url)?
+            .render::<svg::Color>()
+            .max_dimensions(size, size)
+            .build();
+        let code = code
+            .find("<svg")
+            .map_or(code.as_str(), |start| &code[start..]);
+
+        cells.push_str(&format!(
+            concat!(
+                r#"<g transform="translate({x},{y})">{code}"#,
+                r#"<text x="{label_x}" y="{label_y}" text-anchor="middle" "#,
+                r#"font-family="sans-serif" font-size="24">Table {table_id}</text></g>"#,
+            ),
+            x = x,
+            y = y,
+            code = code,
+            label_x = size / 2,
+            label_y = size + LABEL_HEIGHT_PX * 3 / 4,
+            table_id = entry.table_id,
+        ));
+    }
+
+    Ok(format!(
+        concat!(
+            r#"<?xml version="1.0" standalone="yes"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" "#,
+            r#"width="{width}" height="{height}">{cells}</svg>"#,
+        ),
+        width = columns * cell_width,
+        height = rows * cell_height,
+        cells = cells,
+    ))
+}
@@ -0,0 +1,46 @@
+//! Checks rendering of table QR codes and of the sheet with codes of every table.
+
+use image::ImageFormat;
+
+use crate::services::qr_codes::{render_png, render_sheet, render_svg, SheetEntry};
+
+const URL: &str = "https://order.example.com/guest/12.x0lDfVjE8tUPqG2hrMN_3A";
+
+#[test]
+fn png_code_is_at_least_of_requested_size() {
+    let png = render_png(URL, 200).unwrap();
+    let image = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+
+    assert!(image.width() >= 200 && image.height() >= 200);
+}
+
+#[test]
+fn svg_code_is_standalone_document() {
+    let svg = render_svg(URL, 200).unwrap();
+
+    assert!(svg.starts_with("<?xml"), "{svg}");
+    assert!(svg.contains("<svg"), "{svg}");
+}
+
+#[test]
+fn sheet_labels_code_of_every_table() {
+    let entries = (1..=4)
+        .map(|table_id| SheetEntry {
+            table_id,
+            url: format!("{URL}{table_id}"),
+        })
+        .collect::<Vec<_>>();
+
+    let sheet = render_sheet(&entries, 128).unwrap();
+
+    assert_eq!(
+        sheet.matches("<?xml").count(),
+        1,
+        "nested codes must have no prolog"
+    );
+    assert_eq!(sheet.matches("<svg").count(), 1 + entries.len());
+
+    for table_id in 1..=4 {
+        assert!(sheet.contains(&format!(">Table {table_id}</text>")));
+    }
+}
@@ -1,21 +1,90 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
 use actix::Addr;
-use chrono::NaiveDate;
-use redis::RedisError;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, RedisError, RedisResult};
 use serde::Serialize;
+use tokio::sync::OnceCell;
 
+use crate::errors::ApiError;
 use crate::services::db_models::Dish;
 use crate::services::db_utils::PgActor;
-use crate::services::messages::FetchDishIngredients;
-use crate::types::ACTIVE_MENU_KEY;
-use crate::types::{RedisDish, MENU_KEY};
+use crate::services::messages::{FetchDishAllergens, FetchDishIngredients, FetchDishModifiers};
+use crate::types::ACTIVE_MENU_SINCE_KEY;
+use crate::types::{CachedJson, DishType, Menu, MenuCacheReport, MenuRevision, MenuSection};
+use crate::types::{IdempotencyRecord, ACTIVE_MENU_KEY, IDEMPOTENCY_KEY, RATE_LIMIT_KEY};
+use crate::types::{RedisDish, MODIFIED_AT_FIELD, VERSION_FIELD};
+use crate::types::{MENU_FIELD, MENU_INDEX_KEY, MENU_KEY, MENU_VERSION_KEY};
+
+/// How many times a menu is re-read when it changes between reading and writing it
+const MENU_WRITE_ATTEMPTS: u32 = 5;
 
+/// Shares one multiplexed connection between all the workers. The connection is opened
+/// on first use, so the service starts while redis is down, and is reopened when dropped
+#[derive(Clone)]
 pub struct RedisHandler {
-    db: redis::Client,
+    client: redis::Client,
+    manager: Arc<OnceCell<ConnectionManager>>,
+    /// limits connecting as well as every command
+    timeout: Duration,
 }
 
 impl RedisHandler {
-    pub fn new(db: redis::Client) -> Self {
-        Self { db }
+    pub fn new(client: redis::Client, timeout: Duration) -> Self {
+        Self {
+            client,
+            manager: Arc::new(OnceCell::new()),
+            timeout,
+        }
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager, ApiError> {
+        self.connection_with_timeout(self.timeout).await
+    }
+
+    async fn connection_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<ConnectionManager, ApiError> {
+        let connect = self
+            .manager
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()));
+
+        let manager = with_timeout(
+            timeout,
+            "Failed to establish connection with redis",
+            connect,
+        )
+        .await?;
+
+        Ok(manager.clone())
+    }
+
+    /// Runs a command or a pipeline, giving up after the timeout of the handler
+    async fn run<T>(
+        &self,
+        action: &'static str,
+        query: impl Future<Output = RedisResult<T>>,
+    ) -> Result<T, ApiError> {
+        with_timeout(self.timeout, action, query).await
+    }
+
+    /// `timeout` replaces the usual one, so that a health check fails fast
+    pub async fn ping(&self, timeout: Duration) -> Result<(), ApiError> {
+        let mut conn = self.connection_with_timeout(timeout).await?;
+
+        with_timeout(
+            timeout,
+            "Failed to ping redis",
+            redis::cmd("PING").query_async::<_, String>(&mut conn),
+        )
+        .await?;
+
+        Ok(())
     }
 
     pub async fn save_new_menu(
@@ -23,140 +92,597 @@ impl RedisHandler {
         pg_db: Addr<PgActor>,
         dishes: Vec<Dish>,
         date: &NaiveDate,
-    ) -> Result<String, String> {
-        let menu_json = serde_json::to_string(&dishes)
-            .map_err(|_| "Failed to compose JSON object of menu".to_owned())?;
+        title: Option<String>,
+        course_order: &[DishType],
+    ) -> Result<String, ApiError> {
+        let mut redis_dishes = vec![];
+
+        for dish in dishes {
+            redis_dishes.push(compose_redis_dish(&pg_db, dish).await?);
+        }
 
-        let mut conn = self
-            .db
-            .get_connection()
-            .map_err(|_| "Failed to establish connection with redis".to_owned())?;
+        let mut conn = self.connection().await?;
 
         let menu_key = format!("{MENU_KEY}_{date}");
 
-        redis::cmd("SET")
+        let version = self
+            .run(
+                "Failed to bump menu version",
+                redis::cmd("INCR")
+                    .arg(format!("{MENU_VERSION_KEY}_{date}"))
+                    .query_async::<_, i64>(&mut conn),
+            )
+            .await?;
+
+        let menu = Menu {
+            title: title.unwrap_or_else(|| format!("Menu for {date}")),
+            valid_on: *date,
+            version,
+            sections: group_by_course(redis_dishes.clone(), course_order),
+        };
+
+        let menu_json = serde_json::to_string(&menu).map_err(|err| {
+            ApiError::Internal(format!("Failed to compose JSON object of menu: {err}"))
+        })?;
+
+        // the previous composition for the date is replaced with its dishes in one round trip
+        let mut pipeline = redis::pipe();
+        pipeline
+            .atomic()
+            .cmd("DEL")
+            .arg(&menu_key)
+            .ignore()
+            .cmd("HSET")
             .arg(&menu_key)
+            .arg(MENU_FIELD)
             .arg(menu_json)
-            .query::<()>(&mut conn)
-            .map_err(|_| "Failed to set JSON object as menu".to_owned())?;
+            .arg(VERSION_FIELD)
+            .arg(version)
+            .arg(MODIFIED_AT_FIELD)
+            .arg(Utc::now().timestamp())
+            .ignore();
 
-        for dish in dishes {
-            match pg_db.send(FetchDishIngredients(dish.id)).await {
-                Ok(Ok(resp)) => {
-                    let redis_dish = RedisDish {
-                        dish: dish.clone(),
-                        ingredients: resp,
-                    };
-
-                    if let Ok(dish_entry) = serde_json::to_string(&redis_dish) {
-                        redis::cmd("SET")
-                            .arg(format!("{}_dish-{}", &menu_key, dish.id))
-                            .arg(dish_entry)
-                            .query::<()>(&mut conn)
-                            .map_err(|_| "Failed to set dish for new menu".to_owned())?
-                    };
-                }
-                Err(_) => {
-                    return Err("There is no dish_to_product records for this dish id".to_owned())
-                }
-                _ => return Err("Unable to get dish ingredients".to_owned()),
-            }
+        for redis_dish in redis_dishes {
+            let dish_entry = serde_json::to_string(&redis_dish).map_err(|err| {
+                ApiError::Internal(format!("Failed to compose JSON object of dish: {err}"))
+            })?;
+
+            pipeline
+                .cmd("HSET")
+                .arg(&menu_key)
+                .arg(dish_field(redis_dish.dish.id))
+                .arg(dish_entry)
+                .ignore();
         }
 
+        pipeline
+            .cmd("SADD")
+            .arg(MENU_INDEX_KEY)
+            .arg(&menu_key)
+            .ignore();
+
+        self.run(
+            "Failed to set JSON object as menu",
+            pipeline.query_async::<_, ()>(&mut conn),
+        )
+        .await?;
+
         Ok(menu_key)
     }
 
-    pub fn set_active_menu(&self, date: &NaiveDate) -> Result<(), String> {
-        let mut conn = self
-            .db
-            .get_connection()
-            .map_err(|_| "Failed to establish connection with redis".to_owned())?;
+    /// Puts the fresh state of the dish into every cached menu that contains it.
+    /// Archived dishes are removed from those menus instead
+    pub async fn refresh_dish(
+        &self,
+        pg_db: Addr<PgActor>,
+        dish: Dish,
+        course_order: &[DishType],
+    ) -> Result<(), ApiError> {
+        let dish_id = dish.id;
+
+        let redis_dish = if dish.archived_at.is_none() {
+            Some(compose_redis_dish(&pg_db, dish).await?)
+        } else {
+            None
+        };
 
-        redis::cmd("SET")
-            .arg(ACTIVE_MENU_KEY)
-            .arg(format!("{MENU_KEY}_{date}"))
-            .query::<()>(&mut conn)
-            .map_err(|_| "Failed to set active menu".to_owned())
-    }
+        let mut conn = self.connection().await?;
 
-    pub fn get_menu(&self) -> Result<String, String> {
-        let mut conn = self
-            .db
-            .get_connection()
-            .map_err(|_| "Failed to establish connection with redis".to_owned())?;
+        let menu_keys = self
+            .run(
+                "Failed to find cached menus",
+                redis::cmd("SMEMBERS")
+                    .arg(MENU_INDEX_KEY)
+                    .query_async::<_, Vec<String>>(&mut conn),
+            )
+            .await?;
 
-        match redis::cmd("GET")
-            .arg(ACTIVE_MENU_KEY)
-            .query::<String>(&mut conn)
-        {
-            Ok(active_menu_key) => redis::cmd("GET")
-                .arg(active_menu_key)
-                .query::<String>(&mut conn)
-                .map_err(|_| "Failed to get JSON object of menu from redis db".to_owned()),
-            Err(_) => Err("Failed to get value of active menu".to_owned()),
+        for menu_key in menu_keys {
+            let mut attempt = 0;
+
+            while !self
+                .try_refresh_dish(
+                    &mut conn,
+                    &menu_key,
+                    dish_id,
+                    redis_dish.as_ref(),
+                    course_order,
+                )
+                .await?
+            {
+                attempt += 1;
+
+                if attempt == MENU_WRITE_ATTEMPTS {
+                    return Err(ApiError::Internal(format!(
+                        "Menu '{menu_key}' kept changing while dish {dish_id} was refreshed"
+                    )));
+                }
+            }
         }
+
+        Ok(())
     }
 
-    pub fn delete_menu(&self, date: &NaiveDate) -> Result<(), String> {
-        let mut conn = self
-            .db
-            .get_connection()
-            .map_err(|_| "Failed to establish connection with redis".to_owned())?;
+    /// Rewrites one menu with the fresh dish. The menu is written only if its version is still
+    /// the one it was read with, so `false` means a concurrent change and the read is repeated
+    async fn try_refresh_dish(
+        &self,
+        conn: &mut ConnectionManager,
+        menu_key: &str,
+        dish_id: i64,
+        redis_dish: Option<&RedisDish>,
+        course_order: &[DishType],
+    ) -> Result<bool, ApiError> {
+        let (menu_json, read_version, cached_dish) = self
+            .run(
+                "Failed to read cached menu",
+                redis::cmd("HMGET")
+                    .arg(menu_key)
+                    .arg(MENU_FIELD)
+                    .arg(VERSION_FIELD)
+                    .arg(dish_field(dish_id))
+                    .query_async::<_, (Option<String>, Option<i64>, Option<String>)>(conn),
+            )
+            .await?;
 
-        let menu_to_delete = format!("{MENU_KEY}_{date}");
+        if cached_dish.is_none() {
+            return Ok(true);
+        }
 
-        let dishes_to_delete: Vec<String> = redis::cmd("keys")
-            .arg(format!("{menu_to_delete}_*"))
-            .query(&mut conn)
-            .map_err(|_| "Failed to get dishes from specified menu".to_owned())?;
+        let mut menu: Menu = menu_json
+            .and_then(|menu_json| serde_json::from_str(&menu_json).ok())
+            .ok_or_else(|| {
+                ApiError::Internal(format!("Failed to read cached menu '{menu_key}'"))
+            })?;
 
-        let mut pipeline = redis::pipe();
-        let mut pipeline = pipeline.atomic();
+        let mut menu_dishes: Vec<RedisDish> = menu
+            .sections
+            .into_iter()
+            .flat_map(|section| section.dishes)
+            .filter(|menu_dish| menu_dish.dish.id != dish_id)
+            .collect();
 
-        for dish in dishes_to_delete {
-            pipeline.cmd("DEL").arg(dish).ignore();
+        if let Some(redis_dish) = redis_dish {
+            menu_dishes.push(redis_dish.clone());
         }
 
-        redis::cmd("DEL")
-            .arg(&menu_to_delete)
-            .query::<()>(&mut conn)
-            .map_err(|_| "Failed to delete specified menu".to_owned())?;
+        menu.version = self
+            .run(
+                "Failed to bump menu version",
+                redis::cmd("INCR")
+                    .arg(format!("{MENU_VERSION_KEY}_{}", menu.valid_on))
+                    .query_async::<_, i64>(conn),
+            )
+            .await?;
+        menu.sections = group_by_course(menu_dishes, course_order);
 
-        pipeline
-            .query(&mut conn)
-            .map_err(|_| "Failed to delete dishes of specified menu".to_owned())?;
+        let menu_json = serde_json::to_string(&menu).map_err(|err| {
+            ApiError::Internal(format!("Failed to compose JSON object of menu: {err}"))
+        })?;
 
-        if let Ok(active) = redis::cmd("GET")
-            .arg(ACTIVE_MENU_KEY)
-            .query::<String>(&mut conn)
-        {
-            if active == menu_to_delete {
-                redis::cmd("DEL")
+        // an empty entry removes the dish, which is how archived dishes leave the menu
+        let dish_entry = match redis_dish {
+            Some(redis_dish) => serde_json::to_string(redis_dish).map_err(|err| {
+                ApiError::Internal(format!("Failed to compose JSON object of dish: {err}"))
+            })?,
+            None => String::new(),
+        };
+
+        let script = redis::Script::new(
+            r"
+            if (redis.call('HGET', KEYS[1], ARGV[2]) or '') ~= ARGV[3] then
+                return 0
+            end
+            redis.call('HSET', KEYS[1], ARGV[1], ARGV[4], ARGV[2], ARGV[5], ARGV[6], ARGV[7])
+            if ARGV[9] == '' then
+                redis.call('HDEL', KEYS[1], ARGV[8])
+            else
+                redis.call('HSET', KEYS[1], ARGV[8], ARGV[9])
+            end
+            return 1
+            ",
+        );
+
+        let written = self
+            .run(
+                "Failed to refresh dish in menu",
+                script
+                    .key(menu_key)
+                    .arg(MENU_FIELD)
+                    .arg(VERSION_FIELD)
+                    .arg(
+                        read_version
+                            .map(|version| version.to_string())
+                            .unwrap_or_default(),
+                    )
+                    .arg(menu_json)
+                    .arg(menu.version)
+                    .arg(MODIFIED_AT_FIELD)
+                    .arg(Utc::now().timestamp())
+                    .arg(dish_field(dish_id))
+                    .arg(dish_entry)
+                    .invoke_async::<_, i64>(conn),
+            )
+            .await?;
+
+        Ok(written == 1)
+    }
+
+    pub async fn set_active_menu(&self, date: &NaiveDate) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+
+        self.run(
+            "Failed to set active menu",
+            redis::cmd("MSET")
+                .arg(ACTIVE_MENU_KEY)
+                .arg(format!("{MENU_KEY}_{date}"))
+                .arg(ACTIVE_MENU_SINCE_KEY)
+                .arg(Utc::now().timestamp())
+                .query_async::<_, ()>(&mut conn),
+        )
+        .await
+    }
+
+    pub async fn get_menu(&self) -> Result<CachedJson, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let (active_menu_key, activated_at) = self.get_active_menu(&mut conn).await?;
+
+        self.get_cached(&mut conn, &active_menu_key, MENU_FIELD)
+            .await?
+            .map(|menu| menu.activated_at(activated_at))
+            .ok_or_else(|| ApiError::NotFound(format!("Menu '{active_menu_key}' is not found")))
+    }
+
+    pub async fn get_menu_by_date(&self, date: &NaiveDate) -> Result<CachedJson, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let menu_key = format!("{MENU_KEY}_{date}");
+
+        self.get_cached(&mut conn, &menu_key, MENU_FIELD)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Menu '{menu_key}' is not found")))
+    }
+
+    /// Key of the active menu with the time it was activated at
+    async fn get_active_menu(
+        &self,
+        conn: &mut ConnectionManager,
+    ) -> Result<(String, Option<DateTime<Utc>>), ApiError> {
+        let (active_menu_key, activated_at) = self
+            .run(
+                "Failed to get currently active menu",
+                redis::cmd("MGET")
                     .arg(ACTIVE_MENU_KEY)
-                    .query::<()>(&mut conn)
-                    .map_err(|_| "Failed to delete active menu as long as it points to deleted")?;
-            }
+                    .arg(ACTIVE_MENU_SINCE_KEY)
+                    .query_async::<_, (Option<String>, Option<i64>)>(conn),
+            )
+            .await?;
+
+        let active_menu_key = active_menu_key
+            .ok_or_else(|| ApiError::NotFound("There is no active menu".to_owned()))?;
+
+        Ok((
+            active_menu_key,
+            activated_at.and_then(|at| DateTime::from_timestamp(at, 0)),
+        ))
+    }
+
+    /// Reads a field of the menu hash along with the revision of the menu
+    async fn get_cached(
+        &self,
+        conn: &mut ConnectionManager,
+        menu_key: &str,
+        field: &str,
+    ) -> Result<Option<CachedJson>, ApiError> {
+        let (json, version, modified_at) = self
+            .run(
+                "Failed to get JSON object of menu from redis db",
+                redis::cmd("HMGET")
+                    .arg(menu_key)
+                    .arg(field)
+                    .arg(VERSION_FIELD)
+                    .arg(MODIFIED_AT_FIELD)
+                    .query_async::<_, (Option<String>, Option<i64>, Option<i64>)>(conn),
+            )
+            .await?;
+
+        let revision = version
+            .zip(modified_at)
+            .map(|(version, modified_at)| MenuRevision {
+                menu_key: menu_key.to_owned(),
+                version,
+                modified_at: DateTime::from_timestamp(modified_at, 0).unwrap_or_default(),
+            });
+
+        Ok(json.map(|json| CachedJson { json, revision }))
+    }
+
+    /// Deletes the menu with its dishes in one step. The active menu is reset when it is
+    /// the deleted one
+    pub async fn delete_menu(&self, date: &NaiveDate) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+
+        let script = redis::Script::new(
+            r"
+            redis.call('DEL', KEYS[1])
+            redis.call('SREM', KEYS[2], KEYS[1])
+            if redis.call('GET', KEYS[3]) == KEYS[1] then
+                redis.call('DEL', KEYS[3], KEYS[4])
+            end
+            return 0
+            ",
+        );
+
+        self.run(
+            "Failed to delete specified menu",
+            script
+                .key(format!("{MENU_KEY}_{date}"))
+                .key(MENU_INDEX_KEY)
+                .key(ACTIVE_MENU_KEY)
+                .key(ACTIVE_MENU_SINCE_KEY)
+                .invoke_async::<_, ()>(&mut conn),
+        )
+        .await
+    }
+
+    pub async fn get_dish(&self, dish_id: i64) -> Result<CachedJson, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let (active_menu_key, activated_at) = self.get_active_menu(&mut conn).await?;
+
+        self.get_cached(&mut conn, &active_menu_key, &dish_field(dish_id))
+            .await?
+            .map(|dish| dish.activated_at(activated_at))
+            .ok_or_else(|| ApiError::NotFound(format!("Dish {dish_id} is not in the active menu")))
+    }
+
+    /// Compares menus with their index. Keys are scanned in batches, so redis is not blocked,
+    /// but menus changed during the check may be reported
+    pub async fn check_menu_cache(&self) -> Result<MenuCacheReport, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let keys: Vec<String> = self
+            .run("Failed to scan cached menus", async {
+                let keys = conn
+                    .clone()
+                    .scan_match::<_, String>(format!("{MENU_KEY}_*"))
+                    .await?
+                    .collect()
+                    .await;
+
+                Ok(keys)
+            })
+            .await?;
+
+        let indexed = self
+            .run(
+                "Failed to find cached menus",
+                redis::cmd("SMEMBERS")
+                    .arg(MENU_INDEX_KEY)
+                    .query_async::<_, Vec<String>>(&mut conn),
+            )
+            .await?;
+
+        let active = self
+            .run(
+                "Failed to get currently active menu",
+                redis::cmd("GET")
+                    .arg(ACTIVE_MENU_KEY)
+                    .query_async::<_, Option<String>>(&mut conn),
+            )
+            .await?;
+
+        Ok(MenuCacheReport::new(&keys, &indexed, active))
+    }
+
+    /// Stores `pending` record under the key unless the key is already used.
+    /// Returns the record of the earlier request in the latter case
+    pub async fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        pending: &IdempotencyRecord,
+        ttl_s: u64,
+    ) -> Result<Option<IdempotencyRecord>, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let record_key = format!("{IDEMPOTENCY_KEY}_{key}");
+        let record_json = serde_json::to_string(pending).map_err(|err| {
+            ApiError::Internal(format!(
+                "Failed to compose JSON object of idempotency record: {err}"
+            ))
+        })?;
+
+        let is_reserved = self
+            .run(
+                "Failed to reserve idempotency key",
+                redis::cmd("SET")
+                    .arg(&record_key)
+                    .arg(record_json)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_s)
+                    .query_async::<_, Option<String>>(&mut conn),
+            )
+            .await?
+            .is_some();
+
+        if is_reserved {
+            return Ok(None);
         }
 
-        Ok(())
+        self.run(
+            "Failed to get idempotency record",
+            redis::cmd("GET")
+                .arg(&record_key)
+                .query_async::<_, Option<String>>(&mut conn),
+        )
+        .await?
+        .and_then(|record_json| serde_json::from_str(&record_json).ok())
+        .map(Some)
+        .ok_or_else(|| {
+            ApiError::conflict(
+                "idempotency_record_expired",
+                "Idempotency record has expired, repeat the request",
+            )
+        })
+    }
+
+    pub async fn complete_idempotency_key(
+        &self,
+        key: &str,
+        completed: &IdempotencyRecord,
+        ttl_s: u64,
+    ) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+
+        let record_json = serde_json::to_string(completed).map_err(|err| {
+            ApiError::Internal(format!(
+                "Failed to compose JSON object of idempotency record: {err}"
+            ))
+        })?;
+
+        self.run(
+            "Failed to save idempotent response",
+            redis::cmd("SET")
+                .arg(format!("{IDEMPOTENCY_KEY}_{key}"))
+                .arg(record_json)
+                .arg("EX")
+                .arg(ttl_s)
+                .query_async::<_, ()>(&mut conn),
+        )
+        .await
+    }
+
+    /// Frees the key, so that the request may be repeated with it
+    pub async fn release_idempotency_key(&self, key: &str) -> Result<(), ApiError> {
+        let mut conn = self.connection().await?;
+
+        self.run(
+            "Failed to release idempotency key",
+            redis::cmd("DEL")
+                .arg(format!("{IDEMPOTENCY_KEY}_{key}"))
+                .query_async::<_, ()>(&mut conn),
+        )
+        .await
+    }
+
+    /// Counts a request of `client` in the current window of `window_s` seconds,
+    /// returns the number of its requests in the window, this one included
+    pub async fn count_request(
+        &self,
+        client: &str,
+        window_s: u64,
+        now_s: u64,
+    ) -> Result<u64, ApiError> {
+        let mut conn = self.connection().await?;
+
+        let counter_key = format!("{RATE_LIMIT_KEY}_{client}_{}", now_s / window_s);
+
+        let (count,): (u64,) = self
+            .run(
+                "Failed to count request",
+                redis::pipe()
+                    .atomic()
+                    .incr(&counter_key, 1)
+                    .expire(&counter_key, window_s as i64)
+                    .ignore()
+                    .query_async(&mut conn),
+            )
+            .await?;
+
+        Ok(count)
+    }
+}
+
+/// Fails with `Unavailable` when redis does not respond in time
+async fn with_timeout<T>(
+    timeout: Duration,
+    action: &'static str,
+    query: impl Future<Output = RedisResult<T>>,
+) -> Result<T, ApiError> {
+    tokio::time::timeout(timeout, query)
+        .await
+        .map_err(|_| {
+            ApiError::Unavailable(format!(
+                "{action}: Redis did not respond in {}ms",
+                timeout.as_millis()
+            ))
+        })?
+        .map_err(redis_err(action))
+}
+
+/// Keeps the description of the failed step, but tells unreachable redis apart from other failures
+fn redis_err(action: &'static str) -> impl Fn(RedisError) -> ApiError {
+    move |err| match ApiError::from(err) {
+        ApiError::Unavailable(message) => ApiError::Unavailable(format!("{action}: {message}")),
+        other => ApiError::Internal(format!("{action}: {other}")),
     }
+}
+
+fn dish_field(dish_id: i64) -> String {
+    format!("dish-{dish_id}")
+}
+
+/// Collects everything that is cached about the dish besides its own row
+async fn compose_redis_dish(pg_db: &Addr<PgActor>, dish: Dish) -> Result<RedisDish, ApiError> {
+    let ingredients = pg_db.send(FetchDishIngredients(dish.id)).await??;
+    let allergens = pg_db.send(FetchDishAllergens(dish.id)).await??;
+    let modifier_groups = pg_db.send(FetchDishModifiers(dish.id)).await??;
+
+    Ok(RedisDish {
+        dish,
+        ingredients,
+        allergens,
+        modifier_groups,
+    })
+}
 
-    pub fn get_dish(&self, dish_id: i64) -> Result<String, String> {
-        let mut conn = self
-            .db
-            .get_connection()
-            .map_err(|_| "Failed to establish connection with redis".to_owned())?;
+/// Splits dishes into sections following `course_order`. Empty courses are skipped
+/// and dishes inside of a course are ordered by name
+fn group_by_course(dishes: Vec<RedisDish>, course_order: &[DishType]) -> Vec<MenuSection> {
+    let mut sections: Vec<MenuSection> = course_order
+        .iter()
+        .map(|course| MenuSection {
+            course: *course,
+            dishes: vec![],
+        })
+        .collect();
 
-        let dish_prefix = redis::cmd("GET")
-            .arg(ACTIVE_MENU_KEY)
-            .query::<String>(&mut conn)
-            .map_err(|_| "Failed to get currently active menu".to_owned())?;
+    for redis_dish in dishes {
+        if let Some(section) = sections
+            .iter_mut()
+            .find(|section| section.course == redis_dish.dish.type_)
+        {
+            section.dishes.push(redis_dish);
+        }
+    }
 
-        let dish_key = format!("{dish_prefix}_dish-{dish_id}");
+    sections.retain(|section| !section.dishes.is_empty());
 
-        redis::cmd("GET")
-            .arg(dish_key)
-            .query::<String>(&mut conn)
-            .map_err(|_| "Failed to get specified dish from active menu".to_owned())
+    for section in sections.iter_mut() {
+        section.dishes.sort_by(|a, b| a.dish.name.cmp(&b.dish.name));
     }
+
+    sections
 }
@@ -0,0 +1,219 @@
+//! Clocks waiters and other workers in and out against a real database, see
+//! [`crate::services::test_db`].
+
+use diesel::prelude::*;
+use futures::future::join_all;
+
+use crate::errors::ApiError;
+use crate::services::messages::{
+    AddWaiter, AssignTableWaiter, ClockIn, ClockOut, ConfirmOrder, FetchOnShiftStaff, FetchOrder,
+    PayForOrder, ShiftOf,
+};
+use crate::services::test_db::{add_dishes, setup, CONCURRENCY, DISH_PRICE};
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn clock_out_releases_tables_assigned_meanwhile() {
+    use crate::schema::tables;
+
+    let fx = setup("shift handover").await;
+
+    let waiter = fx
+        .pg_db
+        .send(AddWaiter {
+            first_name: "Shift".to_owned(),
+            last_name: "Handover".to_owned(),
+            is_admin: false,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    fx.pg_db
+        .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap()
+        .unwrap();
+    let again = fx
+        .pg_db
+        .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap();
+    assert!(matches!(
+        again,
+        Err(ApiError::Conflict {
+            code: "already_on_shift",
+            ..
+        })
+    ));
+
+    fx.pg_db
+        .send(AssignTableWaiter {
+            table_id: fx.table_id,
+            waiter_id: Some(waiter.id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let order = fx
+        .pg_db
+        .send(FetchOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        order.order.waiter_id,
+        Some(waiter.id),
+        "unpaid order must be handed over"
+    );
+
+    let table_ids = diesel::insert_into(tables::table)
+        .values(vec![tables::seat_count.eq(2); CONCURRENCY])
+        .returning(tables::id)
+        .get_results::<i64>(&mut fx.pool.get().unwrap())
+        .unwrap();
+
+    let assignments = join_all(table_ids.iter().map(|table_id| {
+        fx.pg_db.send(AssignTableWaiter {
+            table_id: *table_id,
+            waiter_id: Some(waiter.id),
+        })
+    }));
+    let (_, summary) = futures::join!(
+        assignments,
+        fx.pg_db.send(ClockOut(ShiftOf::Waiter(waiter.id)))
+    );
+    let summary = summary.unwrap().unwrap();
+
+    let still_assigned = tables::table
+        .filter(tables::waiter_id.eq(waiter.id))
+        .count()
+        .get_result::<i64>(&mut fx.pool.get().unwrap())
+        .unwrap();
+
+    assert_eq!(still_assigned, 0, "off-shift waiter must not keep tables");
+    assert!(summary.released_table_ids.contains(&fx.table_id));
+    assert_eq!(summary.open_orders.len(), 1);
+    assert_eq!(summary.open_orders[0].id, fx.order_id);
+    assert!(summary.shift.ended_at.is_some());
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn shift_summary_counts_orders_paid_during_it() {
+    let fx = setup("shift summary").await;
+
+    let waiter = fx
+        .pg_db
+        .send(AddWaiter {
+            first_name: "Late".to_owned(),
+            last_name: "Payer".to_owned(),
+            is_admin: false,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // the order of the fixture is opened before the shift starts
+    fx.pg_db
+        .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(AssignTableWaiter {
+            table_id: fx.table_id,
+            waiter_id: Some(waiter.id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    add_dishes(&fx, 2).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(PayForOrder {
+            order_id: fx.order_id,
+            tip: 0,
+            day: chrono::Utc::now().date_naive(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let summary = fx
+        .pg_db
+        .send(ClockOut(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(summary.orders_served, 1);
+    assert_eq!(summary.revenue, i64::from(2 * DISH_PRICE));
+    assert!(summary.open_orders.is_empty());
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn worker_without_waiter_record_clocks_in() {
+    use crate::schema::{worker, worker_role};
+
+    let fx = setup("worker shift").await;
+
+    let mut conn = fx.pool.get().unwrap();
+    let role_id = diesel::insert_into(worker_role::table)
+        .values(worker_role::title.eq("cook"))
+        .returning(worker_role::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let cook_id = diesel::insert_into(worker::table)
+        .values((
+            worker::first_name.eq("Kitchen"),
+            worker::last_name.eq("Cook"),
+            worker::role_id.eq(role_id),
+        ))
+        .returning(worker::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+
+    let shift = fx
+        .pg_db
+        .send(ClockIn(ShiftOf::Worker(cook_id)))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((shift.waiter_id, shift.worker_id), (None, Some(cook_id)));
+
+    let again = fx
+        .pg_db
+        .send(ClockIn(ShiftOf::Worker(cook_id)))
+        .await
+        .unwrap();
+    assert!(
+        matches!(&again, Err(ApiError::Conflict { code, .. }) if *code == "already_on_shift"),
+        "{again:?}"
+    );
+
+    let on_shift = fx.pg_db.send(FetchOnShiftStaff).await.unwrap().unwrap();
+    let cook = on_shift
+        .iter()
+        .find(|staff| staff.shift.id == shift.id)
+        .unwrap();
+    assert!(cook.waiter.is_none());
+    assert_eq!(cook.worker.as_ref().unwrap().role, "cook");
+
+    let summary = fx
+        .pg_db
+        .send(ClockOut(ShiftOf::Worker(cook_id)))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(summary.shift.id, shift.id);
+    assert_eq!(summary.orders_served, 0);
+    assert!(summary.released_table_ids.is_empty());
+}
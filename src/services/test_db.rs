@@ -0,0 +1,118 @@
+//! Fixture of the tests running against a real database.
+//!
+//! `TEST_PG_DATABASE_URL` points to the database, pending migrations are applied to it before
+//! the first test. Such tests are ignored unless run with `cargo test -- --ignored`.
+
+use std::sync::Once;
+use std::time::Duration;
+
+use actix::{Addr, SyncArbiter};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use futures::future::join_all;
+
+use crate::services::db_utils::{get_db_pool, run_migrations, PgActor};
+use crate::services::messages::{AddDishToOrder, CreateDish, CreateOrder};
+use crate::types::{DishType, Ingredient};
+
+/// Number of messages sent at once by the tests of races
+pub const CONCURRENCY: usize = 20;
+pub const PRODUCT_STOCK_G: i32 = 100_000;
+pub const DISH_PRICE: i32 = 150;
+
+static MIGRATE: Once = Once::new();
+
+/// Fresh product, dish of 100 g of it, table and an empty order of the table
+pub struct Fixture {
+    pub pg_db: Addr<PgActor>,
+    pub pool: Pool<ConnectionManager<PgConnection>>,
+    pub product_id: i64,
+    pub dish_id: i64,
+    pub table_id: i64,
+    pub order_id: i64,
+}
+
+pub fn db_url() -> String {
+    std::env::var("TEST_PG_DATABASE_URL")
+        .expect("TEST_PG_DATABASE_URL must point to a test database")
+}
+
+/// `name` tells apart the records of the test
+pub async fn setup(name: &str) -> Fixture {
+    use crate::schema::{products, tables};
+
+    let pool = get_db_pool(&db_url(), 10, Duration::from_secs(5))
+        .expect("Unable to connect to the test database");
+    let mut conn = pool.get().unwrap();
+
+    MIGRATE.call_once(|| {
+        run_migrations(&mut conn).expect("Unable to migrate the test database");
+    });
+
+    let suffix = chrono::Local::now().timestamp_nanos_opt().unwrap();
+
+    let product_id = diesel::insert_into(products::table)
+        .values((
+            products::name.eq(format!("{name} {suffix}")),
+            products::in_stock_g.eq(PRODUCT_STOCK_G),
+        ))
+        .returning(products::id)
+        .get_result::<i64>(&mut conn)
+        .unwrap();
+
+    let table_id = diesel::insert_into(tables::table)
+        .values(tables::seat_count.eq(4))
+        .returning(tables::id)
+        .get_result::<i64>(&mut conn)
+        .unwrap();
+
+    let pg_pool = pool.clone();
+    let pg_db = SyncArbiter::start(5, move || PgActor(pg_pool.clone()));
+
+    let dish = pg_db
+        .send(CreateDish {
+            dish_name: format!("{name} {suffix}"),
+            dish_type: DishType::Main,
+            price: DISH_PRICE,
+            approx_cook_time_s: 600,
+            portion_weight_g: 300,
+            description: None,
+            image_url: None,
+            dietary_labels: vec![],
+            ingredients: vec![Ingredient {
+                id: product_id,
+                used_g: 100,
+            }],
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let order_id = pg_db.send(CreateOrder(table_id)).await.unwrap().unwrap();
+
+    Fixture {
+        pg_db,
+        pool,
+        product_id,
+        dish_id: dish.id,
+        table_id,
+        order_id,
+    }
+}
+
+/// Adds the dish of the fixture to its order `times` times at once
+pub async fn add_dishes(fx: &Fixture, times: usize) {
+    let results = join_all((0..times).map(|_| {
+        fx.pg_db.send(AddDishToOrder {
+            order_id: fx.order_id,
+            dish_id: fx.dish_id,
+            modifier_ids: vec![],
+            note: None,
+        })
+    }))
+    .await;
+
+    for res in results {
+        res.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Distribution of tips by the policy of [`TipSettings`]. Amounts are in minor units of the
+//! currency, so every split is rounded down and the remainder goes one unit per worker in the
+//! order of their ids, nothing is lost
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use crate::settings::TipSettings;
+use crate::types::{TipPayout, TipPayoutInputs, TipPayoutReport};
+
+/// Tips of a waiter with a staff record are paid to the worker, so their shares are summed
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Payee {
+    Worker(i32),
+    Waiter(i64),
+}
+
+/// Pools of `crews` are split apart, so a worker shares only the tips paid during their shifts
+pub fn tip_payouts(
+    policy: &TipSettings,
+    crews: Vec<TipPayoutInputs>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> TipPayoutReport {
+    let mut payouts = BTreeMap::<Payee, TipPayout>::new();
+    let mut total_tips = 0;
+    let mut pooled_tips = 0;
+    let mut undistributed = 0;
+
+    for inputs in crews {
+        let mut pool = inputs.unattributed;
+
+        for waiter_tips in inputs.waiter_tips {
+            let direct = waiter_tips.amount * policy.waiter_share_percent as i64 / 100;
+            let waiter = waiter_tips.waiter;
+
+            total_tips += waiter_tips.amount;
+            pool += waiter_tips.amount - direct;
+
+            let payee = waiter
+                .worker_id
+                .map_or(Payee::Waiter(waiter.id), Payee::Worker);
+            let payout = payouts.entry(payee).or_insert_with(|| TipPayout {
+                worker_id: waiter.worker_id,
+                waiter_id: Some(waiter.id),
+                name: format!("{} {}", waiter.first_name, waiter.last_name),
+                role: None,
+                direct: 0,
+                pooled: 0,
+                total: 0,
+            });
+            payout.direct += direct;
+        }
+
+        total_tips += inputs.unattributed;
+        pooled_tips += pool;
+
+        let mut staff = inputs
+            .pool_staff
+            .into_iter()
+            .filter_map(|member| Some((*policy.pool_roles.get(&member.role)? as i64, member)))
+            .collect::<Vec<_>>();
+        staff.sort_by_key(|(_, member)| member.worker_id);
+
+        let total_weight = staff.iter().map(|(weight, _)| weight).sum::<i64>();
+
+        if total_weight == 0 {
+            undistributed += pool;
+            continue;
+        }
+
+        let shares = staff
+            .iter()
+            .map(|(weight, _)| pool * weight / total_weight)
+            .collect::<Vec<_>>();
+        let remainder = pool - shares.iter().sum::<i64>();
+
+        for (idx, ((_, member), share)) in staff.into_iter().zip(shares).enumerate() {
+            let share = share + i64::from((idx as i64) < remainder);
+
+            let payout = payouts
+                .entry(Payee::Worker(member.worker_id))
+                .or_insert_with(|| TipPayout {
+                    worker_id: Some(member.worker_id),
+                    waiter_id: None,
+                    name: member.name,
+                    role: None,
+                    direct: 0,
+                    pooled: 0,
+                    total: 0,
+                });
+            payout.role = Some(member.role);
+            payout.pooled += share;
+        }
+    }
+
+    let payouts = payouts
+        .into_values()
+        .map(|payout| TipPayout {
+            total: payout.direct + payout.pooled,
+            ..payout
+        })
+        .collect();
+
+    TipPayoutReport {
+        from,
+        to,
+        total_tips,
+        pooled_tips,
+        undistributed,
+        payouts,
+    }
+}
@@ -0,0 +1,435 @@
+//! Checks the split of tips between waiters and workers of the pooled roles.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::services::db_models::Waiter;
+use crate::services::tips::tip_payouts;
+use crate::settings::TipSettings;
+use crate::types::{StaffMember, TipPayout, TipPayoutInputs, TipPayoutReport, WaiterTips};
+
+use diesel::prelude::*;
+use futures::future::join_all;
+
+use crate::services::messages::{
+    AddWaiter, AssignTableWaiter, ClockIn, ClockOut, ConfirmOrder, FetchTipPayoutInputs,
+    LinkWaiterWorker, PayForOrder, ShiftOf,
+};
+use crate::services::test_db::{add_dishes, setup, CONCURRENCY};
+
+fn policy(waiter_share_percent: u32) -> TipSettings {
+    TipSettings {
+        waiter_share_percent,
+        pool_roles: HashMap::from([("waiter".to_owned(), 2), ("cook".to_owned(), 1)]),
+    }
+}
+
+fn waiter_tips(id: i64, worker_id: Option<i32>, amount: i64) -> WaiterTips {
+    WaiterTips {
+        waiter: Waiter {
+            id,
+            first_name: "Waiter".to_owned(),
+            last_name: id.to_string(),
+            worker_id,
+        },
+        amount,
+    }
+}
+
+fn member(worker_id: i32, role: &str) -> StaffMember {
+    StaffMember {
+        worker_id,
+        name: format!("Worker {worker_id}"),
+        role: role.to_owned(),
+    }
+}
+
+fn report(policy: &TipSettings, inputs: TipPayoutInputs) -> TipPayoutReport {
+    let day = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+
+    tip_payouts(policy, vec![inputs], day, day)
+}
+
+fn payout_of(report: &TipPayoutReport, worker_id: i32) -> &TipPayout {
+    report
+        .payouts
+        .iter()
+        .find(|payout| payout.worker_id == Some(worker_id))
+        .unwrap()
+}
+
+#[test]
+fn waiter_keeps_share_and_pool_is_split_by_role_weight() {
+    let report = report(
+        &policy(70),
+        TipPayoutInputs {
+            waiter_tips: vec![waiter_tips(1, Some(10), 1000)],
+            unattributed: 0,
+            pool_staff: vec![member(10, "waiter"), member(20, "cook")],
+        },
+    );
+
+    assert_eq!(report.total_tips, 1000);
+    assert_eq!(report.pooled_tips, 300);
+    assert_eq!(report.payouts.len(), 2, "{:?}", report.payouts);
+
+    let waiter = payout_of(&report, 10);
+    assert_eq!(
+        (waiter.direct, waiter.pooled, waiter.total),
+        (700, 200, 900)
+    );
+    assert_eq!(waiter.waiter_id, Some(1));
+
+    let cook = payout_of(&report, 20);
+    assert_eq!((cook.direct, cook.pooled, cook.total), (0, 100, 100));
+}
+
+#[test]
+fn remainder_of_pool_is_not_lost() {
+    let report = report(
+        &policy(0),
+        TipPayoutInputs {
+            waiter_tips: vec![],
+            unattributed: 101,
+            pool_staff: vec![member(3, "cook"), member(1, "cook"), member(2, "cook")],
+        },
+    );
+
+    let pooled = report
+        .payouts
+        .iter()
+        .map(|payout| (payout.worker_id, payout.pooled))
+        .collect::<Vec<_>>();
+
+    assert_eq!(pooled, vec![(Some(1), 34), (Some(2), 34), (Some(3), 33)]);
+    assert_eq!(report.undistributed, 0);
+}
+
+#[test]
+fn pool_without_staff_stays_undistributed() {
+    let report = report(
+        &policy(50),
+        TipPayoutInputs {
+            waiter_tips: vec![waiter_tips(1, None, 500)],
+            unattributed: 100,
+            pool_staff: vec![member(10, "dishwasher")],
+        },
+    );
+
+    assert_eq!(report.total_tips, 600);
+    assert_eq!(report.undistributed, 350);
+    assert_eq!(report.payouts.len(), 1, "{:?}", report.payouts);
+    assert_eq!(report.payouts[0].waiter_id, Some(1));
+    assert_eq!(report.payouts[0].total, 250);
+}
+
+#[test]
+fn pools_of_crews_are_split_apart() {
+    let day = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+    let report = tip_payouts(
+        &policy(0),
+        vec![
+            TipPayoutInputs {
+                waiter_tips: vec![],
+                unattributed: 300,
+                pool_staff: vec![member(10, "waiter"), member(20, "cook")],
+            },
+            TipPayoutInputs {
+                waiter_tips: vec![],
+                unattributed: 50,
+                pool_staff: vec![member(20, "cook")],
+            },
+        ],
+        day,
+        day,
+    );
+
+    assert_eq!(report.total_tips, 350);
+    assert_eq!(report.pooled_tips, 350);
+    assert_eq!(payout_of(&report, 10).pooled, 200);
+    assert_eq!(payout_of(&report, 20).pooled, 150);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn tip_is_recorded_once_for_waiter_of_table() {
+    use crate::schema::tips;
+
+    let fx = setup("tips").await;
+
+    let waiter = fx
+        .pg_db
+        .send(AddWaiter {
+            first_name: "Tipped".to_owned(),
+            last_name: "Waiter".to_owned(),
+            is_admin: false,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    fx.pg_db
+        .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(AssignTableWaiter {
+            table_id: fx.table_id,
+            waiter_id: Some(waiter.id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    add_dishes(&fx, 1).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let results = join_all((0..CONCURRENCY).map(|_| {
+        fx.pg_db.send(PayForOrder {
+            order_id: fx.order_id,
+            tip: 25,
+            day: chrono::Utc::now().date_naive(),
+        })
+    }))
+    .await;
+
+    assert_eq!(
+        results
+            .into_iter()
+            .filter(|res| matches!(res, Ok(Ok(()))))
+            .count(),
+        1
+    );
+
+    let recorded = tips::table
+        .filter(tips::order_id.eq(fx.order_id))
+        .select((tips::waiter_id, tips::amount))
+        .load::<(Option<i64>, i32)>(&mut fx.pool.get().unwrap())
+        .unwrap();
+
+    assert_eq!(recorded, vec![(Some(waiter.id), 25)]);
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn tips_are_pooled_among_staff_on_shift() {
+    use crate::schema::{worker, worker_role};
+
+    let fx = setup("tip pool").await;
+
+    let from = chrono::Utc::now();
+    let mut conn = fx.pool.get().unwrap();
+    let role = format!("pooled {}", from.timestamp_nanos_opt().unwrap());
+    let role_id = diesel::insert_into(worker_role::table)
+        .values(worker_role::title.eq(&role))
+        .returning(worker_role::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+
+    let mut staff = vec![];
+
+    for last_name in ["On", "Off"] {
+        let worker_id = diesel::insert_into(worker::table)
+            .values((
+                worker::first_name.eq("Shift"),
+                worker::last_name.eq(last_name),
+                worker::role_id.eq(role_id),
+            ))
+            .returning(worker::id)
+            .get_result::<i32>(&mut conn)
+            .unwrap();
+        let waiter = fx
+            .pg_db
+            .send(AddWaiter {
+                first_name: "Shift".to_owned(),
+                last_name: last_name.to_owned(),
+                is_admin: false,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        fx.pg_db
+            .send(LinkWaiterWorker {
+                waiter_id: waiter.id,
+                worker_id: Some(worker_id),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        fx.pg_db
+            .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        staff.push((worker_id, waiter.id));
+    }
+
+    let [(on_worker_id, on_waiter_id), (off_worker_id, off_waiter_id)] = staff[..] else {
+        unreachable!();
+    };
+
+    // the shift of the other worker falls into the period, but ends before the tip is paid
+    fx.pg_db
+        .send(ClockOut(ShiftOf::Waiter(off_waiter_id)))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(AssignTableWaiter {
+            table_id: fx.table_id,
+            waiter_id: Some(on_waiter_id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    add_dishes(&fx, 1).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(PayForOrder {
+            order_id: fx.order_id,
+            tip: 100,
+            day: from.date_naive(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let crews = fx
+        .pg_db
+        .send(FetchTipPayoutInputs {
+            from,
+            to: chrono::Utc::now(),
+            pool_roles: vec![role.clone()],
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    let policy = TipSettings {
+        waiter_share_percent: 50,
+        pool_roles: HashMap::from([(role, 1)]),
+    };
+    let report = tip_payouts(&policy, crews, from.date_naive(), from.date_naive());
+
+    let payout_of = |worker_id| {
+        report
+            .payouts
+            .iter()
+            .find(|payout| payout.worker_id == Some(worker_id))
+    };
+
+    let on_shift = payout_of(on_worker_id).unwrap();
+    assert_eq!(on_shift.direct, 50);
+    assert!(on_shift.pooled >= 50, "{on_shift:?}");
+    assert!(
+        payout_of(off_worker_id).is_none(),
+        "off-shift worker must not share the pool"
+    );
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_PG_DATABASE_URL"]
+async fn kitchen_worker_without_waiter_record_shares_tips() {
+    use crate::schema::{worker, worker_role};
+
+    let fx = setup("kitchen tips").await;
+
+    let from = chrono::Utc::now();
+    let mut conn = fx.pool.get().unwrap();
+    let role = format!("kitchen {}", from.timestamp_nanos_opt().unwrap());
+    let role_id = diesel::insert_into(worker_role::table)
+        .values(worker_role::title.eq(&role))
+        .returning(worker_role::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let cook_id = diesel::insert_into(worker::table)
+        .values((
+            worker::first_name.eq("Kitchen"),
+            worker::last_name.eq("Cook"),
+            worker::role_id.eq(role_id),
+        ))
+        .returning(worker::id)
+        .get_result::<i32>(&mut conn)
+        .unwrap();
+    let waiter = fx
+        .pg_db
+        .send(AddWaiter {
+            first_name: "Floor".to_owned(),
+            last_name: "Waiter".to_owned(),
+            is_admin: false,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    fx.pg_db
+        .send(ClockIn(ShiftOf::Worker(cook_id)))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(ClockIn(ShiftOf::Waiter(waiter.id)))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(AssignTableWaiter {
+            table_id: fx.table_id,
+            waiter_id: Some(waiter.id),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    add_dishes(&fx, 1).await;
+    fx.pg_db
+        .send(ConfirmOrder(fx.order_id))
+        .await
+        .unwrap()
+        .unwrap();
+    fx.pg_db
+        .send(PayForOrder {
+            order_id: fx.order_id,
+            tip: 100,
+            day: from.date_naive(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let crews = fx
+        .pg_db
+        .send(FetchTipPayoutInputs {
+            from,
+            to: chrono::Utc::now(),
+            pool_roles: vec![role.clone()],
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    let policy = TipSettings {
+        waiter_share_percent: 50,
+        pool_roles: HashMap::from([(role, 1)]),
+    };
+    let report = tip_payouts(&policy, crews, from.date_naive(), from.date_naive());
+
+    let cook = report
+        .payouts
+        .iter()
+        .find(|payout| payout.worker_id == Some(cook_id))
+        .unwrap();
+    assert_eq!(cook.direct, 0);
+    assert!(cook.pooled >= 50, "{cook:?}");
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use actix::Addr;
+
+use crate::errors::ApiError;
+use crate::services::db_utils::PgActor;
+use crate::services::messages::{FindUnknownProducts, IsDishNameTaken};
+use crate::types::{
+    FieldError, Ingredient, MAX_COOK_TIME_S, MAX_DISH_NAME_LEN, MAX_LINE_COUNT, MAX_LINE_NOTE_LEN,
+    MAX_PORTION_WEIGHT_G,
+};
+
+/// Collects every problem of a request instead of stopping at the first one
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn into_result(self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(self.errors))
+        }
+    }
+
+    pub fn dish_name(&mut self, name: &str) {
+        if name.trim().is_empty() {
+            self.add("dish_name", "must not be empty");
+        } else if name.chars().count() > MAX_DISH_NAME_LEN {
+            self.add(
+                "dish_name",
+                format!("must be at most {MAX_DISH_NAME_LEN} characters long"),
+            );
+        }
+    }
+
+    pub fn price(&mut self, price: i32) {
+        if price <= 0 {
+            self.add("price", "must be positive");
+        }
+    }
+
+    pub fn cook_time(&mut self, approx_cook_time_s: i32) {
+        if !(1..=MAX_COOK_TIME_S).contains(&approx_cook_time_s) {
+            self.add(
+                "approx_cook_time_s",
+                format!("must be between 1 and {MAX_COOK_TIME_S} seconds"),
+            );
+        }
+    }
+
+    pub fn portion_weight(&mut self, portion_weight_g: i32) {
+        if !(1..=MAX_PORTION_WEIGHT_G).contains(&portion_weight_g) {
+            self.add(
+                "portion_weight_g",
+                format!("must be between 1 and {MAX_PORTION_WEIGHT_G} grams"),
+            );
+        }
+    }
+
+    pub fn line_count(&mut self, field: impl Into<String>, count: i32, min: i32) {
+        if !(min..=MAX_LINE_COUNT).contains(&count) {
+            self.add(field, format!("must be between {min} and {MAX_LINE_COUNT}"));
+        }
+    }
+
+    pub fn line_note(&mut self, field: impl Into<String>, note: Option<&str>) {
+        if note.is_some_and(|note| note.chars().count() > MAX_LINE_NOTE_LEN) {
+            self.add(
+                field,
+                format!("must be at most {MAX_LINE_NOTE_LEN} characters long"),
+            );
+        }
+    }
+
+    /// Checks that each product is used once and with a positive weight
+    pub fn ingredients(&mut self, ingredients: &[Ingredient]) {
+        let mut seen = HashSet::new();
+
+        for (idx, ing) in ingredients.iter().enumerate() {
+            if ing.used_g <= 0 {
+                self.add(format!("ingredients[{idx}].used_g"), "must be positive");
+            }
+
+            if !seen.insert(ing.id) {
+                self.add(
+                    format!("ingredients[{idx}].id"),
+                    format!("product {} is listed more than once", ing.id),
+                );
+            }
+        }
+    }
+
+    /// Checks the parts that require the database: the name is not used by
+    /// another active dish and all the products exist
+    pub async fn dish_references(
+        &mut self,
+        pg_db: &Addr<PgActor>,
+        dish_name: Option<&str>,
+        except_id: Option<i64>,
+        ingredients: &[Ingredient],
+    ) -> Result<(), ApiError> {
+        if let Some(name) = dish_name {
+            let is_taken = pg_db
+                .send(IsDishNameTaken {
+                    name: name.to_owned(),
+                    except_id,
+                })
+                .await??;
+
+            if is_taken {
+                self.add("dish_name", format!("dish named '{name}' already exists"));
+            }
+        }
+
+        if ingredients.is_empty() {
+            return Ok(());
+        }
+
+        let unknown = pg_db
+            .send(FindUnknownProducts(
+                ingredients.iter().map(|ing| ing.id).collect(),
+            ))
+            .await??;
+
+        for (idx, ing) in ingredients.iter().enumerate() {
+            if unknown.contains(&ing.id) {
+                self.add(
+                    format!("ingredients[{idx}].id"),
+                    format!("product {} does not exist", ing.id),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Days, LocalResult, NaiveDate, NaiveTime, Offset, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, ConfigError, Environment, File};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+
+use crate::types::DishType;
+
+/// Path of the settings file, used when `CONFIG_FILE` is not set. The file is optional
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Shorter secrets of guest tokens may be guessed
+const MIN_TOKEN_SECRET_LEN: usize = 32;
+
+/// Variables read before the settings file was introduced, they are still accepted,
+/// but `NNN_` prefixed ones take precedence
+const LEGACY_ENV_VARS: &[(&str, &str)] = &[
+    ("PG_DATABASE_URL", "DATABASE__URL"),
+    ("REDIS_DATABASE_URI", "REDIS__URL"),
+    ("ADDRESS", "SERVER__ADDRESS"),
+    ("FRONT_ORIGIN", "SERVER__CORS_ORIGINS"),
+    ("MENU_COURSE_ORDER", "MENU__COURSE_ORDER"),
+    ("MIN_MARGIN_PERCENT", "MENU__MIN_MARGIN_PERCENT"),
+    ("IDEMPOTENCY_TTL_SECONDS", "IDEMPOTENCY__TTL_S"),
+];
+
+/// Settings of the service. Layered from lowest to highest priority:
+/// defaults, the TOML file (`config.toml` or the one in `CONFIG_FILE`), legacy environment
+/// variables and `NNN_` prefixed ones, e.g. `NNN_DATABASE__POOL_SIZE=20`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub redis: RedisSettings,
+    pub restaurant: RestaurantSettings,
+    pub menu: MenuSettings,
+    pub idempotency: IdempotencySettings,
+    pub health: HealthSettings,
+    pub guest: GuestSettings,
+    pub tips: TipSettings,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServerSettings {
+    pub address: String,
+    /// origins allowed by CORS, comma separated in environment variables
+    pub cors_origins: Vec<String>,
+    /// time for a client to send request headers
+    pub client_request_timeout_ms: u64,
+    pub keep_alive_s: u64,
+    /// time for running requests to finish on graceful shutdown
+    pub shutdown_timeout_s: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub url: String,
+    /// max number of open connections
+    pub pool_size: u32,
+    /// number of threads handling database messages
+    pub actor_threads: usize,
+    /// time to wait for a free connection of the pool
+    pub connection_timeout_s: u64,
+    /// apply pending migrations before serving, otherwise the service refuses to start
+    /// until they are applied with `migrate` command
+    pub migrate_on_startup: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RedisSettings {
+    pub url: String,
+    /// limits connecting as well as every command
+    pub timeout_ms: u64,
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RestaurantSettings {
+    pub name: String,
+    /// ISO 4217 code of the currency prices are set in
+    pub currency: String,
+    #[serde(default)]
+    pub opening_hours: Vec<OpeningHours>,
+    /// IANA name of the timezone the restaurant works in
+    #[schema(value_type = String, example = "Europe/Berlin")]
+    pub timezone: Tz,
+    /// local time a business day starts at, e.g. sales before 04:00 count for the previous day.
+    /// Stats and menus are kept per business day
+    #[schema(value_type = String, example = "04:00:00")]
+    pub business_day_starts_at: NaiveTime,
+}
+
+impl RestaurantSettings {
+    /// Business day the moment belongs to
+    pub fn business_day(&self, at: DateTime<Utc>) -> NaiveDate {
+        let local = at.with_timezone(&self.timezone).naive_local();
+
+        (local - (self.business_day_starts_at - NaiveTime::MIN)).date()
+    }
+
+    /// Moment the business day starts at
+    pub fn business_day_start(&self, day: NaiveDate) -> DateTime<Utc> {
+        let local = day.and_time(self.business_day_starts_at);
+
+        match self.timezone.from_local_datetime(&local) {
+            LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at.with_timezone(&Utc),
+            // the clocks skip the start, so the day starts as much later as they are moved,
+            // a day earlier they still show the time before the gap
+            LocalResult::None => {
+                let offset = self
+                    .timezone
+                    .offset_from_utc_datetime(&(local - Days::new(1)))
+                    .fix();
+
+                (local - offset).and_utc()
+            }
+        }
+    }
+
+    /// Moments the business days from `from` to `to` inclusive start and end at
+    pub fn business_days_span(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let end = to.checked_add_days(Days::new(1))?;
+
+        Some((self.business_day_start(from), self.business_day_start(end)))
+    }
+}
+
+/// Restaurant is open from `open` till `close` on each of `days`.
+/// `close` earlier than `open` means closing after midnight
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OpeningHours {
+    #[schema(value_type = Vec<String>, example = json!(["mon", "tue"]))]
+    pub days: Vec<Weekday>,
+    #[schema(value_type = String, example = "10:00:00")]
+    pub open: NaiveTime,
+    #[schema(value_type = String, example = "23:00:00")]
+    pub close: NaiveTime,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MenuSettings {
+    /// comma separated dish types in the order of menu sections, missing types go last
+    #[serde(deserialize_with = "course_order")]
+    pub course_order: Vec<DishType>,
+    /// dishes with lower gross margin (in percents of price) are flagged in costing report
+    pub min_margin_percent: f64,
+    /// for how long clients and proxies may show a menu before revalidating it
+    pub cache_max_age_s: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IdempotencySettings {
+    /// for how long responses to requests with `Idempotency-Key` are replayed
+    pub ttl_s: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HealthSettings {
+    /// limits each dependency probe of the readiness check
+    pub timeout_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GuestSettings {
+    /// signs table tokens of QR codes, changing it invalidates every printed code
+    pub token_secret: String,
+    /// page of the guest app, QR codes link to `{app_url}/{token}`
+    pub app_url: String,
+    /// requests guests of one table may send per minute
+    pub requests_per_minute: u64,
+}
+
+/// Each tip is split between the waiter who served the order and a pool shared by workers
+/// of `pool_roles`, every worker gets the weight of their role
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TipSettings {
+    /// percents of each tip kept by the waiter, the rest is pooled
+    pub waiter_share_percent: u32,
+    /// titles of `worker_role` sharing the pool with their weights, a tip is shared by the
+    /// workers on shift when it was paid
+    pub pool_roles: HashMap<String, u32>,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// the file can't be read, or a value is missing or has a wrong type
+    Load(ConfigError),
+    /// every value is well-formed, but some of them are out of range
+    Invalid(Vec<String>),
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Load(err) => write!(f, "Failed to load settings: {err}"),
+            SettingsError::Invalid(problems) => {
+                write!(f, "Invalid settings:")?;
+
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<ConfigError> for SettingsError {
+    fn from(err: ConfigError) -> Self {
+        SettingsError::Load(err)
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, SettingsError> {
+        // only an explicitly set file must exist
+        let (file, is_file_required) = match env::var("CONFIG_FILE") {
+            Ok(file) => (file, true),
+            Err(_) => (DEFAULT_CONFIG_FILE.to_owned(), false),
+        };
+
+        let legacy_vars = LEGACY_ENV_VARS
+            .iter()
+            .filter_map(|(var, key)| Some((key.to_string(), env::var(var).ok()?)))
+            .collect::<HashMap<_, _>>();
+
+        let config = Self::defaults()?
+            .add_source(File::with_name(&file).required(is_file_required))
+            .add_source(env_source(Environment::default()).source(Some(legacy_vars)))
+            .add_source(env_source(
+                Environment::with_prefix("NNN").prefix_separator("_"),
+            ))
+            .build()?;
+
+        Self::from_config(config)
+    }
+
+    pub(crate) fn defaults() -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+        Config::builder()
+            .set_default("server.address", "127.0.0.1:8080")?
+            .set_default("server.cors_origins", vec!["http://localhost:5173"])?
+            .set_default("server.client_request_timeout_ms", 5_000)?
+            .set_default("server.keep_alive_s", 5)?
+            .set_default("server.shutdown_timeout_s", 30)?
+            .set_default("database.url", "")?
+            .set_default("database.pool_size", 10)?
+            .set_default("database.actor_threads", 5)?
+            .set_default("database.connection_timeout_s", 30)?
+            .set_default("database.migrate_on_startup", false)?
+            .set_default("redis.url", "")?
+            .set_default("redis.timeout_ms", 2_000)?
+            .set_default("restaurant.name", "NomNomNavigator")?
+            .set_default("restaurant.currency", "USD")?
+            .set_default("restaurant.timezone", "UTC")?
+            .set_default("restaurant.business_day_starts_at", "00:00:00")?
+            .set_default("menu.course_order", "")?
+            .set_default("menu.min_margin_percent", 60.0)?
+            .set_default("menu.cache_max_age_s", 10)?
+            .set_default("idempotency.ttl_s", 24 * 60 * 60)?
+            .set_default("health.timeout_ms", 1_000)?
+            .set_default("guest.token_secret", "")?
+            .set_default("guest.app_url", "http://localhost:5173/guest")?
+            .set_default("guest.requests_per_minute", 60)?
+            .set_default("tips.waiter_share_percent", 100)?
+            .set_default("tips.pool_roles", HashMap::<String, u32>::new())
+    }
+
+    pub(crate) fn from_config(config: Config) -> Result<Self, SettingsError> {
+        let mut settings = config.try_deserialize::<Settings>()?;
+
+        for origin in settings.server.cors_origins.iter_mut() {
+            *origin = origin.trim().to_owned();
+        }
+
+        settings.guest.app_url = settings
+            .guest
+            .app_url
+            .trim()
+            .trim_end_matches('/')
+            .to_owned();
+
+        let problems = settings.problems();
+
+        if problems.is_empty() {
+            Ok(settings)
+        } else {
+            Err(SettingsError::Invalid(problems))
+        }
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let mut check = |is_valid: bool, problem: &str| {
+            if !is_valid {
+                problems.push(problem.to_owned());
+            }
+        };
+
+        check(
+            !self.database.url.is_empty(),
+            "database.url must be set, e.g. with PG_DATABASE_URL variable",
+        );
+        check(
+            !self.redis.url.is_empty(),
+            "redis.url must be set, e.g. with REDIS_DATABASE_URI variable",
+        );
+        check(
+            !self.server.cors_origins.is_empty(),
+            "server.cors_origins must list at least one origin",
+        );
+        check(
+            self.database.pool_size > 0,
+            "database.pool_size must be positive",
+        );
+        check(
+            self.database.actor_threads > 0,
+            "database.actor_threads must be positive",
+        );
+        check(
+            self.database.actor_threads <= self.database.pool_size as usize,
+            "database.actor_threads must not exceed database.pool_size",
+        );
+        check(
+            self.database.connection_timeout_s > 0,
+            "database.connection_timeout_s must be positive",
+        );
+        check(
+            self.redis.timeout_ms > 0,
+            "redis.timeout_ms must be positive",
+        );
+        check(
+            !self.restaurant.name.trim().is_empty(),
+            "restaurant.name must not be empty",
+        );
+        check(
+            self.restaurant.currency.len() == 3
+                && self
+                    .restaurant
+                    .currency
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase()),
+            "restaurant.currency must be an ISO 4217 code, e.g. USD",
+        );
+        check(
+            (0.0..100.0).contains(&self.menu.min_margin_percent),
+            "menu.min_margin_percent must be in range 0..100",
+        );
+        check(
+            self.idempotency.ttl_s > 0,
+            "idempotency.ttl_s must be positive",
+        );
+        check(
+            self.health.timeout_ms > 0,
+            "health.timeout_ms must be positive",
+        );
+        check(
+            self.guest.token_secret.len() >= MIN_TOKEN_SECRET_LEN,
+            "guest.token_secret must be at least 32 characters long, \
+            e.g. with NNN_GUEST__TOKEN_SECRET variable",
+        );
+        check(
+            self.guest.app_url.starts_with("http://") || self.guest.app_url.starts_with("https://"),
+            "guest.app_url must be an http(s) URL",
+        );
+        check(
+            self.guest.requests_per_minute > 0,
+            "guest.requests_per_minute must be positive",
+        );
+        check(
+            self.tips.waiter_share_percent <= 100,
+            "tips.waiter_share_percent must be in range 0..=100",
+        );
+        check(
+            self.tips.waiter_share_percent == 100 || !self.tips.pool_roles.is_empty(),
+            "tips.pool_roles must list roles sharing the pool, \
+            unless tips.waiter_share_percent is 100",
+        );
+        check(
+            self.tips.pool_roles.values().all(|weight| *weight > 0),
+            "tips.pool_roles weights must be positive",
+        );
+
+        for (idx, hours) in self.restaurant.opening_hours.iter().enumerate() {
+            if hours.days.is_empty() {
+                problems.push(format!(
+                    "restaurant.opening_hours[{idx}].days must list at least one day"
+                ));
+            }
+            if hours.open == hours.close {
+                problems.push(format!(
+                    "restaurant.opening_hours[{idx}] must not open and close at the same time"
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Environment variables use `__` between nested keys and commas between list items
+fn env_source(env: Environment) -> Environment {
+    env.separator("__")
+        .list_separator(",")
+        .with_list_parse_key("server.cors_origins")
+        .try_parsing(true)
+}
+
+fn course_order<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<DishType>, D::Error> {
+    let input = String::deserialize(deserializer)?;
+
+    DishType::parse_course_order(&input).map_err(serde::de::Error::custom)
+}
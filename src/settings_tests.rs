@@ -0,0 +1,137 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use config::{Config, File, FileFormat};
+
+use crate::settings::{Settings, SettingsError};
+use crate::types::DishType;
+
+/// values without defaults
+const REQUIRED: &str = r#"
+[database]
+url = "postgres://localhost/nnn"
+
+[redis]
+url = "redis://localhost"
+
+[guest]
+token_secret = "0123456789abcdef0123456789abcdef"
+"#;
+
+fn load(toml: &str) -> Result<Settings, SettingsError> {
+    let config = Settings::defaults()
+        .unwrap()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()
+        .unwrap();
+
+    Settings::from_config(config)
+}
+
+#[test]
+fn example_file_is_valid() {
+    let settings = load(include_str!("../config.example.toml")).unwrap();
+
+    assert_eq!(settings.restaurant.opening_hours.len(), 2);
+    assert_eq!(settings.menu.course_order[0], DishType::Appetizer);
+}
+
+#[test]
+fn defaults_cover_everything_but_required_values() {
+    let settings = load(REQUIRED).unwrap();
+
+    assert_eq!(settings.database.pool_size, 10);
+    assert_eq!(settings.server.cors_origins, vec!["http://localhost:5173"]);
+    assert!(settings.restaurant.opening_hours.is_empty());
+
+    let SettingsError::Invalid(problems) = load("").unwrap_err() else {
+        panic!("missing required values must be reported as invalid settings");
+    };
+
+    assert_eq!(problems.len(), 3, "{problems:?}");
+}
+
+#[test]
+fn every_problem_is_reported() {
+    let err = load(&format!(
+        r#"{REQUIRED}
+[restaurant]
+currency = "usd"
+
+[[restaurant.opening_hours]]
+days = []
+open = "10:00"
+close = "10:00"
+"#
+    ))
+    .unwrap_err();
+
+    let SettingsError::Invalid(problems) = err else {
+        panic!("unexpected error: {err}");
+    };
+
+    assert_eq!(problems.len(), 3, "{problems:?}");
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let err = load(&format!("{REQUIRED}\npool_size = 5")).unwrap_err();
+
+    assert!(err.to_string().contains("pool_size"), "{err}");
+}
+
+#[test]
+fn unknown_dish_type_is_rejected() {
+    let err = load(&format!(
+        "{REQUIRED}\n[menu]\ncourse_order = \"main,dessert\""
+    ))
+    .unwrap_err();
+
+    assert!(err.to_string().contains("dessert"), "{err}");
+}
+
+#[test]
+fn late_night_sales_count_for_previous_business_day() {
+    let settings = load(&format!(
+        "{REQUIRED}\n[restaurant]\ntimezone = \"Europe/Berlin\"\nbusiness_day_starts_at = \"04:00\""
+    ))
+    .unwrap();
+    let restaurant = &settings.restaurant;
+    let day = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+
+    // 03:30 and 04:30 in Berlin, summer time
+    let before_cutoff = Utc.with_ymd_and_hms(2026, 10, 19, 1, 30, 0).unwrap();
+    let after_cutoff = Utc.with_ymd_and_hms(2026, 10, 19, 2, 30, 0).unwrap();
+
+    assert_eq!(restaurant.business_day(before_cutoff), day(18));
+    assert_eq!(restaurant.business_day(after_cutoff), day(19));
+
+    // clocks are moved back at 03:00 on the 25th, so the business day before lasts 25 hours
+    let (start, end) = restaurant.business_days_span(day(24), day(24)).unwrap();
+
+    assert_eq!(start, Utc.with_ymd_and_hms(2026, 10, 24, 2, 0, 0).unwrap());
+    assert_eq!(end - start, Duration::hours(25));
+}
+
+#[test]
+fn business_day_skipped_by_clocks_starts_after_the_gap() {
+    let settings = load(&format!(
+        "{REQUIRED}\n[restaurant]\ntimezone = \"Europe/Berlin\"\nbusiness_day_starts_at = \"02:30\""
+    ))
+    .unwrap();
+    let day = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap();
+
+    // 02:30 does not exist that night, the clocks go from 02:00 to 03:00
+    assert_eq!(
+        settings.restaurant.business_day_start(day),
+        Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn unknown_timezone_is_rejected() {
+    let err = load(&format!(
+        "{REQUIRED}\n[restaurant]\ntimezone = \"Mars/Olympus\""
+    ))
+    .unwrap_err();
+
+    assert!(err.to_string().contains("Mars/Olympus"), "{err}");
+}
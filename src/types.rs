@@ -1,28 +1,81 @@
+use std::collections::HashSet;
 use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
 
+use chrono::{DateTime, NaiveDate, Utc};
 use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::pg::Pg;
 use diesel::query_builder::QueryId;
-use diesel::serialize::{Output, ToSql};
+use diesel::serialize::{IsNull, Output, ToSql};
 use diesel::sql_types::Text;
 use diesel::{AsExpression, FromSqlRow, SqlType};
 use serde::ser::StdError;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::services::db_models::{Dish, Order};
+use crate::services::db_models::{Dish, Modifier, ModifierGroup, Order, Shift, Waiter};
 
 // Constants
 
 pub const ACTIVE_MENU_KEY: &str = "active-menu";
+/// unix time the active menu was set at, see [`CachedJson::activated_at`]
+pub const ACTIVE_MENU_SINCE_KEY: &str = "active-menu-since";
+/// `menu_{date}` is a hash of the menu JSON under [`MENU_FIELD`] and JSON of each of its dishes
+/// under `dish-{id}`, so the whole menu is deleted along with the key
 pub const MENU_KEY: &str = "menu";
+pub const MENU_FIELD: &str = "menu";
+/// fields of the menu hash identifying its revision, see [`MenuRevision`]
+pub const VERSION_FIELD: &str = "version";
+pub const MODIFIED_AT_FIELD: &str = "modified_at";
+/// set of every `menu_{date}` key, so that menus are found without scanning the keyspace
+pub const MENU_INDEX_KEY: &str = "menus";
+pub const MENU_VERSION_KEY: &str = "menu-version";
+pub const IDEMPOTENCY_KEY: &str = "idempotency";
+/// `rate-limit_{scope}_{window}` counts requests of one client in one fixed window
+pub const RATE_LIMIT_KEY: &str = "rate-limit";
+
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+pub const IDEMPOTENT_REPLAY_HEADER: &str = "Idempotent-Replayed";
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+pub const MAX_DISH_NAME_LEN: usize = 255;
+pub const MAX_COOK_TIME_S: i32 = 4 * 60 * 60;
+pub const MAX_PORTION_WEIGHT_G: i32 = 10_000;
+pub const MAX_LINE_NOTE_LEN: usize = 200;
+pub const MAX_LINE_COUNT: i32 = 100;
+
+pub const DEFAULT_COURSE_ORDER: [DishType; 7] = [
+    DishType::Appetizer,
+    DishType::Salad,
+    DishType::Cold,
+    DishType::Main,
+    DishType::Garnish,
+    DishType::Drink,
+    DishType::Alcohol,
+];
 
 // actual User Defined Types
 
 #[derive(Debug)]
 pub struct PoolInitializationError(pub String);
 
-#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug)]
+pub struct MigrationError(pub String);
+
+#[derive(
+    FromSqlRow,
+    AsExpression,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+)]
 #[diesel(sql_type = Text)]
 pub enum DishType {
     Main,
@@ -34,38 +87,474 @@ pub enum DishType {
     Alcohol,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RedisDish {
     pub dish: Dish,
     pub ingredients: Vec<(String, i32)>,
+    /// union of allergens of all the ingredients
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+    #[serde(default)]
+    pub modifier_groups: Vec<ModifierGroupInfo>,
+}
+
+/// Major food allergens a product may contain
+#[derive(
+    FromSqlRow,
+    AsExpression,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum Allergen {
+    Gluten,
+    Shellfish,
+    Molluscs,
+    Eggs,
+    Fish,
+    Peanuts,
+    Nuts,
+    Soy,
+    Dairy,
+    Celery,
+    Mustard,
+    Sesame,
+    Sulphites,
+    Lupin,
+}
+
+#[derive(
+    FromSqlRow, AsExpression, Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum DietaryLabel {
+    Vegan,
+    Vegetarian,
+    Halal,
 }
 
-#[derive(Deserialize)]
+/// One course of the menu, e.g. all the salads, in the order they are served
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MenuSection {
+    pub course: DishType,
+    pub dishes: Vec<RedisDish>,
+}
+
+/// Menu as it is stored in redis and returned to the clients.
+/// `version` grows every time a menu for the same date is composed again,
+/// so clients are able to detect their stale copies
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Menu {
+    pub title: String,
+    pub valid_on: NaiveDate,
+    pub version: i64,
+    pub sections: Vec<MenuSection>,
+}
+
+/// Single problem found in a request body, `field` is a path like "ingredients[1].used_g"
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct Ingredient {
     pub id: i64,
     pub used_g: i32,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DishCosting {
+    pub dish_id: i64,
+    pub name: String,
+    pub price: i32,
+    pub food_cost: i32,
+    pub food_cost_pct: f64,
+    pub gross_margin: i32,
+    pub margin_pct: f64,
+    /// margin percentage is lower than the configured floor
+    pub below_margin_floor: bool,
+}
+
+/// Change of a product weight caused by a modifier, negative removes the product
+#[derive(Deserialize, ToSchema)]
+pub struct IngredientDelta {
+    pub id: i64,
+    pub delta_g: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ModifierSpec {
+    pub name: String,
+    #[serde(default)]
+    pub price_delta: i32,
+    #[serde(default)]
+    pub ingredients: Vec<IngredientDelta>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModifierGroupInfo {
+    pub group: ModifierGroup,
+    pub modifiers: Vec<Modifier>,
+}
+
+/// Desired state of one line of an order
+#[derive(Deserialize, ToSchema)]
+pub struct LineSpec {
+    pub dish_id: i64,
+    pub count: i32,
+    #[serde(default)]
+    pub modifiers: Vec<i64>,
+    pub note: Option<String>,
+}
+
+/// Change of the count of one line of an order
+#[derive(Deserialize, ToSchema)]
+pub struct LineDelta {
+    pub dish_id: i64,
+    pub delta: i32,
+    #[serde(default)]
+    pub modifiers: Vec<i64>,
+    pub note: Option<String>,
+}
+
+/// Either the full desired set of lines, or changes of their counts.
+/// Sent as `{"replace": [...]}` or `{"deltas": [...]}`
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchEdit {
+    Replace(Vec<LineSpec>),
+    Deltas(Vec<LineDelta>),
+}
+
+/// Points to lines of an order either by the dish or by the line itself
+pub enum OrderLineRef {
+    Dish(i64),
+    Line(i64),
+}
+
+/// Line of an order. Lines of the same dish are kept apart when modifiers or notes differ
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct DishWithCount {
+    pub line_id: i64,
     pub dish: Dish,
     pub count: i32,
+    pub modifiers: Vec<Modifier>,
+    pub note: Option<String>,
+    /// price of the dish with all the modifier deltas applied
+    pub unit_price: i32,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct OrderInfo {
     pub order: Order,
     pub dishes: Vec<DishWithCount>,
 }
 
+/// Someone clocked in, a waiter without a staff record has no `worker`, other staff no `waiter`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OnShiftStaff {
+    pub shift: Shift,
+    pub waiter: Option<Waiter>,
+    pub worker: Option<StaffMember>,
+}
+
+/// Handed over on clock-out, a worker who is not a waiter has no orders or tables
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShiftSummary {
+    pub shift: Shift,
+    /// orders of the waiter paid during the shift
+    pub orders_served: i64,
+    /// total cost of the served orders
+    pub revenue: i64,
+    /// unpaid orders of the waiter, they go to the next waiter of their table
+    pub open_orders: Vec<Order>,
+    /// tables the waiter was assigned to, they are left without a waiter
+    pub released_table_ids: Vec<i64>,
+}
+
+/// Tips paid for orders of the waiter
+#[derive(Debug)]
+pub struct WaiterTips {
+    pub waiter: Waiter,
+    pub amount: i64,
+}
+
+/// Active worker with the title of their role
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StaffMember {
+    pub worker_id: i32,
+    pub name: String,
+    pub role: String,
+}
+
+/// Tips paid while the same staff was on shift, their pool is shared by that staff only
+#[derive(Debug)]
+pub struct TipPayoutInputs {
+    pub waiter_tips: Vec<WaiterTips>,
+    /// tips of orders whose table had no waiter, they go to the pool entirely
+    pub unattributed: i64,
+    /// workers of the pooled roles on shift when the tips were paid
+    pub pool_staff: Vec<StaffMember>,
+}
+
+/// Tips owed to one person, a worker or a waiter without a staff record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TipPayout {
+    pub worker_id: Option<i32>,
+    pub waiter_id: Option<i64>,
+    pub name: String,
+    pub role: Option<String>,
+    /// kept from tips of orders the waiter served
+    pub direct: i64,
+    /// share of the pool
+    pub pooled: i64,
+    pub total: i64,
+}
+
+/// Tips of the period by payee, they are not included into the restaurant income
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TipPayoutReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_tips: i64,
+    pub pooled_tips: i64,
+    /// pooled tips nobody is eligible for, e.g. no worker has a pooled role
+    pub undistributed: i64,
+    pub payouts: Vec<TipPayout>,
+}
+
+/// Token identifying the table in guest routes, its QR code on the table links to `url`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TableToken {
+    pub table_id: i64,
+    #[schema(example = "12.x0lDfVjE8tUPqG2hrMN_3A")]
+    pub token: String,
+    /// page of the guest app for the table
+    #[schema(example = "https://order.example.com/guest/12.x0lDfVjE8tUPqG2hrMN_3A")]
+    pub url: String,
+}
+
+/// State of a cached menu, changes along with the menu or any of its dishes
+#[derive(Debug, Clone)]
+pub struct MenuRevision {
+    pub menu_key: String,
+    pub version: i64,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Menu or dish JSON as it is cached. `revision` is missing for menus cached before
+/// revisions were stored, they are served without validators
+#[derive(Debug, Clone)]
+pub struct CachedJson {
+    pub json: String,
+    pub revision: Option<MenuRevision>,
+}
+
+impl CachedJson {
+    /// Activating another menu changes what the active menu routes return, so their copy is
+    /// not modified before the activation. The time is missing for menus activated before
+    /// it was stored
+    pub fn activated_at(mut self, activated_at: Option<DateTime<Utc>>) -> Self {
+        if let (Some(revision), Some(activated_at)) = (&mut self.revision, activated_at) {
+            revision.modified_at = revision.modified_at.max(activated_at);
+        }
+
+        self
+    }
+}
+
+/// Problems of cached menus, empty lists mean the cache is consistent
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct MenuCacheReport {
+    /// `menu_{date}_dish-{id}` keys left from the layout before menus became hashes
+    pub orphaned_dish_keys: Vec<String>,
+    /// indexed menus that don't exist anymore
+    pub missing_menus: Vec<String>,
+    /// menus that are not indexed, so dish updates don't reach them
+    pub unindexed_menus: Vec<String>,
+    /// the active menu, when it points to a menu that does not exist
+    pub dangling_active_menu: Option<String>,
+}
+
+impl MenuCacheReport {
+    /// `keys` are all the keys starting with `menu_`, `indexed` are members of the menu index
+    pub fn new(keys: &[String], indexed: &[String], active: Option<String>) -> Self {
+        let (orphaned_dish_keys, menus): (Vec<String>, Vec<String>) =
+            keys.iter().cloned().partition(|key| key.contains("_dish-"));
+
+        let mut report = MenuCacheReport {
+            orphaned_dish_keys,
+            missing_menus: indexed
+                .iter()
+                .filter(|key| !menus.contains(key))
+                .cloned()
+                .collect(),
+            unindexed_menus: menus
+                .iter()
+                .filter(|key| !indexed.contains(key))
+                .cloned()
+                .collect(),
+            dangling_active_menu: active.filter(|key| !menus.contains(key)),
+        };
+
+        report.orphaned_dish_keys.sort();
+        report.missing_menus.sort();
+        report.unindexed_menus.sort();
+
+        report
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        *self == MenuCacheReport::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Connections of the pool, `saturation` is the share of `max_size` in use.
+/// At 1.0 requests wait for a connection to be returned
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolStatus {
+    pub max_size: u32,
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub saturation: f64,
+}
+
+/// Result of probing a single dependency, `latency_ms` includes waiting for a connection
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DependencyHealth {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// present for postgres only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<PoolStatus>,
+}
+
+/// `status` is up only when every dependency is up
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub postgres: DependencyHealth,
+    pub redis: DependencyHealth,
+}
+
+/// State of a request made with `Idempotency-Key` header. `fingerprint` is a hash of the
+/// method, path and body the key was used with, so that the same key can't be reused for
+/// another action
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum IdempotencyRecord {
+    Pending {
+        fingerprint: String,
+    },
+    Completed {
+        fingerprint: String,
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    },
+}
+
+impl IdempotencyRecord {
+    pub fn fingerprint(&self) -> &str {
+        match self {
+            IdempotencyRecord::Pending { fingerprint } => fingerprint,
+            IdempotencyRecord::Completed { fingerprint, .. } => fingerprint,
+        }
+    }
+}
+
 // additional code for types
 
+impl DishCosting {
+    pub fn new(dish: &Dish, food_cost: i32, min_margin_pct: f64) -> Self {
+        let gross_margin = dish.price - food_cost;
+
+        let (food_cost_pct, margin_pct) = if dish.price > 0 {
+            let price = dish.price as f64;
+            (
+                round_pct(food_cost as f64 * 100.0 / price),
+                round_pct(gross_margin as f64 * 100.0 / price),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        Self {
+            dish_id: dish.id,
+            name: dish.name.clone(),
+            price: dish.price,
+            food_cost,
+            food_cost_pct,
+            gross_margin,
+            margin_pct,
+            below_margin_floor: margin_pct < min_margin_pct,
+        }
+    }
+}
+
+fn round_pct(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+impl Menu {
+    pub fn dish_ids(&self) -> HashSet<i64> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.dishes.iter().map(|redis_dish| redis_dish.dish.id))
+            .collect()
+    }
+
+    /// Drops dishes containing any of the allergens, and sections left empty
+    pub fn without_allergens(mut self, excluded: &[Allergen]) -> Self {
+        for section in self.sections.iter_mut() {
+            section.dishes.retain(|redis_dish| {
+                !redis_dish
+                    .allergens
+                    .iter()
+                    .any(|allergen| excluded.contains(allergen))
+            });
+        }
+
+        self.sections.retain(|section| !section.dishes.is_empty());
+
+        self
+    }
+}
+
 impl Display for PoolInitializationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.pad(&self.0)
     }
 }
 
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UnknownDishType(String);
 
@@ -110,17 +599,44 @@ impl FromSql<Text, Pg> for DishType {
     }
 }
 
+impl Display for DishType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            DishType::Main => "main",
+            DishType::Appetizer => "appetizer",
+            DishType::Garnish => "garnish",
+            DishType::Cold => "cold",
+            DishType::Salad => "salad",
+            DishType::Drink => "drink",
+            DishType::Alcohol => "alcohol",
+        };
+
+        f.pad(value)
+    }
+}
+
 impl DishType {
-    pub fn to_string(self) -> String {
-        match self {
-            DishType::Main => String::from("main"),
-            DishType::Appetizer => String::from("appetizer"),
-            DishType::Garnish => String::from("garnish"),
-            DishType::Cold => String::from("cold"),
-            DishType::Salad => String::from("salad"),
-            DishType::Drink => String::from("drink"),
-            DishType::Alcohol => String::from("alcohol"),
+    /// Parses comma-separated list of dish types (e.g. "appetizer,main,drink").
+    /// Types that are not mentioned are appended in their default order,
+    /// so that no dish gets lost from the menu
+    pub fn parse_course_order(input: &str) -> Result<Vec<Self>, String> {
+        let mut order = vec![];
+
+        for course in input.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let dish_type = DishType::from_string(course)?;
+
+            if !order.contains(&dish_type) {
+                order.push(dish_type);
+            }
+        }
+
+        for dish_type in DEFAULT_COURSE_ORDER {
+            if !order.contains(&dish_type) {
+                order.push(dish_type);
+            }
         }
+
+        Ok(order)
     }
 
     pub fn from_string(input: &str) -> Result<Self, String> {
@@ -136,3 +652,113 @@ impl DishType {
         }
     }
 }
+
+impl Display for Allergen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            Allergen::Gluten => "gluten",
+            Allergen::Shellfish => "shellfish",
+            Allergen::Molluscs => "molluscs",
+            Allergen::Eggs => "eggs",
+            Allergen::Fish => "fish",
+            Allergen::Peanuts => "peanuts",
+            Allergen::Nuts => "nuts",
+            Allergen::Soy => "soy",
+            Allergen::Dairy => "dairy",
+            Allergen::Celery => "celery",
+            Allergen::Mustard => "mustard",
+            Allergen::Sesame => "sesame",
+            Allergen::Sulphites => "sulphites",
+            Allergen::Lupin => "lupin",
+        };
+
+        f.pad(value)
+    }
+}
+
+impl Allergen {
+    pub fn from_string(input: &str) -> Result<Self, String> {
+        match input {
+            "gluten" => Ok(Allergen::Gluten),
+            "shellfish" => Ok(Allergen::Shellfish),
+            "molluscs" => Ok(Allergen::Molluscs),
+            "eggs" => Ok(Allergen::Eggs),
+            "fish" => Ok(Allergen::Fish),
+            "peanuts" => Ok(Allergen::Peanuts),
+            "nuts" => Ok(Allergen::Nuts),
+            "soy" => Ok(Allergen::Soy),
+            "dairy" => Ok(Allergen::Dairy),
+            "celery" => Ok(Allergen::Celery),
+            "mustard" => Ok(Allergen::Mustard),
+            "sesame" => Ok(Allergen::Sesame),
+            "sulphites" => Ok(Allergen::Sulphites),
+            "lupin" => Ok(Allergen::Lupin),
+            _ => Err(format!("Couldn't recognize allergen: {}", input)),
+        }
+    }
+
+    /// Parses comma-separated list like "nuts,dairy"
+    pub fn parse_list(input: &str) -> Result<Vec<Self>, String> {
+        input
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Allergen::from_string)
+            .collect()
+    }
+}
+
+impl ToSql<Text, Pg> for Allergen {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> diesel::serialize::Result {
+        out.write_all(self.to_string().as_bytes())?;
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for Allergen {
+    fn from_sql(bytes: <Pg as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        Ok(Allergen::from_string(&String::from_utf8_lossy(
+            bytes.as_bytes(),
+        ))?)
+    }
+}
+
+impl Display for DietaryLabel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            DietaryLabel::Vegan => "vegan",
+            DietaryLabel::Vegetarian => "vegetarian",
+            DietaryLabel::Halal => "halal",
+        };
+
+        f.pad(value)
+    }
+}
+
+impl DietaryLabel {
+    pub fn from_string(input: &str) -> Result<Self, String> {
+        match input {
+            "vegan" => Ok(DietaryLabel::Vegan),
+            "vegetarian" => Ok(DietaryLabel::Vegetarian),
+            "halal" => Ok(DietaryLabel::Halal),
+            _ => Err(format!("Couldn't recognize dietary label: {}", input)),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for DietaryLabel {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> diesel::serialize::Result {
+        out.write_all(self.to_string().as_bytes())?;
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for DietaryLabel {
+    fn from_sql(bytes: <Pg as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        Ok(DietaryLabel::from_string(&String::from_utf8_lossy(
+            bytes.as_bytes(),
+        ))?)
+    }
+}